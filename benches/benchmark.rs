@@ -2,7 +2,7 @@ use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use rtools::{
     get_file_info, analyze_text_file, scan_directory,
     search_files, SearchCriteria, analyze_log_file,
-    ConfigManager, ConfigValue
+    ConfigManager
 };
 use std::fs;
 
@@ -3,8 +3,8 @@
 //! 这个示例展示了如何使用rtools库的所有功能
 
 use rtools::{
-    get_file_info, analyze_text_file, scan_directory,
-    search_files, SearchCriteria, analyze_log_file,
+    scan_directory,
+    search_files, search_iter, SearchCriteria, analyze_log_file,
     ConfigManager, ConfigValue
 };
 
@@ -26,6 +26,20 @@ fn main() {
         }
         Err(e) => println!("搜索错误: {}", e),
     }
+    
+    // 流式搜索: 找到前两个匹配就停止，并报告进度
+    let criteria = SearchCriteria::new().with_extension("rs".to_string());
+    match search_iter(".", criteria) {
+        Ok(iter) => {
+            let iter = iter.with_progress(|progress| {
+                println!("  正在搜索: {} (已访问 {} 个目录)", progress.current_dir.display(), progress.dirs_visited);
+            });
+            for found in iter.take(2).flatten() {
+                println!("  找到: {} ({} 字节)", found.path.display(), found.details.size);
+            }
+        }
+        Err(e) => println!("搜索错误: {}", e),
+    }
     println!();
     
     // 示例2: 日志分析
@@ -2,7 +2,7 @@
 //! 
 //! 这个示例展示了如何使用rtools库的各种功能
 
-use rtools::{get_file_info, analyze_text_file, scan_directory, TextStats};
+use rtools::{get_file_info, scan_directory, TextStats};
 
 fn main() {
     println!("=== rtools 库使用示例 ===\n");
@@ -0,0 +1,396 @@
+use std::collections::BTreeMap;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use crate::loganalyzer::{LogEntry, LogLevel};
+use crate::logparser::LogParser;
+use crate::sketch::{DEFAULT_TOP_K, DistinctCounter, Reservoir, TopK};
+use crate::{RtoolsResult, RtoolsError};
+
+/// Apache/Nginx 的通用日志格式 (Common Log Format)
+pub const COMMON_LOG_FORMAT: &str = r#"$remote_addr - $remote_user [$time_local] "$request" $status $body_bytes_sent"#;
+/// Apache/Nginx 的组合日志格式 (Combined Log Format)，也是 nginx 默认的 `log_format combined`
+pub const COMBINED_LOG_FORMAT: &str =
+    r#"$remote_addr - $remote_user [$time_local] "$request" $status $body_bytes_sent "$http_referer" "$http_user_agent""#;
+
+/// 按 nginx `log_format` 定义解析访问日志
+///
+/// 格式中的每个 `$变量`（或 `${变量}`）匹配到下一个字面字符为止（格式末尾的变量匹配到空白为止），
+/// 值保存在同名字段中，nginx 用 `-` 表示的空值不保存。
+/// `$request` 会拆分为 `request_method`、`request_uri` 和 `server_protocol`，
+/// 级别由状态码决定: 5xx 为错误，4xx 为警告，其余为信息。
+#[derive(Debug, Clone)]
+pub struct AccessLogParser {
+    name: String,
+    format: String,
+    regex: Regex,
+    variables: Vec<String>,
+}
+
+impl AccessLogParser {
+    /// 从 nginx `log_format` 字符串创建解析器，格式中至少要有一个变量
+    pub fn new(name: &str, format: &str) -> RtoolsResult<Self> {
+        let mut pattern = String::from("^");
+        let mut variables = Vec::new();
+        let mut rest = format;
+
+        while let Some(start) = rest.find('$') {
+            pattern.push_str(&regex::escape(&rest[..start]));
+            let after = &rest[start + 1..];
+            let (variable, remaining) = match after.strip_prefix('{') {
+                Some(braced) => {
+                    let end = braced.find('}').ok_or_else(|| {
+                        RtoolsError::ParseError(format!("日志格式位置 {}: 缺少 '}}'", format.len() - rest.len() + start + 1))
+                    })?;
+                    (&braced[..end], &braced[end + 1..])
+                }
+                None => {
+                    let end = after
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            if variable.is_empty() {
+                return Err(RtoolsError::ParseError(format!(
+                    "日志格式位置 {}: '$' 后缺少变量名", format.len() - rest.len() + start + 1
+                )));
+            }
+
+            // 变量的值延伸到下一个字面字符，格式末尾的变量不包含空白
+            match remaining.chars().next() {
+                Some(next) => pattern.push_str(&format!("([^{}]*)", regex::escape(&next.to_string()))),
+                None => pattern.push_str(r"(\S*)"),
+            }
+            variables.push(variable.to_string());
+            rest = remaining;
+        }
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+
+        if variables.is_empty() {
+            return Err(RtoolsError::ParseError(format!("日志格式 '{}' 中没有变量", format)));
+        }
+
+        let regex = Regex::new(&pattern)
+            .map_err(|e| RtoolsError::ParseError(format!("日志格式 '{}': {}", format, e)))?;
+        Ok(Self {
+            name: name.to_string(),
+            format: format.to_string(),
+            regex,
+            variables,
+        })
+    }
+
+    /// 通用日志格式，名称为 `common`
+    pub fn common() -> Self {
+        Self::new("common", COMMON_LOG_FORMAT).expect("内置格式有效")
+    }
+
+    /// 组合日志格式，名称为 `combined`
+    pub fn combined() -> Self {
+        Self::new("combined", COMBINED_LOG_FORMAT).expect("内置格式有效")
+    }
+
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+}
+
+impl LogParser for AccessLogParser {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.format
+    }
+
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let captures = self.regex.captures(line.trim_end())?;
+
+        let mut fields: BTreeMap<String, String> = BTreeMap::new();
+        for (i, variable) in self.variables.iter().enumerate() {
+            let value = captures.get(i + 1).map_or("", |m| m.as_str());
+            if !value.is_empty() && value != "-" {
+                fields.insert(variable.clone(), value.to_string());
+            }
+        }
+
+        if let Some(request) = fields.get("request").cloned() {
+            let mut parts = request.splitn(3, ' ');
+            for name in ["request_method", "request_uri", "server_protocol"] {
+                if let Some(part) = parts.next() {
+                    fields.entry(name.to_string()).or_insert_with(|| part.to_string());
+                }
+            }
+        }
+
+        let timestamp = if let Some(time) = fields.get("time_local") {
+            DateTime::parse_from_str(time, "%d/%b/%Y:%H:%M:%S %z").ok().map(|t| t.with_timezone(&Utc))
+        } else {
+            fields
+                .get("time_iso8601")
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map(|t| t.with_timezone(&Utc))
+        };
+
+        let level = match fields.get("status").and_then(|s| s.parse::<u16>().ok()) {
+            Some(500..) => LogLevel::Error,
+            Some(400..=499) => LogLevel::Warning,
+            Some(_) => LogLevel::Info,
+            None => LogLevel::Unknown(String::new()),
+        };
+        let message = fields.get("request").cloned().unwrap_or_default();
+
+        let mut entry = LogEntry::new(timestamp, level, message);
+        entry.source = fields.get("remote_addr").cloned();
+        entry.fields = fields;
+        Some(entry)
+    }
+}
+
+/// 请求耗时的百分位数，单位毫秒
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyStats {
+    /// 有耗时的请求数；请求很多时百分位数按抽样的样本计算
+    pub samples: usize,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl LatencyStats {
+    /// 按最近秩法计算百分位数，没有样本时返回 `None`
+    pub fn from_samples(mut samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(f64::total_cmp);
+
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0) * samples.len() as f64).ceil() as usize;
+            samples[rank.clamp(1, samples.len()) - 1]
+        };
+        Some(Self {
+            samples: samples.len(),
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: samples[samples.len() - 1],
+        })
+    }
+}
+
+/// 访问日志统计
+#[derive(Debug, Clone, Default)]
+pub struct AccessStats {
+    pub total_requests: usize,
+    /// 按状态码类别 (`2xx`、`4xx` 等) 统计的请求数
+    pub status_classes: BTreeMap<String, usize>,
+    /// 以下列表按请求数从多到少排序
+    pub top_paths: Vec<(String, usize)>,
+    pub top_clients: Vec<(String, usize)>,
+    pub top_user_agents: Vec<(String, usize)>,
+    /// 不同客户端的个数（估计值）
+    pub distinct_clients: usize,
+    pub bytes_served: u64,
+    /// 每分钟的请求数，键为 `YYYY-MM-DD HH:MM`
+    pub requests_per_minute: BTreeMap<String, usize>,
+    /// 格式中有 `$request_time` 时的耗时统计
+    pub latency: Option<LatencyStats>,
+}
+
+impl AccessStats {
+    /// 统计带有 `status` 字段的条目，没有这样的条目时返回 `None`
+    pub fn from_entries(entries: &[LogEntry]) -> Option<Self> {
+        let mut collector = AccessCollector::new();
+        for entry in entries {
+            collector.add(entry);
+        }
+        collector.stats()
+    }
+
+    /// 请求最多的一分钟及其请求数
+    pub fn peak_minute(&self) -> Option<(&str, usize)> {
+        self.requests_per_minute
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(minute, count)| (minute.as_str(), *count))
+    }
+
+    pub fn print_stats(&self) {
+        println!("\n访问统计:");
+        println!("- 请求数: {}", self.total_requests);
+        println!("- 传输字节数: {}", self.bytes_served);
+        println!("- 客户端数: {}", self.distinct_clients);
+
+        let status: Vec<String> = self
+            .status_classes
+            .iter()
+            .map(|(class, count)| format!("{} {}", class, count))
+            .collect();
+        println!("- 状态码: {}", status.join(", "));
+
+        if let Some((minute, count)) = self.peak_minute() {
+            let average = self.requests_per_minute.values().sum::<usize>() as f64 / self.requests_per_minute.len() as f64;
+            println!("- 每分钟请求数: 平均 {:.1}, 峰值 {} ({})", average, count, minute);
+        }
+        if let Some(latency) = &self.latency {
+            println!("- 耗时 (毫秒): p50 {:.1}, p90 {:.1}, p95 {:.1}, p99 {:.1}, 最大 {:.1}",
+                     latency.p50, latency.p90, latency.p95, latency.p99, latency.max);
+        }
+
+        print_top("访问最多的路径", &self.top_paths);
+        print_top("请求最多的客户端", &self.top_clients);
+        print_top("最常见的User-Agent", &self.top_user_agents);
+    }
+}
+
+/// 最多保留的耗时样本数，超过后用蓄水池抽样
+const LATENCY_SAMPLES: usize = 10_000;
+
+/// 逐条累计访问统计
+///
+/// 路径、客户端和 User-Agent 只保留请求最多的 `top_k` 个，耗时只保留固定数量的样本，
+/// 所以内存占用不随日志大小增长。
+#[derive(Debug, Clone)]
+pub struct AccessCollector {
+    stats: AccessStats,
+    paths: TopK,
+    clients: TopK,
+    user_agents: TopK,
+    distinct_clients: DistinctCounter,
+    latencies: Reservoir,
+    max_latency: f64,
+}
+
+impl Default for AccessCollector {
+    fn default() -> Self {
+        Self::with_top_k(DEFAULT_TOP_K)
+    }
+}
+
+impl AccessCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_top_k(top_k: usize) -> Self {
+        Self {
+            stats: AccessStats::default(),
+            paths: TopK::new(top_k),
+            clients: TopK::new(top_k),
+            user_agents: TopK::new(top_k),
+            distinct_clients: DistinctCounter::new(),
+            latencies: Reservoir::new(LATENCY_SAMPLES),
+            max_latency: 0.0,
+        }
+    }
+
+    /// 累计一个条目，没有 `status` 字段的条目被忽略
+    pub fn add(&mut self, entry: &LogEntry) {
+        let Some(status) = entry.fields.get("status") else {
+            return;
+        };
+        let stats = &mut self.stats;
+        stats.total_requests += 1;
+
+        let class = match status.chars().next() {
+            Some(c) if c.is_ascii_digit() => format!("{}xx", c),
+            _ => status.clone(),
+        };
+        *stats.status_classes.entry(class).or_insert(0) += 1;
+
+        if let Some(uri) = entry.fields.get("request_uri") {
+            let path = uri.split_once('?').map_or(uri.as_str(), |(path, _)| path);
+            self.paths.add(path);
+        }
+        if let Some(client) = entry.fields.get("remote_addr") {
+            self.clients.add(client);
+            self.distinct_clients.add(client.as_str());
+        }
+        if let Some(agent) = entry.fields.get("http_user_agent") {
+            self.user_agents.add(agent);
+        }
+
+        let bytes = entry.fields.get("body_bytes_sent").or_else(|| entry.fields.get("bytes_sent"));
+        if let Some(bytes) = bytes.and_then(|b| b.parse::<u64>().ok()) {
+            stats.bytes_served += bytes;
+        }
+        if let Some(ts) = entry.timestamp {
+            *stats.requests_per_minute.entry(ts.format("%Y-%m-%d %H:%M").to_string()).or_insert(0) += 1;
+        }
+        // nginx 的 $request_time 以秒为单位
+        if let Some(seconds) = entry.fields.get("request_time").and_then(|t| t.parse::<f64>().ok()) {
+            let millis = seconds * 1000.0;
+            self.latencies.add(millis);
+            self.max_latency = self.max_latency.max(millis);
+        }
+    }
+
+    /// 合并另一个统计，用于并行处理后汇总
+    pub fn merge(&mut self, other: &AccessCollector) {
+        let stats = &mut self.stats;
+        stats.total_requests += other.stats.total_requests;
+        stats.bytes_served += other.stats.bytes_served;
+        for (class, count) in &other.stats.status_classes {
+            *stats.status_classes.entry(class.clone()).or_insert(0) += count;
+        }
+        for (minute, count) in &other.stats.requests_per_minute {
+            *stats.requests_per_minute.entry(minute.clone()).or_insert(0) += count;
+        }
+
+        self.paths.merge(&other.paths);
+        self.clients.merge(&other.clients);
+        self.user_agents.merge(&other.user_agents);
+        self.distinct_clients.merge(&other.distinct_clients);
+        self.latencies.merge(&other.latencies);
+        self.max_latency = self.max_latency.max(other.max_latency);
+    }
+
+    /// 到目前为止的统计，还没有带状态码的条目时返回 `None`
+    pub fn stats(&self) -> Option<AccessStats> {
+        if self.stats.total_requests == 0 {
+            return None;
+        }
+
+        let mut stats = self.stats.clone();
+        stats.top_paths = top_counts(&self.paths);
+        stats.top_clients = top_counts(&self.clients);
+        stats.top_user_agents = top_counts(&self.user_agents);
+        stats.distinct_clients = self.distinct_clients.estimate();
+        // 样本只是抽样，样本数和最大值按全部请求计算
+        stats.latency = LatencyStats::from_samples(self.latencies.samples().to_vec()).map(|latency| LatencyStats {
+            samples: self.latencies.seen() as usize,
+            max: self.max_latency,
+            ..latency
+        });
+        Some(stats)
+    }
+}
+
+fn top_counts(counts: &TopK) -> Vec<(String, usize)> {
+    counts
+        .top(counts.len())
+        .into_iter()
+        .map(|(key, count)| (key, count as usize))
+        .collect()
+}
+
+fn print_top(title: &str, items: &[(String, usize)]) {
+    if items.is_empty() {
+        return;
+    }
+
+    println!("\n{}:", title);
+    for (i, (key, count)) in items.iter().enumerate().take(10) {
+        println!("  {}. {}: {}次", i + 1, key, count);
+    }
+}
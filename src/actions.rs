@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::filesearch::SearchResult;
+use crate::{RtoolsResult, RtoolsError};
+
+/// 命令中代表文件路径的占位符
+pub const PATH_PLACEHOLDER: &str = "{}";
+
+/// 批量执行时单条命令行中路径参数的总长度上限，避免超出系统的参数长度限制
+const MAX_BATCH_BYTES: usize = 128 * 1024;
+
+/// 对搜索结果执行的动作
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchAction {
+    /// 对每个文件执行命令 (`--exec cmd {} ;`)，`batch` 为真时一次传入多个文件 (`--exec cmd {} +`)
+    Exec { command: Vec<String>, batch: bool },
+    /// 删除文件和空目录
+    Delete,
+    /// 复制到目标目录，保留相对于搜索目录的结构
+    Copy { target: PathBuf },
+    /// 移动到目标目录，保留相对于搜索目录的结构
+    Move { target: PathBuf },
+    /// 输出以NUL分隔的路径
+    Print0,
+}
+
+impl SearchAction {
+    /// 创建执行命令的动作，命令中没有 `{}` 时把路径追加到末尾
+    pub fn exec(command: Vec<String>, batch: bool) -> RtoolsResult<Self> {
+        if command.is_empty() {
+            return Err(RtoolsError::InvalidArgument("--exec 需要指定命令".to_string()));
+        }
+        Ok(Self::Exec { command, batch })
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Exec { .. } => "执行",
+            Self::Delete => "删除",
+            Self::Copy { .. } => "复制",
+            Self::Move { .. } => "移动",
+            Self::Print0 => "输出",
+        }
+    }
+
+    /// 执行动作，`dry_run` 为真时只列出将要处理的文件和将要执行的命令行（对输出无效）
+    pub fn run(&self, result: &SearchResult, dry_run: bool) -> RtoolsResult<ActionReport> {
+        let mut report = ActionReport {
+            action: self.description(),
+            dry_run,
+            ..ActionReport::default()
+        };
+
+        match self {
+            Self::Exec { command, batch } => {
+                let chunks: Vec<&[PathBuf]> = if *batch {
+                    batches(&result.files)
+                } else {
+                    result.files.chunks(1).collect()
+                };
+                for chunk in chunks {
+                    let outcome = if dry_run {
+                        report.commands.push(command_line(command, chunk));
+                        Ok(())
+                    } else {
+                        run_command(command, chunk)
+                    };
+                    report.record(chunk.to_vec(), outcome);
+                }
+            }
+            Self::Delete => {
+                // 先删除深层的条目，使目录在其内容被删除后变为空目录
+                let mut paths: Vec<&PathBuf> = result.files.iter().collect();
+                paths.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
+                for path in paths {
+                    let outcome = if dry_run { Ok(()) } else { delete_path(path) };
+                    report.record(vec![path.clone()], outcome);
+                }
+            }
+            Self::Copy { target } | Self::Move { target } => {
+                let is_move = matches!(self, Self::Move { .. });
+                // 目录被移动时其中的文件随之移动，跳过结果中位于其他结果目录中的条目；
+                // 结果可能已经排序，子条目不一定排在目录之后，所以要在移动之前确定
+                let moved_dirs: HashSet<&Path> = if is_move {
+                    result.files.iter().filter(|path| path.is_dir()).map(PathBuf::as_path).collect()
+                } else {
+                    HashSet::new()
+                };
+                for path in &result.files {
+                    if path.ancestors().skip(1).any(|dir| moved_dirs.contains(dir)) {
+                        continue;
+                    }
+
+                    let relative = path.strip_prefix(&result.root).unwrap_or(path);
+                    let destination = target.join(relative);
+                    let outcome = if dry_run {
+                        Ok(())
+                    } else if is_move {
+                        move_path(path, &destination)
+                    } else {
+                        copy_path(path, &destination)
+                    };
+                    report.record(vec![path.clone()], outcome);
+                }
+            }
+            Self::Print0 => {
+                let mut stdout = io::stdout().lock();
+                for path in &result.files {
+                    stdout.write_all(path.as_os_str().as_encoded_bytes())?;
+                    stdout.write_all(b"\0")?;
+                }
+                stdout.flush()?;
+                report.processed = result.files.clone();
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// 动作的执行结果
+#[derive(Debug, Default)]
+pub struct ActionReport {
+    pub action: &'static str,
+    pub dry_run: bool,
+    pub processed: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+    /// 预演执行命令时将要执行的命令行
+    pub commands: Vec<String>,
+}
+
+impl ActionReport {
+    fn record(&mut self, paths: Vec<PathBuf>, outcome: RtoolsResult<()>) {
+        match outcome {
+            Ok(()) => self.processed.extend(paths),
+            Err(e) => {
+                let message = e.to_string();
+                self.failed.extend(paths.into_iter().map(|path| (path, message.clone())));
+            }
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    pub fn print_report(&self) {
+        let prefix = if self.dry_run { "将" } else { "已" };
+        for command in &self.commands {
+            println!("  {}{}: {}", prefix, self.action, command);
+        }
+        if self.commands.is_empty() {
+            for path in &self.processed {
+                println!("  {}{}: {}", prefix, self.action, path.display());
+            }
+        }
+        for (path, error) in &self.failed {
+            println!("  {}失败: {} ({})", self.action, path.display(), error);
+        }
+        println!("{}{} {}项, 失败 {}项", prefix, self.action, self.processed.len(), self.failed.len());
+    }
+}
+
+/// 替换命令中的占位符，得到命令的参数
+fn command_args<'a>(command: &'a [String], paths: &'a [PathBuf]) -> Vec<&'a OsStr> {
+    let mut args: Vec<&OsStr> = Vec::new();
+    let mut replaced = false;
+    for arg in &command[1..] {
+        if arg == PATH_PLACEHOLDER {
+            args.extend(paths.iter().map(|path| path.as_os_str()));
+            replaced = true;
+        } else {
+            args.push(arg.as_ref());
+        }
+    }
+    if !replaced {
+        args.extend(paths.iter().map(|path| path.as_os_str()));
+    }
+    args
+}
+
+/// 预演时显示的命令行，含空白或引号的参数用单引号括起来
+fn command_line(command: &[String], paths: &[PathBuf]) -> String {
+    let mut words = vec![command[0].clone()];
+    for arg in command_args(command, paths) {
+        let arg = arg.to_string_lossy();
+        if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+            words.push(format!("'{}'", arg.replace('\'', "'\\''")));
+        } else {
+            words.push(arg.into_owned());
+        }
+    }
+    words.join(" ")
+}
+
+/// 替换命令中的占位符并执行，命令的输出直接写到标准输出
+fn run_command(command: &[String], paths: &[PathBuf]) -> RtoolsResult<()> {
+    let status = Command::new(&command[0]).args(command_args(command, paths)).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        let code = status.code().map_or_else(|| "被信号终止".to_string(), |code| format!("退出码 {}", code));
+        Err(io::Error::other(format!("命令 '{}' 执行失败: {}", command[0], code)).into())
+    }
+}
+
+/// 按参数总长度把路径分成多批
+fn batches(paths: &[PathBuf]) -> Vec<&[PathBuf]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+
+    for (i, path) in paths.iter().enumerate() {
+        let len = path.as_os_str().len() + 1;
+        if i > start && bytes + len > MAX_BATCH_BYTES {
+            batches.push(&paths[start..i]);
+            start = i;
+            bytes = 0;
+        }
+        bytes += len;
+    }
+    if start < paths.len() {
+        batches.push(&paths[start..]);
+    }
+
+    batches
+}
+
+/// 删除文件、符号链接或空目录；非空目录不会被删除
+fn delete_path(path: &Path) -> RtoolsResult<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// 复制文件或创建对应的目录，目标已存在时失败而不会覆盖
+fn copy_path(source: &Path, destination: &Path) -> RtoolsResult<()> {
+    if source.is_dir() {
+        fs::create_dir_all(destination)?;
+        return Ok(());
+    }
+
+    if destination.exists() {
+        return Err(already_exists(destination));
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(source, destination)?;
+    Ok(())
+}
+
+/// 移动文件或目录，跨文件系统时退化为复制后删除
+fn move_path(source: &Path, destination: &Path) -> RtoolsResult<()> {
+    if destination.exists() {
+        return Err(already_exists(destination));
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if fs::rename(source, destination).is_ok() {
+        return Ok(());
+    }
+    if source.is_dir() {
+        return Err(io::Error::other(format!("无法跨文件系统移动目录: {}", source.display())).into());
+    }
+    fs::copy(source, destination)?;
+    fs::remove_file(source)?;
+    Ok(())
+}
+
+fn already_exists(path: &Path) -> RtoolsError {
+    io::Error::new(io::ErrorKind::AlreadyExists, format!("目标已存在: {}", path.display())).into()
+}
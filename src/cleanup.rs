@@ -0,0 +1,265 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use crate::dirscan::{FileInfo, format_size};
+use crate::{RtoolsResult, RtoolsError};
+
+/// 零字节时视为残留锁文件的扩展名
+const LOCK_EXTENSIONS: [&str; 3] = ["lock", "lck", "pid"];
+
+/// 清理候选项的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CleanupKind {
+    EmptyFile,
+    EmptyDirectory,
+    LockFile,
+    BrokenSymlink,
+    StaleFile,
+}
+
+impl CleanupKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::EmptyFile => "空文件",
+            Self::EmptyDirectory => "空目录",
+            Self::LockFile => "零字节锁文件",
+            Self::BrokenSymlink => "失效的符号链接",
+            Self::StaleFile => "长期未修改的文件",
+        }
+    }
+}
+
+/// 一个清理候选项
+#[derive(Debug, Clone)]
+pub struct CleanupItem {
+    pub path: PathBuf,
+    pub kind: CleanupKind,
+    pub size: u64,
+}
+
+/// 清理报告选项
+#[derive(Debug, Clone, Default)]
+pub struct CleanupOptions {
+    /// 超过该时长未修改的文件会被列为过期文件，未设置时不检查
+    pub stale_after: Option<Duration>,
+}
+
+impl CleanupOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_stale_after(mut self, age: Duration) -> Self {
+        self.stale_after = Some(age);
+        self
+    }
+}
+
+/// 删除操作的结果
+#[derive(Debug, Default)]
+pub struct DeletionResult {
+    pub dry_run: bool,
+    pub deleted: Vec<PathBuf>,
+    pub freed_bytes: u64,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl DeletionResult {
+    pub fn print_result(&self) {
+        let action = if self.dry_run { "将删除" } else { "已删除" };
+        for path in &self.deleted {
+            println!("  {}: {}", action, path.display());
+        }
+        for (path, error) in &self.failed {
+            println!("  删除失败: {} ({})", path.display(), error);
+        }
+        println!("{} {}项, 释放 {}", action, self.deleted.len(), format_size(self.freed_bytes));
+    }
+}
+
+/// 清理报告
+#[derive(Debug)]
+pub struct CleanupReport {
+    pub options: CleanupOptions,
+    pub items: Vec<CleanupItem>,
+    root: PathBuf,
+    /// 解析符号链接后的根目录，只接受位于其中的候选项
+    canonical_root: PathBuf,
+    now: SystemTime,
+}
+
+impl CleanupReport {
+    pub fn new(root: &Path, options: CleanupOptions) -> Self {
+        Self {
+            options,
+            items: Vec::new(),
+            root: root.to_path_buf(),
+            canonical_root: fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf()),
+            now: SystemTime::now(),
+        }
+    }
+
+    /// 检查一个扫描到的普通文件
+    pub fn record_file(&mut self, file_info: &FileInfo) {
+        if !self.is_within_root(&file_info.path, true) {
+            return;
+        }
+
+        let kind = if file_info.size == 0 {
+            if is_lock_file(file_info) {
+                CleanupKind::LockFile
+            } else {
+                CleanupKind::EmptyFile
+            }
+        } else {
+            let age = self.now.duration_since(file_info.modified).unwrap_or_default();
+            match self.options.stale_after {
+                Some(stale_after) if age > stale_after => CleanupKind::StaleFile,
+                _ => return,
+            }
+        };
+
+        self.items.push(CleanupItem {
+            path: file_info.path.clone(),
+            kind,
+            size: file_info.size,
+        });
+    }
+
+    pub fn record_broken_symlink(&mut self, path: &Path) {
+        if !self.is_within_root(path, false) {
+            return;
+        }
+
+        self.items.push(CleanupItem {
+            path: path.to_path_buf(),
+            kind: CleanupKind::BrokenSymlink,
+            size: 0,
+        });
+    }
+
+    /// 记录一个不包含任何文件的目录（可以包含空的子目录）
+    pub fn record_empty_dir(&mut self, path: &Path) {
+        if path == self.root || !self.is_within_root(path, true) {
+            return;
+        }
+
+        // 子目录先于父目录扫描完成，父目录为空时只保留父目录
+        self.items.retain(|item| {
+            item.kind != CleanupKind::EmptyDirectory || !item.path.starts_with(path)
+        });
+        self.items.push(CleanupItem {
+            path: path.to_path_buf(),
+            kind: CleanupKind::EmptyDirectory,
+            size: 0,
+        });
+    }
+
+    pub fn items_of(&self, kind: CleanupKind) -> impl Iterator<Item = &CleanupItem> {
+        self.items.iter().filter(move |item| item.kind == kind)
+    }
+
+    pub fn reclaimable_bytes(&self) -> u64 {
+        self.items.iter().map(|item| item.size).sum()
+    }
+
+    pub fn print_report(&self) {
+        println!("清理报告:");
+        println!("- 候选项: {}个", self.items.len());
+        println!("- 可释放空间: {}", format_size(self.reclaimable_bytes()));
+
+        let kinds = [
+            CleanupKind::EmptyFile,
+            CleanupKind::EmptyDirectory,
+            CleanupKind::LockFile,
+            CleanupKind::BrokenSymlink,
+            CleanupKind::StaleFile,
+        ];
+
+        for kind in kinds {
+            let items: Vec<&CleanupItem> = self.items_of(kind).collect();
+            if items.is_empty() {
+                continue;
+            }
+
+            println!("\n{} ({}个):", kind.description(), items.len());
+            for item in items {
+                if kind == CleanupKind::StaleFile {
+                    println!("  {} ({} 字节)", item.path.display(), item.size);
+                } else {
+                    println!("  {}", item.path.display());
+                }
+            }
+        }
+    }
+
+    /// 删除报告中的所有候选项，`dry_run` 为真时只列出将要删除的内容
+    pub fn delete(&self, dry_run: bool) -> DeletionResult {
+        let mut result = DeletionResult {
+            dry_run,
+            ..DeletionResult::default()
+        };
+        let mut done: HashSet<&Path> = HashSet::new();
+
+        for item in &self.items {
+            if !done.insert(&item.path) {
+                continue;
+            }
+
+            // 扫描之后路径可能被替换为指向别处的符号链接，删除前再检查一次
+            let follow = item.kind != CleanupKind::BrokenSymlink;
+            let outcome = if !self.is_within_root(&item.path, follow) {
+                Err(RtoolsError::PermissionDenied(format!("不在扫描的根目录中: {}", item.path.display())))
+            } else if dry_run {
+                Ok(())
+            } else if item.kind == CleanupKind::EmptyDirectory {
+                remove_empty_dir_tree(&item.path)
+            } else {
+                fs::remove_file(&item.path).map_err(Into::into)
+            };
+
+            match outcome {
+                Ok(()) => {
+                    result.deleted.push(item.path.clone());
+                    result.freed_bytes += item.size;
+                }
+                Err(e) => result.failed.push((item.path.clone(), e.to_string())),
+            }
+        }
+
+        result
+    }
+
+    /// 判断路径是否位于根目录中；`follow` 为假时不解析路径最后一段的符号链接
+    fn is_within_root(&self, path: &Path, follow: bool) -> bool {
+        let resolved = if follow {
+            fs::canonicalize(path)
+        } else {
+            match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) => fs::canonicalize(parent).map(|p| p.join(name)),
+                _ => return false,
+            }
+        };
+        resolved.is_ok_and(|resolved| resolved.starts_with(&self.canonical_root))
+    }
+}
+
+fn is_lock_file(file_info: &FileInfo) -> bool {
+    file_info.name == "LOCK"
+        || file_info.extension
+            .as_deref()
+            .is_some_and(|ext| LOCK_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// 自底向上删除只包含空目录的目录树，遇到文件时失败而不会删除它
+fn remove_empty_dir_tree(path: &Path) -> RtoolsResult<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_empty_dir_tree(&entry.path())?;
+        }
+    }
+    fs::remove_dir(path)?;
+    Ok(())
+}
@@ -123,6 +123,11 @@ impl ConfigManager {
     
     /// 获取配置值
     pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        // INI 文件的节展开为 "section.key" 形式的键，先按完整的键查找
+        if let Some(value) = self.data.get(key) {
+            return Some(value);
+        }
+        
         if key.contains('.') {
             // 处理嵌套键，如 "app.name"
             let parts: Vec<&str> = key.split('.').collect();
@@ -143,7 +148,7 @@ impl ConfigManager {
             }
             None
         } else {
-            self.data.get(key)
+            None
         }
     }
     
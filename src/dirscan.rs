@@ -1,9 +1,45 @@
-use std::collections::HashMap;
-use std::fs;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::{self, File};
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+use sha2::{Digest, Sha256};
+use crate::cleanup::{CleanupOptions, CleanupReport};
+use crate::snapshot::Snapshot;
 use crate::{RtoolsResult, RtoolsError};
 
+/// 排行榜默认保留的条目数
+pub const DEFAULT_TOP_N: usize = 10;
+
+/// 直方图中最长的柱状条宽度（字符数）
+const HISTOGRAM_BAR_WIDTH: usize = 40;
+
+/// 文件大小分桶的上界（不含），每档为上一档的4倍，最后一档无上界
+const SIZE_BUCKET_BOUNDS: [(u64, &str); 11] = [
+    (1 << 10, "<1K"),
+    (1 << 12, "1K-4K"),
+    (1 << 14, "4K-16K"),
+    (1 << 16, "16K-64K"),
+    (1 << 18, "64K-256K"),
+    (1 << 20, "256K-1M"),
+    (1 << 22, "1M-4M"),
+    (1 << 24, "4M-16M"),
+    (1 << 26, "16M-64M"),
+    (1 << 28, "64M-256M"),
+    (1 << 30, "256M-1G"),
+];
+
+const DAY_SECS: u64 = 86400;
+
+/// 修改时间分桶的上界（不含），最后一档无上界
+const AGE_BUCKET_BOUNDS: [(u64, &str); 4] = [
+    (DAY_SECS, "今天"),
+    (7 * DAY_SECS, "一周内"),
+    (30 * DAY_SECS, "一月内"),
+    (365 * DAY_SECS, "一年内"),
+];
+
 #[derive(Debug)]
 pub struct FileInfo {
     pub name: String,
@@ -12,116 +48,443 @@ pub struct FileInfo {
     pub is_dir: bool,
     pub modified: SystemTime,
     pub extension: Option<String>,
+    /// 相对于扫描根目录的深度，根目录下的直接条目为1
+    pub depth: usize,
+}
+
+/// 子目录汇总信息，在该目录扫描完成后生成
+#[derive(Debug, Clone)]
+pub struct DirectorySummary {
+    pub path: PathBuf,
+    /// 目录内所有文件的总大小（仅统计扫描深度范围内的内容）
+    pub total_size: u64,
+    /// 目录的直接子条目数
+    pub entry_count: usize,
+    /// 目录树中非目录条目的总数
+    pub file_count: usize,
+    /// 所有子目录是否都在扫描深度范围内被完整扫描
+    pub complete: bool,
+    pub depth: usize,
+}
+
+/// 堆中的元素，只按排序键比较
+#[derive(Debug, Clone)]
+struct Ranked<K, T> {
+    key: K,
+    item: T,
+}
+
+impl<K: Ord, T> PartialEq for Ranked<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord, T> Eq for Ranked<K, T> {}
+
+impl<K: Ord, T> PartialOrd for Ranked<K, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T> Ord for Ranked<K, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// 固定容量的排行榜，保留排序键最大的N个元素
+///
+/// 内部使用最小堆，插入为 O(log N)，只在读取时排序。
+#[derive(Debug, Clone)]
+pub struct TopN<K: Ord, T> {
+    capacity: usize,
+    heap: BinaryHeap<Reverse<Ranked<K, T>>>,
+}
+
+impl<K: Ord, T> TopN<K, T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            heap: BinaryHeap::with_capacity(capacity + 1),
+        }
+    }
+
+    /// 插入元素，`make_item` 只在元素确实进入排行榜时才被调用
+    pub fn push_with(&mut self, key: K, make_item: impl FnOnce() -> T) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.heap.len() < self.capacity {
+            self.heap.push(Reverse(Ranked { key, item: make_item() }));
+        } else if let Some(mut min) = self.heap.peek_mut()
+            && key > min.0.key {
+            *min = Reverse(Ranked { key, item: make_item() });
+        }
+    }
+
+    pub fn push(&mut self, key: K, item: T) {
+        self.push_with(key, || item);
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 按排序键从大到小返回元素
+    pub fn sorted(&self) -> Vec<&T> {
+        let mut ranked: Vec<&Ranked<K, T>> = self.heap.iter().map(|r| &r.0).collect();
+        ranked.sort_by(|a, b| b.key.cmp(&a.key));
+        ranked.into_iter().map(|r| &r.item).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.sorted().into_iter()
+    }
+}
+
+/// 直方图的一个分桶
+#[derive(Debug, Clone)]
+pub struct HistogramBucket {
+    pub label: &'static str,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// 文件数量和字节数的分桶统计
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    pub buckets: Vec<HistogramBucket>,
+}
+
+impl Histogram {
+    fn with_labels(labels: impl IntoIterator<Item = &'static str>) -> Self {
+        Self {
+            buckets: labels
+                .into_iter()
+                .map(|label| HistogramBucket { label, count: 0, bytes: 0 })
+                .collect(),
+        }
+    }
+
+    /// 按文件大小分桶: <1K, 1K-4K, ..., 256M-1G, >1G
+    pub fn by_size() -> Self {
+        let labels = SIZE_BUCKET_BOUNDS.iter().map(|(_, label)| *label);
+        Self::with_labels(labels.chain([">1G"]))
+    }
+
+    /// 按修改时间距今的长短分桶: 今天、一周内、一月内、一年内、更早
+    pub fn by_age() -> Self {
+        let labels = AGE_BUCKET_BOUNDS.iter().map(|(_, label)| *label);
+        Self::with_labels(labels.chain(["更早"]))
+    }
+
+    fn record(&mut self, index: usize, size: u64) {
+        if let Some(bucket) = self.buckets.get_mut(index) {
+            bucket.count += 1;
+            bucket.bytes += size;
+        }
+    }
+
+    pub fn total_count(&self) -> usize {
+        self.buckets.iter().map(|b| b.count).sum()
+    }
+
+    /// 以ASCII柱状图的形式打印，柱长按文件数缩放
+    pub fn print(&self, title: &str) {
+        let max_count = self.buckets.iter().map(|b| b.count).max().unwrap_or(0);
+        if max_count == 0 {
+            return;
+        }
+
+        println!("\n{}:", title);
+        let label_width = self.buckets.iter().map(|b| b.label.chars().count()).max().unwrap_or(0);
+        for bucket in &self.buckets {
+            let bar_len = (bucket.count * HISTOGRAM_BAR_WIDTH).div_ceil(max_count);
+            let padding = label_width - bucket.label.chars().count();
+            println!("  {}{} |{:<width$}| {}个文件, {}",
+                     bucket.label,
+                     " ".repeat(padding),
+                     "#".repeat(bar_len),
+                     bucket.count,
+                     format_size(bucket.bytes),
+                     width = HISTOGRAM_BAR_WIDTH);
+        }
+    }
+}
+
+fn size_bucket_index(size: u64) -> usize {
+    SIZE_BUCKET_BOUNDS
+        .iter()
+        .position(|(bound, _)| size < *bound)
+        .unwrap_or(SIZE_BUCKET_BOUNDS.len())
+}
+
+fn age_bucket_index(age: Duration) -> usize {
+    AGE_BUCKET_BOUNDS
+        .iter()
+        .position(|(bound, _)| age.as_secs() < *bound)
+        .unwrap_or(AGE_BUCKET_BOUNDS.len())
+}
+
+/// 将字节数格式化为带单位的可读字符串，如 `1.50 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.2} {}", value, UNITS[unit])
+    }
 }
 
-#[derive(Debug, Default)]
+/// 计算文件内容的SHA-256摘要（十六进制），`max_bytes` 限制只读取文件开头的部分内容
+pub fn hash_file(path: &Path, max_bytes: Option<u64>) -> RtoolsResult<String> {
+    let file = File::open(path)?;
+    let mut reader = file.take(max_bytes.unwrap_or(u64::MAX));
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// 解析时长，如 `30s`、`15m`、`12h`、`7d`、`2w`、`1y`，不带单位时按天计算
+pub fn parse_age(s: &str) -> RtoolsResult<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let value = number.parse::<u64>()
+        .map_err(|_| RtoolsError::InvalidArgument(format!("无效的时长: {}", s)))?;
+
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "" | "d" => DAY_SECS,
+        "w" => 7 * DAY_SECS,
+        "y" => 365 * DAY_SECS,
+        _ => return Err(RtoolsError::InvalidArgument(format!("无效的时长单位: {}", s))),
+    };
+
+    Ok(Duration::from_secs(value * unit_secs))
+}
+
+#[derive(Debug)]
 pub struct DirectoryStats {
     pub total_files: usize,
     pub total_dirs: usize,
     pub total_size: u64,
     pub extension_stats: HashMap<String, usize>,
-    pub largest_files: Vec<FileInfo>,
-    pub oldest_files: Vec<FileInfo>,
+    pub largest_files: TopN<u64, FileInfo>,
+    pub oldest_files: TopN<Reverse<SystemTime>, FileInfo>,
+    pub newest_files: TopN<SystemTime, FileInfo>,
+    pub largest_dirs: TopN<u64, DirectorySummary>,
+    pub busiest_dirs: TopN<usize, DirectorySummary>,
+    pub deepest_paths: TopN<usize, FileInfo>,
+    pub size_histogram: Histogram,
+    pub age_histogram: Histogram,
+    /// 计算文件年龄时使用的参考时间
+    pub scanned_at: SystemTime,
+    /// 启用快照时记录的文件列表
+    pub snapshot: Option<Snapshot>,
+    /// 启用清理报告时收集的候选项
+    pub cleanup: Option<CleanupReport>,
+    /// 扫描中无法读取内容的文件，这些文件的快照记录不含摘要
+    pub warnings: Vec<String>,
+}
+
+impl Default for DirectoryStats {
+    fn default() -> Self {
+        Self::with_top_n(DEFAULT_TOP_N)
+    }
 }
 
 impl DirectoryStats {
     pub fn new() -> Self {
         Self::default()
     }
-    
+
+    /// 创建统计对象，每个排行榜保留 `top_n` 个条目
+    pub fn with_top_n(top_n: usize) -> Self {
+        Self {
+            total_files: 0,
+            total_dirs: 0,
+            total_size: 0,
+            extension_stats: HashMap::new(),
+            largest_files: TopN::new(top_n),
+            oldest_files: TopN::new(top_n),
+            newest_files: TopN::new(top_n),
+            largest_dirs: TopN::new(top_n),
+            busiest_dirs: TopN::new(top_n),
+            deepest_paths: TopN::new(top_n),
+            size_histogram: Histogram::by_size(),
+            age_histogram: Histogram::by_age(),
+            scanned_at: SystemTime::now(),
+            snapshot: None,
+            cleanup: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn top_n(&self) -> usize {
+        self.largest_files.capacity()
+    }
+
     pub fn add_file(&mut self, file_info: FileInfo) {
+        // 优化：只在条目进入排行榜时才克隆
+        self.deepest_paths.push_with(file_info.depth, || file_info.clone());
+
         if file_info.is_dir {
             self.total_dirs += 1;
         } else {
             self.total_files += 1;
             self.total_size += file_info.size;
-            
+
             // 统计文件扩展名
             if let Some(ext) = &file_info.extension {
                 *self.extension_stats.entry(ext.clone()).or_insert(0) += 1;
             }
-            
-            // 优化：只在需要时克隆
-            self.update_largest_files(&file_info);
-            self.update_oldest_files(&file_info);
-        }
-    }
-    
-    fn update_largest_files(&mut self, file_info: &FileInfo) {
-        // 如果列表未满，直接添加
-        if self.largest_files.len() < 10 {
-            self.largest_files.push(file_info.clone());
-            self.largest_files.sort_by(|a, b| b.size.cmp(&a.size));
-        } else {
-            // 如果新文件比最小的还大，替换最小的
-            if let Some(smallest) = self.largest_files.iter_mut().min_by_key(|f| f.size) {
-                if file_info.size > smallest.size {
-                    *smallest = file_info.clone();
-                    self.largest_files.sort_by(|a, b| b.size.cmp(&a.size));
-                }
-            }
+
+            let age = self.scanned_at
+                .duration_since(file_info.modified)
+                .unwrap_or_default();
+            self.size_histogram.record(size_bucket_index(file_info.size), file_info.size);
+            self.age_histogram.record(age_bucket_index(age), file_info.size);
+
+            self.largest_files.push_with(file_info.size, || file_info.clone());
+            self.oldest_files.push_with(Reverse(file_info.modified), || file_info.clone());
+            self.newest_files.push(file_info.modified, file_info);
         }
     }
-    
-    fn update_oldest_files(&mut self, file_info: &FileInfo) {
-        // 如果列表未满，直接添加
-        if self.oldest_files.len() < 10 {
-            self.oldest_files.push(file_info.clone());
-            self.oldest_files.sort_by(|a, b| a.modified.cmp(&b.modified));
-        } else {
-            // 如果新文件比最新的还旧，替换最新的
-            if let Some(newest) = self.oldest_files.iter_mut().max_by_key(|f| f.modified) {
-                if file_info.modified < newest.modified {
-                    *newest = file_info.clone();
-                    self.oldest_files.sort_by(|a, b| a.modified.cmp(&b.modified));
-                }
-            }
-        }
+
+    /// 记录一个已扫描完成的子目录
+    pub fn add_directory(&mut self, summary: DirectorySummary) {
+        self.largest_dirs.push_with(summary.total_size, || summary.clone());
+        self.busiest_dirs.push(summary.entry_count, summary);
     }
-    
+
     pub fn print_stats(&self) {
+        let top_n = self.top_n();
+
         println!("目录统计信息:");
         println!("- 总文件数: {}", self.total_files);
         println!("- 总目录数: {}", self.total_dirs);
-        println!("- 总大小: {} 字节 ({:.2} MB)", 
-                 self.total_size, 
+        println!("- 总大小: {} 字节 ({:.2} MB)",
+                 self.total_size,
                  self.total_size as f64 / 1024.0 / 1024.0);
-        
+
         if !self.extension_stats.is_empty() {
             println!("\n文件类型统计:");
             let mut sorted_extensions: Vec<(&String, &usize)> = self.extension_stats.iter().collect();
             sorted_extensions.sort_by(|a, b| b.1.cmp(a.1));
-            
-            for (ext, count) in sorted_extensions.iter().take(10) {
+
+            for (ext, count) in sorted_extensions.iter().take(top_n) {
                 println!("  .{}: {}个文件", ext, count);
             }
         }
-        
+
+        self.size_histogram.print("文件大小分布");
+        self.age_histogram.print("修改时间分布");
+
         if !self.largest_files.is_empty() {
-            println!("\n最大的10个文件:");
+            println!("\n最大的{}个文件:", top_n);
             for (i, file) in self.largest_files.iter().enumerate() {
-                println!("  {}. {} ({} 字节)", 
-                         i + 1, 
-                         file.name, 
+                println!("  {}. {} ({} 字节)",
+                         i + 1,
+                         file.name,
                          file.size);
             }
         }
-        
+
         if !self.oldest_files.is_empty() {
-            println!("\n最旧的10个文件:");
+            println!("\n最旧的{}个文件:", top_n);
             for (i, file) in self.oldest_files.iter().enumerate() {
-                let duration = SystemTime::now()
-                    .duration_since(file.modified)
-                    .unwrap_or_default();
-                println!("  {}. {} ({}天前)", 
-                         i + 1, 
-                         file.name, 
-                         duration.as_secs() / 86400);
+                println!("  {}. {} ({}天前)",
+                         i + 1,
+                         file.name,
+                         days_since(file.modified));
+            }
+        }
+
+        if !self.newest_files.is_empty() {
+            println!("\n最新的{}个文件:", top_n);
+            for (i, file) in self.newest_files.iter().enumerate() {
+                println!("  {}. {} ({}天前)",
+                         i + 1,
+                         file.name,
+                         days_since(file.modified));
+            }
+        }
+
+        if !self.largest_dirs.is_empty() {
+            println!("\n最大的{}个目录:", top_n);
+            for (i, dir) in self.largest_dirs.iter().enumerate() {
+                println!("  {}. {} ({} 字节)",
+                         i + 1,
+                         dir.path.display(),
+                         dir.total_size);
+            }
+        }
+
+        if !self.busiest_dirs.is_empty() {
+            println!("\n条目最多的{}个目录:", top_n);
+            for (i, dir) in self.busiest_dirs.iter().enumerate() {
+                println!("  {}. {} ({}个条目)",
+                         i + 1,
+                         dir.path.display(),
+                         dir.entry_count);
+            }
+        }
+
+        if !self.deepest_paths.is_empty() {
+            println!("\n最深的{}个路径:", top_n);
+            for (i, file) in self.deepest_paths.iter().enumerate() {
+                println!("  {}. {} (深度 {})",
+                         i + 1,
+                         file.path.display(),
+                         file.depth);
             }
         }
     }
 }
 
+fn days_since(time: SystemTime) -> u64 {
+    SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs() / DAY_SECS
+}
+
 impl Clone for FileInfo {
     fn clone(&self) -> Self {
         Self {
@@ -131,53 +494,149 @@ impl Clone for FileInfo {
             is_dir: self.is_dir,
             modified: self.modified,
             extension: self.extension.clone(),
+            depth: self.depth,
+        }
+    }
+}
+
+/// 目录扫描选项
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub max_depth: Option<usize>,
+    pub top_n: usize,
+    /// 是否在扫描时生成快照
+    pub snapshot: bool,
+    /// 生成快照时是否计算文件内容摘要
+    pub hash_files: bool,
+    /// 设置后在扫描时生成清理报告
+    pub cleanup: Option<CleanupOptions>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            top_n: DEFAULT_TOP_N,
+            snapshot: false,
+            hash_files: false,
+            cleanup: None,
         }
     }
 }
 
+impl ScanOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn with_top_n(mut self, top_n: usize) -> Self {
+        self.top_n = top_n;
+        self
+    }
+
+    /// 扫描时生成快照，`hash_files` 为真时同时记录文件内容摘要
+    pub fn with_snapshot(mut self, hash_files: bool) -> Self {
+        self.snapshot = true;
+        self.hash_files = hash_files;
+        self
+    }
+
+    pub fn with_cleanup(mut self, cleanup: CleanupOptions) -> Self {
+        self.cleanup = Some(cleanup);
+        self
+    }
+}
+
 pub fn scan_directory(dir_path: &str, max_depth: Option<usize>) -> RtoolsResult<DirectoryStats> {
+    let mut options = ScanOptions::new();
+    options.max_depth = max_depth;
+    scan_directory_with_options(dir_path, &options)
+}
+
+/// 按指定选项扫描目录
+pub fn scan_directory_with_options(dir_path: &str, options: &ScanOptions) -> RtoolsResult<DirectoryStats> {
     let path = Path::new(dir_path);
-    
+
     if !path.exists() {
         return Err(RtoolsError::DirectoryNotFound(dir_path.to_string()));
     }
-    
+
     if !path.is_dir() {
         return Err(RtoolsError::NotADirectory(dir_path.to_string()));
     }
-    
-    let mut stats = DirectoryStats::new();
-    scan_directory_recursive(path, &mut stats, 0, max_depth.unwrap_or(usize::MAX))?;
-    
+
+    let mut stats = DirectoryStats::with_top_n(options.top_n);
+    if options.snapshot {
+        stats.snapshot = Some(Snapshot::new(path));
+    }
+    if let Some(cleanup) = &options.cleanup {
+        stats.cleanup = Some(CleanupReport::new(path, cleanup.clone()));
+    }
+
+    scan_directory_recursive(path, &mut stats, 0, options)?;
+
     Ok(stats)
 }
 
+/// 递归扫描目录，返回该目录的汇总信息
 fn scan_directory_recursive(
-    dir_path: &Path, 
-    stats: &mut DirectoryStats, 
-    current_depth: usize, 
-    max_depth: usize
-) -> RtoolsResult<()> {
+    dir_path: &Path,
+    stats: &mut DirectoryStats,
+    current_depth: usize,
+    options: &ScanOptions,
+) -> RtoolsResult<DirectorySummary> {
+    let max_depth = options.max_depth.unwrap_or(usize::MAX);
+    let mut summary = DirectorySummary {
+        path: dir_path.to_path_buf(),
+        total_size: 0,
+        entry_count: 0,
+        file_count: 0,
+        complete: true,
+        depth: current_depth,
+    };
+
     if current_depth > max_depth {
-        return Ok(());
+        summary.complete = false;
+        return Ok(summary);
     }
-    
+
     let entries = fs::read_dir(dir_path)?;
-    
+
     for entry in entries {
         let entry = entry?;
         let path = entry.path();
-        
-        let metadata = fs::metadata(&path)?;
+        summary.entry_count += 1;
+
+        // 符号链接按其目标统计，但不进入指向的目录；目标不存在时只记录到清理报告
+        let mut metadata = fs::symlink_metadata(&path)?;
+        let is_symlink = metadata.file_type().is_symlink();
+        if is_symlink {
+            match fs::metadata(&path) {
+                Ok(target) => metadata = target,
+                Err(_) => {
+                    summary.file_count += 1;
+                    if let Some(cleanup) = stats.cleanup.as_mut() {
+                        cleanup.record_broken_symlink(&path);
+                    }
+                    continue;
+                }
+            }
+        }
+
         let name = path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("未知")
             .to_string();
-        
+
         let extension = path.extension()
             .and_then(|ext| ext.to_str())
             .map(|s| s.to_string());
-        
+
         let file_info = FileInfo {
             name,
             path: path.clone(),
@@ -185,15 +644,48 @@ fn scan_directory_recursive(
             is_dir: metadata.is_dir(),
             modified: metadata.modified()?,
             extension,
+            depth: current_depth + 1,
         };
-        
+
+        if is_symlink && metadata.is_dir() {
+            // 指向目录的链接所在的目录不是空目录
+            summary.file_count += 1;
+        } else if !metadata.is_dir() {
+            summary.total_size += metadata.len();
+            summary.file_count += 1;
+
+            if let Some(snapshot) = stats.snapshot.as_mut()
+                && let Err(e) = snapshot.record(&file_info, options.hash_files)
+            {
+                stats.warnings.push(format!("无法计算摘要 {}: {}", path.display(), e));
+            }
+            if let Some(cleanup) = stats.cleanup.as_mut() {
+                cleanup.record_file(&file_info);
+            }
+        }
+
         stats.add_file(file_info);
-        
-        // 递归扫描子目录
-        if metadata.is_dir() && current_depth < max_depth {
-            scan_directory_recursive(&path, stats, current_depth + 1, max_depth)?;
+
+        // 递归扫描子目录，不跟随符号链接，以免扫描到根目录之外
+        if metadata.is_dir() && !is_symlink {
+            if current_depth < max_depth {
+                let child = scan_directory_recursive(&path, stats, current_depth + 1, options)?;
+                summary.total_size += child.total_size;
+                summary.file_count += child.file_count;
+                summary.complete &= child.complete;
+                stats.add_directory(child);
+            } else {
+                summary.complete = false;
+            }
         }
     }
-    
-    Ok(())
-} 
\ No newline at end of file
+
+    // 只有完整扫描过的目录才能确定为空
+    if summary.complete
+        && summary.file_count == 0
+        && let Some(cleanup) = stats.cleanup.as_mut() {
+        cleanup.record_empty_dir(dir_path);
+    }
+
+    Ok(summary)
+}
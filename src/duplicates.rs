@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::dirscan::{ScanOptions, format_size, hash_file, scan_directory_with_options};
+use crate::RtoolsResult;
+
+/// 第一轮内容比较时读取的字节数
+const PARTIAL_HASH_BYTES: u64 = 4096;
+
+/// 重复文件查找选项
+#[derive(Debug, Clone)]
+pub struct DuplicateOptions {
+    pub max_depth: Option<usize>,
+    /// 小于该大小的文件不参与比较，默认跳过空文件
+    pub min_size: u64,
+    /// 是否识别硬链接，指向同一inode的路径不计为浪费空间
+    pub detect_hard_links: bool,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            min_size: 1,
+            detect_hard_links: false,
+        }
+    }
+}
+
+impl DuplicateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn with_min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    pub fn with_hard_links(mut self, detect: bool) -> Self {
+        self.detect_hard_links = detect;
+        self
+    }
+}
+
+/// 一组内容完全相同的文件
+#[derive(Debug, Clone)]
+pub struct DuplicateSet {
+    pub size: u64,
+    pub hash: String,
+    /// 每个元素是一份独立的数据副本，硬链接到同一inode的路径归为同一个元素
+    pub copies: Vec<Vec<PathBuf>>,
+}
+
+impl DuplicateSet {
+    pub fn file_count(&self) -> usize {
+        self.copies.iter().map(|paths| paths.len()).sum()
+    }
+
+    /// 删除多余副本后可以节省的字节数
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.copies.len() as u64).saturating_sub(1)
+    }
+}
+
+/// 重复文件查找结果
+#[derive(Debug, Default)]
+pub struct DuplicateReport {
+    pub files_scanned: usize,
+    pub sets: Vec<DuplicateSet>,
+    /// 识别出的额外硬链接路径数（不计入浪费空间）
+    pub hard_links: usize,
+}
+
+impl DuplicateReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn wasted_bytes(&self) -> u64 {
+        self.sets.iter().map(|s| s.wasted_bytes()).sum()
+    }
+
+    pub fn print_report(&self) {
+        println!("重复文件统计:");
+        println!("- 扫描文件数: {}", self.files_scanned);
+        println!("- 重复文件组: {}", self.sets.len());
+        println!("- 浪费空间: {} ({} 字节)", format_size(self.wasted_bytes()), self.wasted_bytes());
+        if self.hard_links > 0 {
+            println!("- 硬链接: {}个 (不计入浪费空间)", self.hard_links);
+        }
+
+        for (i, set) in self.sets.iter().enumerate() {
+            println!("\n{}. {}个副本, 每个 {} 字节, 浪费 {}",
+                     i + 1,
+                     set.copies.len(),
+                     set.size,
+                     format_size(set.wasted_bytes()));
+            for paths in &set.copies {
+                for (j, path) in paths.iter().enumerate() {
+                    let marker = if j == 0 { "-" } else { "=" };
+                    println!("   {} {}", marker, path.display());
+                }
+            }
+        }
+    }
+}
+
+/// 在目录中查找内容重复的文件
+///
+/// 依次按文件大小、开头部分内容的摘要、完整内容的摘要分组，
+/// 每一轮只对上一轮仍有多个候选的分组读取文件内容。
+pub fn find_duplicates(dir_path: &str, options: &DuplicateOptions) -> RtoolsResult<DuplicateReport> {
+    let mut scan_options = ScanOptions::new().with_snapshot(false);
+    scan_options.max_depth = options.max_depth;
+    let stats = scan_directory_with_options(dir_path, &scan_options)?;
+
+    let mut report = DuplicateReport::new();
+    let Some(snapshot) = stats.snapshot else {
+        return Ok(report);
+    };
+    report.files_scanned = snapshot.entries.len();
+
+    // 第一轮: 按大小分组
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for entry in &snapshot.entries {
+        if entry.size >= options.min_size {
+            by_size.entry(entry.size).or_default().push(snapshot.root.join(&entry.path));
+        }
+    }
+
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+
+        // 同一inode的路径只需要比较一次
+        let copies = group_by_inode(paths, options.detect_hard_links)?;
+        report.hard_links += copies.iter().map(|c| c.len() - 1).sum::<usize>();
+        if copies.len() < 2 {
+            continue;
+        }
+
+        // 第二轮: 按开头部分内容分组
+        for (partial_hash, candidates) in group_by_hash(copies, Some(PARTIAL_HASH_BYTES))? {
+            if candidates.len() < 2 {
+                continue;
+            }
+
+            // 第三轮: 按完整内容分组，小文件的部分摘要已经覆盖全部内容
+            let groups = if size <= PARTIAL_HASH_BYTES {
+                HashMap::from([(partial_hash, candidates)])
+            } else {
+                group_by_hash(candidates, None)?
+            };
+
+            for (hash, mut copies) in groups {
+                if copies.len() >= 2 {
+                    copies.sort();
+                    report.sets.push(DuplicateSet { size, hash, copies });
+                }
+            }
+        }
+    }
+
+    report.sets.sort_by(|a, b| {
+        b.wasted_bytes()
+            .cmp(&a.wasted_bytes())
+            .then_with(|| a.copies.cmp(&b.copies))
+    });
+
+    Ok(report)
+}
+
+fn group_by_hash(
+    copies: Vec<Vec<PathBuf>>,
+    max_bytes: Option<u64>,
+) -> RtoolsResult<HashMap<String, Vec<Vec<PathBuf>>>> {
+    let mut groups: HashMap<String, Vec<Vec<PathBuf>>> = HashMap::new();
+    for paths in copies {
+        let hash = hash_file(&paths[0], max_bytes)?;
+        groups.entry(hash).or_default().push(paths);
+    }
+    Ok(groups)
+}
+
+/// 将路径按 (设备号, inode) 分组；不识别硬链接时每个路径单独成组
+fn group_by_inode(paths: Vec<PathBuf>, detect_hard_links: bool) -> RtoolsResult<Vec<Vec<PathBuf>>> {
+    if !detect_hard_links {
+        return Ok(paths.into_iter().map(|p| vec![p]).collect());
+    }
+
+    let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+    let mut index_by_inode: HashMap<(u64, u64), usize> = HashMap::new();
+    for path in paths {
+        match file_id(&path)? {
+            Some(id) => {
+                if let Some(&index) = index_by_inode.get(&id) {
+                    groups[index].push(path);
+                } else {
+                    index_by_inode.insert(id, groups.len());
+                    groups.push(vec![path]);
+                }
+            }
+            None => groups.push(vec![path]),
+        }
+    }
+
+    for group in &mut groups {
+        group.sort();
+    }
+    Ok(groups)
+}
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> RtoolsResult<Option<(u64, u64)>> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path)?;
+    Ok(Some((metadata.dev(), metadata.ino())))
+}
+
+#[cfg(not(unix))]
+fn file_id(path: &Path) -> RtoolsResult<Option<(u64, u64)>> {
+    fs::metadata(path)?;
+    Ok(None)
+}
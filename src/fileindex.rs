@@ -0,0 +1,510 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Utc};
+use crate::dirscan::format_size;
+use crate::filesearch::{
+    EntryType, FileDetails, SearchCriteria, SearchMatch, SearchResult, fuzzy_score, matches_name,
+};
+use crate::{RtoolsResult, RtoolsError};
+
+/// 默认的索引文件名，保存在被索引目录的根目录下
+pub const INDEX_FILE_NAME: &str = ".rtools-index";
+
+/// 索引文件第一行的格式标识
+const INDEX_MAGIC: &str = "rtools-index";
+const INDEX_VERSION: u32 = 2;
+
+/// 目录中的一个条目
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub name: String,
+    /// 条目本身的类型，不跟随符号链接
+    pub entry_type: EntryType,
+    /// 符号链接指向的条目类型，目标不存在时为 `None`
+    pub target_type: Option<EntryType>,
+    /// 大小，符号链接的目标存在时为目标的大小
+    pub size: u64,
+    /// 修改时间，距UNIX纪元的纳秒数，未知时为0；符号链接的目标存在时为目标的修改时间
+    pub modified: u64,
+}
+
+/// 一个已索引的目录及其直接包含的条目
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedDir {
+    /// 目录的修改时间，用于判断增量更新时是否需要重新读取
+    pub modified: u64,
+    pub entries: Vec<IndexEntry>,
+}
+
+/// 增量更新的统计
+#[derive(Debug, Clone, Default)]
+pub struct IndexUpdate {
+    /// 重新读取的目录数
+    pub dirs_scanned: usize,
+    /// 修改时间未变、沿用条目列表的目录数（条目的元数据仍会重新读取）
+    pub dirs_reused: usize,
+    /// 已不存在而被移除的目录数
+    pub dirs_removed: usize,
+    /// 无法读取而跳过的目录
+    pub skipped: Vec<PathBuf>,
+    pub elapsed: Duration,
+}
+
+impl IndexUpdate {
+    pub fn print_update(&self) {
+        println!("- 重新读取目录: {}个", self.dirs_scanned);
+        println!("- 未变化的目录: {}个", self.dirs_reused);
+        println!("- 已删除的目录: {}个", self.dirs_removed);
+        for path in &self.skipped {
+            println!("- 无法读取: {}", path.display());
+        }
+        println!("- 耗时: {} ms", self.elapsed.as_millis());
+    }
+}
+
+/// 持久化的文件名索引（类似 `locate`）
+///
+/// 按目录保存条目的名称、类型、大小和修改时间。增量更新时只重新读取修改时间发生变化的目录：
+/// 目录中增删或重命名条目会更新目录的修改时间，而文件内容的变化不会，
+/// 因此未变化目录沿用条目列表，但每个条目的大小和修改时间仍会重新读取。
+#[derive(Debug, Clone)]
+pub struct FileIndex {
+    /// 被索引目录的绝对路径
+    pub root: PathBuf,
+    pub updated_at: DateTime<Utc>,
+    /// 以相对于根目录的路径（`/` 分隔，根目录为空字符串）为键
+    pub dirs: BTreeMap<String, IndexedDir>,
+}
+
+impl FileIndex {
+    /// 完整扫描目录并建立索引
+    pub fn build(dir_path: &str) -> RtoolsResult<Self> {
+        let path = Path::new(dir_path);
+        if !path.exists() {
+            return Err(RtoolsError::DirectoryNotFound(dir_path.to_string()));
+        }
+        if !path.is_dir() {
+            return Err(RtoolsError::NotADirectory(dir_path.to_string()));
+        }
+
+        let mut index = Self {
+            root: fs::canonicalize(path)?,
+            updated_at: Utc::now(),
+            dirs: BTreeMap::new(),
+        };
+        index.update()?;
+        Ok(index)
+    }
+
+    /// 默认的索引文件位置
+    pub fn default_path(dir_path: &str) -> PathBuf {
+        Path::new(dir_path).join(INDEX_FILE_NAME)
+    }
+
+    /// 增量更新索引，只重新读取修改时间变化的目录
+    pub fn update(&mut self) -> RtoolsResult<IndexUpdate> {
+        let start = Instant::now();
+        let mut stats = IndexUpdate::default();
+        let mut previous = std::mem::take(&mut self.dirs);
+
+        let root = self.root.clone();
+        self.refresh_dir(&mut previous, String::new(), &root, &mut stats)?;
+
+        stats.dirs_removed = previous.len();
+        stats.elapsed = start.elapsed();
+        self.updated_at = Utc::now();
+        Ok(stats)
+    }
+
+    fn refresh_dir(
+        &mut self,
+        previous: &mut BTreeMap<String, IndexedDir>,
+        relative: String,
+        path: &Path,
+        stats: &mut IndexUpdate,
+    ) -> RtoolsResult<()> {
+        let modified = fs::metadata(path)?.modified().map(to_nanos).unwrap_or(0);
+
+        let dir = match previous.remove(&relative) {
+            Some(mut dir) if modified != 0 && dir.modified == modified => {
+                stats.dirs_reused += 1;
+                // 原地改写文件不会改变目录的修改时间，沿用的条目要重新读取元数据
+                dir.entries = dir.entries
+                    .into_iter()
+                    .filter_map(|entry| {
+                        let entry_path = path.join(&entry.name);
+                        stat_entry(entry.name, &entry_path)
+                    })
+                    .collect();
+                dir
+            }
+            _ => {
+                stats.dirs_scanned += 1;
+                read_dir_entries(path, modified, relative.is_empty())?
+            }
+        };
+
+        let subdirs: Vec<String> = dir.entries
+            .iter()
+            .filter(|entry| entry.entry_type == EntryType::Dir)
+            .map(|entry| entry.name.clone())
+            .collect();
+        self.dirs.insert(relative.clone(), dir);
+
+        for name in subdirs {
+            let child_relative = if relative.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", relative, name)
+            };
+            let child_path = path.join(&name);
+            // 子目录无法读取时跳过，不影响其余部分的索引
+            if self.refresh_dir(previous, child_relative, &child_path, stats).is_err() {
+                stats.skipped.push(child_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn dir_count(&self) -> usize {
+        self.dirs.len()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.entries().filter(|(_, entry)| entry.entry_type != EntryType::Dir).count()
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.entries()
+            .filter(|(_, entry)| entry.entry_type == EntryType::File)
+            .map(|(_, entry)| entry.size)
+            .sum()
+    }
+
+    /// 所有条目及其所在目录的相对路径
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &IndexEntry)> {
+        self.dirs
+            .iter()
+            .flat_map(|(dir, indexed)| indexed.entries.iter().map(move |entry| (dir.as_str(), entry)))
+    }
+
+    pub fn print_summary(&self) {
+        println!("索引信息:");
+        println!("- 根目录: {}", self.root.display());
+        println!("- 目录数: {}", self.dir_count());
+        println!("- 文件数: {}", self.file_count());
+        println!("- 文件总大小: {}", format_size(self.total_size()));
+        println!("- 更新时间: {}", self.updated_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    }
+
+    /// 用索引回答搜索，只支持名称、扩展名、通配符/正则、模糊查询、大小、修改时间、类型、深度、排序和数量限制
+    ///
+    /// `dir_path` 可以是被索引目录或其中的子目录，结果路径以 `dir_path` 开头。
+    pub fn search(&self, dir_path: &str, criteria: &SearchCriteria) -> RtoolsResult<SearchResult> {
+        let start = Instant::now();
+        check_supported(criteria)?;
+
+        let dir = Path::new(dir_path);
+        let absolute = fs::canonicalize(dir)
+            .map_err(|_| RtoolsError::DirectoryNotFound(dir_path.to_string()))?;
+        if !absolute.starts_with(&self.root) {
+            return Err(RtoolsError::InvalidArgument(format!(
+                "目录 {} 不在索引范围 {} 内", dir_path, self.root.display()
+            )));
+        }
+        let prefix = relative_key(&absolute, &self.root);
+        let max_depth = criteria.max_depth.unwrap_or(usize::MAX);
+
+        let mut result = SearchResult::new();
+        result.root = dir.to_path_buf();
+
+        for (dir_key, indexed) in self.dirs.range(prefix.clone()..) {
+            // 以前缀开头的键是连续的，其中还可能有 `a b` 这样不在 `a` 之下的目录
+            if !dir_key.starts_with(&prefix) {
+                break;
+            }
+            if !is_within(dir_key, &prefix) {
+                continue;
+            }
+
+            let sub_dir = dir_key[prefix.len()..].trim_start_matches('/');
+            let depth = if sub_dir.is_empty() { 0 } else { sub_dir.split('/').count() };
+            if depth > max_depth {
+                continue;
+            }
+
+            let dir_path = dir.join(sub_dir);
+            for entry in &indexed.entries {
+                // 与直接搜索相同，除非明确搜索符号链接，否则按链接目标的类型处理
+                let entry_type = match entry.target_type {
+                    Some(target) if !criteria.wants_symlinks() => target,
+                    _ => entry.entry_type,
+                };
+                if !criteria.accepts_type(entry_type) {
+                    continue;
+                }
+
+                let path = dir_path.join(&entry.name);
+                let Some(score) = fuzzy_score(&path, dir, criteria) else {
+                    continue;
+                };
+                if !matches_name(&path, dir, &entry.name, criteria) || !matches_details(entry, criteria) {
+                    continue;
+                }
+
+                result.push(SearchMatch {
+                    path,
+                    entry_type,
+                    details: FileDetails {
+                        size: entry.size,
+                        modified: from_nanos(entry.modified),
+                        score,
+                    },
+                    content: None,
+                });
+            }
+        }
+
+        result.apply_order(criteria.effective_sort(), criteria.limit);
+        result.search_time_ms = start.elapsed().as_millis();
+        Ok(result)
+    }
+
+    /// 保存为按目录分组的制表符分隔文本，条目只保存名称以减小体积
+    pub fn save_to_file(&self, file_path: &Path) -> RtoolsResult<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            INDEX_MAGIC,
+            INDEX_VERSION,
+            escape(&self.root.to_string_lossy()),
+            self.updated_at.to_rfc3339()
+        )?;
+
+        for (relative, dir) in &self.dirs {
+            writeln!(writer, "D\t{}\t{}", escape(relative), dir.modified)?;
+            for entry in &dir.entries {
+                // 符号链接的类型代码后接目标的类型代码，如 `lf`
+                let target_code = entry.target_type.map(type_code).unwrap_or_default();
+                writeln!(
+                    writer,
+                    "{}{}\t{}\t{}\t{}",
+                    type_code(entry.entry_type),
+                    target_code,
+                    escape(&entry.name),
+                    entry.size,
+                    entry.modified
+                )?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load_from_file(file_path: &Path) -> RtoolsResult<Self> {
+        let file = File::open(file_path)
+            .map_err(|_| RtoolsError::FileNotFound(file_path.display().to_string()))?;
+        let mut lines = BufReader::new(file).lines();
+        let invalid = |line: usize, message: &str| {
+            RtoolsError::ParseError(format!("索引文件 {} 第{}行: {}", file_path.display(), line, message))
+        };
+
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let fields: Vec<&str> = header.split('\t').collect();
+        if fields.len() != 4 || fields[0] != INDEX_MAGIC {
+            return Err(invalid(1, "不是rtools索引文件"));
+        }
+        if fields[1] != INDEX_VERSION.to_string() {
+            return Err(invalid(1, &format!("不支持的索引版本 {}", fields[1])));
+        }
+        let updated_at = DateTime::parse_from_rfc3339(fields[3])
+            .map_err(|_| invalid(1, "无效的更新时间"))?
+            .with_timezone(&Utc);
+
+        let mut index = Self {
+            root: PathBuf::from(unescape(fields[2])),
+            updated_at,
+            dirs: BTreeMap::new(),
+        };
+        let mut current: Option<String> = None;
+
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let line_number = i + 2;
+            let fields: Vec<&str> = line.split('\t').collect();
+
+            match fields.as_slice() {
+                ["D", relative, modified] => {
+                    let modified = modified.parse().map_err(|_| invalid(line_number, "无效的修改时间"))?;
+                    let relative = unescape(relative);
+                    index.dirs.insert(relative.clone(), IndexedDir { modified, entries: Vec::new() });
+                    current = Some(relative);
+                }
+                [code, name, size, modified] => {
+                    let invalid_type = || invalid(line_number, "无效的条目类型");
+                    let (entry_type, target_type) = match code.strip_prefix('l') {
+                        Some("") => (EntryType::Symlink, None),
+                        Some(target) => (EntryType::Symlink, Some(parse_type_code(target).ok_or_else(invalid_type)?)),
+                        None => (parse_type_code(code).ok_or_else(invalid_type)?, None),
+                    };
+                    let entry = IndexEntry {
+                        name: unescape(name),
+                        entry_type,
+                        target_type,
+                        size: size.parse().map_err(|_| invalid(line_number, "无效的大小"))?,
+                        modified: modified.parse().map_err(|_| invalid(line_number, "无效的修改时间"))?,
+                    };
+                    let dir = current
+                        .as_ref()
+                        .and_then(|key| index.dirs.get_mut(key))
+                        .ok_or_else(|| invalid(line_number, "条目不属于任何目录"))?;
+                    dir.entries.push(entry);
+                }
+                _ => return Err(invalid(line_number, "格式错误")),
+            }
+        }
+
+        Ok(index)
+    }
+}
+
+/// 读取一个目录的直接条目，不跟随符号链接
+fn read_dir_entries(path: &Path, modified: u64, is_root: bool) -> RtoolsResult<IndexedDir> {
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_root && name == INDEX_FILE_NAME {
+            continue;
+        }
+
+        if let Some(indexed) = stat_entry(name, &entry.path()) {
+            entries.push(indexed);
+        }
+    }
+
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(IndexedDir { modified, entries })
+}
+
+/// 读取条目的元数据，符号链接同时记录目标的类型；无法读取时返回 `None`
+fn stat_entry(name: String, path: &Path) -> Option<IndexEntry> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    let entry_type = EntryType::from_file_type(metadata.file_type());
+    let (target_type, metadata) = match entry_type {
+        EntryType::Symlink => match fs::metadata(path) {
+            Ok(target) => (Some(EntryType::from_file_type(target.file_type())), target),
+            Err(_) => (None, metadata),
+        },
+        _ => (None, metadata),
+    };
+
+    Some(IndexEntry {
+        name,
+        entry_type,
+        target_type,
+        size: metadata.len(),
+        modified: metadata.modified().map(to_nanos).unwrap_or(0),
+    })
+}
+
+/// 索引不保存内容、所有者和权限等信息，这些条件需要直接搜索磁盘
+fn check_supported(criteria: &SearchCriteria) -> RtoolsResult<()> {
+    let unsupported = [
+        (criteria.content.is_some(), "内容搜索"),
+        (criteria.accessed.is_set(), "访问时间"),
+        (criteria.created.is_set(), "创建时间"),
+        (criteria.owner.is_some() || criteria.group.is_some(), "所有者"),
+        (!criteria.permissions.is_empty(), "权限"),
+        (criteria.empty, "空文件"),
+        (criteria.expression.is_some(), "过滤表达式"),
+    ];
+
+    match unsupported.iter().find(|(set, _)| *set) {
+        Some((_, name)) => Err(RtoolsError::InvalidArgument(format!("索引搜索不支持{}条件", name))),
+        None => Ok(()),
+    }
+}
+
+fn matches_details(entry: &IndexEntry, criteria: &SearchCriteria) -> bool {
+    if criteria.min_size.is_some_and(|min| entry.size < min)
+        || criteria.max_size.is_some_and(|max| entry.size > max) {
+        return false;
+    }
+
+    if criteria.modified.is_set() {
+        return from_nanos(entry.modified).is_some_and(|time| criteria.modified.contains(time));
+    }
+
+    true
+}
+
+/// `key` 是否等于 `prefix` 或位于其下
+fn is_within(key: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || key == prefix
+        || (key.starts_with(prefix) && key.as_bytes().get(prefix.len()) == Some(&b'/'))
+}
+
+fn relative_key(path: &Path, root: &Path) -> String {
+    crate::filesearch::relative_path_string(path, root)
+}
+
+fn to_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos().min(u64::MAX as u128) as u64)
+        .unwrap_or(0)
+}
+
+fn from_nanos(nanos: u64) -> Option<SystemTime> {
+    (nanos != 0).then(|| UNIX_EPOCH + Duration::from_nanos(nanos))
+}
+
+fn type_code(entry_type: EntryType) -> &'static str {
+    match entry_type {
+        EntryType::File => "f",
+        EntryType::Dir => "d",
+        EntryType::Symlink => "l",
+        EntryType::Socket => "s",
+        EntryType::Fifo => "p",
+        EntryType::Other => "o",
+    }
+}
+
+fn parse_type_code(code: &str) -> Option<EntryType> {
+    match code {
+        "o" => Some(EntryType::Other),
+        code => EntryType::parse(code),
+    }
+}
+
+/// 转义名称中的反斜杠、制表符和换行符
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
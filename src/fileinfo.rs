@@ -15,7 +15,6 @@ pub fn get_file_info(path: &str) -> RtoolsResult<String> {
     let is_file = metadata.is_file();
     let is_dir = metadata.is_dir();
 
-    path_obj.file_name().
     let file_name = path_obj.file_name()
         .and_then(|name| name.to_str())
         .unwrap_or("未知文件名");
@@ -1,8 +1,357 @@
 
-use std::fs;
+use std::cmp::Ordering;
+use std::fs::{self, Metadata};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use crate::dirscan::{format_size, parse_age};
+use crate::filterexpr::{EntryContext, FilterExpr};
+use crate::fuzzy::FuzzyQuery;
+use crate::grep::{ContentMode, ContentQuery, FileMatches, search_content};
+use crate::pattern::NamePattern;
 use crate::{RtoolsResult, RtoolsError};
 
+/// 目录条目的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryType {
+    File,
+    Dir,
+    Symlink,
+    Socket,
+    Fifo,
+    Other,
+}
+
+impl EntryType {
+    /// 解析 `find -type` 风格的类型名: f/file, d/dir, l/symlink, s/socket, p/fifo
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "f" | "file" => Some(Self::File),
+            "d" | "dir" => Some(Self::Dir),
+            "l" | "symlink" => Some(Self::Symlink),
+            "s" | "socket" => Some(Self::Socket),
+            "p" | "fifo" => Some(Self::Fifo),
+            _ => None,
+        }
+    }
+    
+    pub(crate) fn from_file_type(file_type: fs::FileType) -> Self {
+        if file_type.is_file() {
+            return Self::File;
+        }
+        if file_type.is_dir() {
+            return Self::Dir;
+        }
+        if file_type.is_symlink() {
+            return Self::Symlink;
+        }
+        
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_socket() {
+                return Self::Socket;
+            }
+            if file_type.is_fifo() {
+                return Self::Fifo;
+            }
+        }
+        
+        Self::Other
+    }
+}
+
+/// 权限位匹配方式，与 `find -perm` 的三种写法对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionMatch {
+    /// `MODE`: 权限位完全相同
+    Exact(u32),
+    /// `-MODE`: 包含所有指定的位
+    All(u32),
+    /// `/MODE`: 包含任意一个指定的位
+    Any(u32),
+}
+
+impl PermissionMatch {
+    /// 任何人可写
+    pub const WORLD_WRITABLE: Self = Self::All(0o002);
+    /// 任何一类用户可执行
+    pub const EXECUTABLE: Self = Self::Any(0o111);
+    
+    /// 解析八进制权限，如 `644`、`-002`、`/111`
+    pub fn parse(s: &str) -> RtoolsResult<Self> {
+        let (constructor, digits): (fn(u32) -> Self, &str) = if let Some(rest) = s.strip_prefix('-') {
+            (Self::All, rest)
+        } else if let Some(rest) = s.strip_prefix('/') {
+            (Self::Any, rest)
+        } else {
+            (Self::Exact, s)
+        };
+        
+        u32::from_str_radix(digits, 8)
+            .ok()
+            .filter(|mode| *mode <= 0o7777)
+            .map(constructor)
+            .ok_or_else(|| RtoolsError::InvalidArgument(format!("无效的权限模式: {}", s)))
+    }
+    
+    pub fn matches(&self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+        match *self {
+            Self::Exact(bits) => mode == bits,
+            Self::All(bits) => mode & bits == bits,
+            Self::Any(bits) => bits == 0 || mode & bits != 0,
+        }
+    }
+}
+
+/// 解析文件大小，如 `512`、`10K`、`1.5MiB`、`2G`
+///
+/// `K`/`M`/`G`/`T`（可带 `iB` 后缀）以1024为单位，`KB`/`MB`/`GB`/`TB` 以1000为单位，
+/// 大小写不敏感，数值可以带小数。
+pub fn parse_size(s: &str) -> RtoolsResult<u64> {
+    parse_size_with_unit(s).map(|(bytes, _)| bytes)
+}
+
+/// 解析文件大小，同时返回单位的字节数
+fn parse_size_with_unit(s: &str) -> RtoolsResult<(u64, u64)> {
+    let s = s.trim();
+    let number_end = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(number_end);
+    
+    let multiplier: u64 = match unit.trim_start().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KIB" => 1 << 10,
+        "M" | "MIB" => 1 << 20,
+        "G" | "GIB" => 1 << 30,
+        "T" | "TIB" => 1 << 40,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        _ => return Err(RtoolsError::InvalidArgument(format!("无效的大小单位 '{}': {}", unit, s))),
+    };
+    
+    if number.is_empty() {
+        return Err(RtoolsError::InvalidArgument(format!("无效的大小: '{}'", s)));
+    }
+    
+    let bytes = if number.contains('.') {
+        let value: f64 = number
+            .parse()
+            .map_err(|_| RtoolsError::InvalidArgument(format!("无效的大小: '{}'", s)))?;
+        let bytes = (value * multiplier as f64).round();
+        (bytes < u64::MAX as f64).then_some(bytes as u64)
+    } else {
+        number.parse::<u64>().ok().and_then(|value| value.checked_mul(multiplier))
+    };
+    
+    bytes
+        .map(|bytes| (bytes, multiplier))
+        .ok_or_else(|| RtoolsError::InvalidArgument(format!("大小超出范围: '{}'", s)))
+}
+
+/// 解析大小范围，返回 `(最小值, 最大值)`，两端均包含
+///
+/// - `10M`: 与 `find -size` 相同，按单位向上取整后等于该值，即大于 9M 且不超过 10M；不带单位时恰好等于
+/// - `1K-10M`、`10M-`: 闭区间和只有下限的区间
+/// - `+100M`/`-1K`: 与 `find -size` 相同，表示大于/小于
+/// - `>1G`、`>=1G`、`<1K`、`<=1K`
+pub fn parse_size_range(s: &str) -> RtoolsResult<(Option<u64>, Option<u64>)> {
+    let s = s.trim();
+    
+    let greater_than = |value: &str| -> RtoolsResult<(Option<u64>, Option<u64>)> {
+        let size = parse_size(value)?;
+        let min = size
+            .checked_add(1)
+            .ok_or_else(|| RtoolsError::InvalidArgument(format!("大小超出范围: '{}'", s)))?;
+        Ok((Some(min), None))
+    };
+    let less_than = |value: &str| -> RtoolsResult<(Option<u64>, Option<u64>)> {
+        let size = parse_size(value)?;
+        let max = size
+            .checked_sub(1)
+            .ok_or_else(|| RtoolsError::InvalidArgument(format!("没有小于0的大小: '{}'", s)))?;
+        Ok((None, Some(max)))
+    };
+    
+    if let Some(value) = s.strip_prefix(">=") {
+        return Ok((Some(parse_size(value)?), None));
+    }
+    if let Some(value) = s.strip_prefix("<=") {
+        return Ok((None, Some(parse_size(value)?)));
+    }
+    if let Some(value) = s.strip_prefix('>').or_else(|| s.strip_prefix('+')) {
+        return greater_than(value);
+    }
+    if let Some(value) = s.strip_prefix('<').or_else(|| s.strip_prefix('-')) {
+        return less_than(value);
+    }
+    
+    match s.split_once('-') {
+        Some((min, "")) => Ok((Some(parse_size(min)?), None)),
+        Some((min, max)) => {
+            let (min, max) = (parse_size(min)?, parse_size(max)?);
+            if min > max {
+                return Err(RtoolsError::InvalidArgument(format!("大小范围的下限大于上限: '{}'", s)));
+            }
+            Ok((Some(min), Some(max)))
+        }
+        None => {
+            if s.contains('.') {
+                return Err(RtoolsError::InvalidArgument(format!(
+                    "单个大小不能带小数，请用 +N、-N 或 N-M 指定范围: '{}'", s
+                )));
+            }
+            let (size, unit) = parse_size_with_unit(s)?;
+            Ok((Some(size.saturating_sub(unit - 1)), Some(size)))
+        }
+    }
+}
+
+/// 解析时间条件: 相对时长（如 `7d`、`12h`，表示距今）或本地时间的绝对日期
+/// （`2024-01-31`、`2024-01-31 08:00:00`、`2024-01-31T08:00:00`）
+pub fn parse_time_spec(s: &str) -> RtoolsResult<SystemTime> {
+    let s = s.trim();
+    
+    if s.starts_with(|c: char| c.is_ascii_digit()) && !s.contains('-') {
+        let age = parse_age(s)?;
+        return SystemTime::now()
+            .checked_sub(age)
+            .ok_or_else(|| RtoolsError::InvalidArgument(format!("时长超出范围: {}", s)));
+    }
+    
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| RtoolsError::InvalidArgument(format!("无效的时间: {}", s)))?;
+    
+    Local.from_local_datetime(&naive)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| RtoolsError::InvalidArgument(format!("无效的本地时间: {}", s)))
+}
+
+/// 按用户名或数字ID查找用户ID
+pub fn lookup_user_id(name: &str) -> RtoolsResult<u32> {
+    lookup_id("/etc/passwd", name)
+        .ok_or_else(|| RtoolsError::InvalidArgument(format!("未知用户: {}", name)))
+}
+
+/// 按组名或数字ID查找组ID
+pub fn lookup_group_id(name: &str) -> RtoolsResult<u32> {
+    lookup_id("/etc/group", name)
+        .ok_or_else(|| RtoolsError::InvalidArgument(format!("未知用户组: {}", name)))
+}
+
+/// 在 `name:x:id:...` 格式的数据库文件中查找ID
+fn lookup_id(database: &str, name: &str) -> Option<u32> {
+    if let Ok(id) = name.parse::<u32>() {
+        return Some(id);
+    }
+    
+    let content = fs::read_to_string(database).ok()?;
+    content
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() > 2 && fields[0] == name)
+        .and_then(|fields| fields[2].parse().ok())
+}
+
+/// 时间范围条件，两端均为开区间
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeRange {
+    pub after: Option<SystemTime>,
+    pub before: Option<SystemTime>,
+}
+
+impl TimeRange {
+    pub fn is_set(&self) -> bool {
+        self.after.is_some() || self.before.is_some()
+    }
+    
+    pub fn contains(&self, time: SystemTime) -> bool {
+        self.after.is_none_or(|after| time > after)
+            && self.before.is_none_or(|before| time < before)
+    }
+}
+
+/// 搜索结果的排序依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Path,
+    Name,
+    Size,
+    Modified,
+    Extension,
+    /// 模糊匹配得分，得分高的排在前面
+    Score,
+}
+
+impl SortKey {
+    /// 解析排序键: path, name, size, mtime, ext, score
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "score" => Some(Self::Score),
+            "path" => Some(Self::Path),
+            "name" => Some(Self::Name),
+            "size" => Some(Self::Size),
+            "mtime" | "modified" | "time" => Some(Self::Modified),
+            "ext" | "extension" => Some(Self::Extension),
+            _ => None,
+        }
+    }
+}
+
+/// 结果列表中路径的显示方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathDisplay {
+    /// 只显示文件名
+    Name,
+    /// 相对于搜索目录的路径
+    #[default]
+    Relative,
+    /// 绝对路径
+    Absolute,
+}
+
+/// 结果列表的输出格式
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListFormat {
+    pub path_display: PathDisplay,
+    /// 显示文件大小列
+    pub show_size: bool,
+    /// 显示修改时间列
+    pub show_modified: bool,
+}
+
+impl ListFormat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn with_path_display(mut self, path_display: PathDisplay) -> Self {
+        self.path_display = path_display;
+        self
+    }
+    
+    pub fn with_size(mut self, show: bool) -> Self {
+        self.show_size = show;
+        self
+    }
+    
+    pub fn with_modified(mut self, show: bool) -> Self {
+        self.show_modified = show;
+        self
+    }
+}
+
 /// 搜索条件
 #[derive(Debug, Clone, Default)]
 pub struct SearchCriteria {
@@ -11,6 +360,33 @@ pub struct SearchCriteria {
     pub min_size: Option<u64>,
     pub max_size: Option<u64>,
     pub max_depth: Option<usize>,
+    /// `name_pattern` 是否区分大小写
+    pub case_sensitive: bool,
+    /// 文件至少匹配其中一个模式（为空时不限制）
+    pub include_patterns: Vec<NamePattern>,
+    /// 匹配其中任意一个模式的文件被排除
+    pub exclude_patterns: Vec<NamePattern>,
+    /// 文件内容需要匹配的条件，最后检查
+    pub content: Option<ContentQuery>,
+    pub modified: TimeRange,
+    pub accessed: TimeRange,
+    pub created: TimeRange,
+    /// 允许的条目类型，为空时只匹配普通文件（跟随符号链接）
+    pub entry_types: Vec<EntryType>,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+    /// 权限条件，需要全部满足
+    pub permissions: Vec<PermissionMatch>,
+    /// 只匹配空文件和空目录
+    pub empty: bool,
+    /// 组合条件表达式，在其他条件都满足后求值
+    pub expression: Option<FilterExpr>,
+    /// 排序依据和是否降序，未设置时保持遍历顺序（模糊搜索时按得分排序）
+    pub sort: Option<(SortKey, bool)>,
+    /// 模糊查询，匹配相对路径或文件名
+    pub fuzzy: Option<FuzzyQuery>,
+    /// 最多返回的结果数
+    pub limit: Option<usize>,
 }
 
 impl SearchCriteria {
@@ -38,15 +414,147 @@ impl SearchCriteria {
         self.max_depth = Some(depth);
         self
     }
+    
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+    
+    pub fn with_include(mut self, pattern: NamePattern) -> Self {
+        self.include_patterns.push(pattern);
+        self
+    }
+    
+    pub fn with_exclude(mut self, pattern: NamePattern) -> Self {
+        self.exclude_patterns.push(pattern);
+        self
+    }
+    
+    pub fn with_content(mut self, query: ContentQuery) -> Self {
+        self.content = Some(query);
+        self
+    }
+    
+    pub fn with_modified(mut self, after: Option<SystemTime>, before: Option<SystemTime>) -> Self {
+        self.modified = TimeRange { after, before };
+        self
+    }
+    
+    pub fn with_accessed(mut self, after: Option<SystemTime>, before: Option<SystemTime>) -> Self {
+        self.accessed = TimeRange { after, before };
+        self
+    }
+    
+    pub fn with_created(mut self, after: Option<SystemTime>, before: Option<SystemTime>) -> Self {
+        self.created = TimeRange { after, before };
+        self
+    }
+    
+    pub fn with_entry_type(mut self, entry_type: EntryType) -> Self {
+        self.entry_types.push(entry_type);
+        self
+    }
+    
+    pub fn with_owner(mut self, uid: u32) -> Self {
+        self.owner = Some(uid);
+        self
+    }
+    
+    pub fn with_group(mut self, gid: u32) -> Self {
+        self.group = Some(gid);
+        self
+    }
+    
+    pub fn with_permission(mut self, permission: PermissionMatch) -> Self {
+        self.permissions.push(permission);
+        self
+    }
+    
+    pub fn with_empty(mut self, empty: bool) -> Self {
+        self.empty = empty;
+        self
+    }
+    
+    pub fn with_expression(mut self, expression: FilterExpr) -> Self {
+        self.expression = Some(expression);
+        self
+    }
+    
+    pub fn with_sort(mut self, key: SortKey, descending: bool) -> Self {
+        self.sort = Some((key, descending));
+        self
+    }
+    
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+    
+    pub fn with_fuzzy(mut self, query: FuzzyQuery) -> Self {
+        self.fuzzy = Some(query);
+        self
+    }
+    
+    /// 实际使用的排序方式: 未指定排序时，模糊搜索的结果按得分排序
+    pub(crate) fn effective_sort(&self) -> Option<(SortKey, bool)> {
+        self.sort.or_else(|| self.fuzzy.as_ref().map(|_| (SortKey::Score, false)))
+    }
+    
+    pub(crate) fn accepts_type(&self, entry_type: EntryType) -> bool {
+        if self.entry_types.is_empty() {
+            // 默认只接受文件，表达式中肯定的类型条件可以接受其他类型；`NOT type d` 不会放入其他类型
+            entry_type == EntryType::File
+                || self.expression.as_ref().is_some_and(|e| e.allowed_types().contains(&entry_type))
+        } else {
+            self.entry_types.contains(&entry_type)
+        }
+    }
+    
+    /// 是否按符号链接本身而不是链接目标进行匹配
+    pub(crate) fn wants_symlinks(&self) -> bool {
+        self.entry_types.contains(&EntryType::Symlink)
+            || self.expression.as_ref().is_some_and(|e| e.mentioned_types().contains(&EntryType::Symlink))
+    }
+    
+    /// 是否有需要读取元数据才能判断的条件
+    fn needs_metadata(&self) -> bool {
+        self.min_size.is_some()
+            || self.max_size.is_some()
+            || self.modified.is_set()
+            || self.accessed.is_set()
+            || self.created.is_set()
+            || self.owner.is_some()
+            || self.group.is_some()
+            || !self.permissions.is_empty()
+            || self.empty
+    }
+}
+
+/// 匹配文件的大小、修改时间和模糊匹配得分，用于排序和列表输出
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileDetails {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// 设置了模糊查询时的得分
+    pub score: Option<i64>,
 }
 
 /// 搜索结果
 #[derive(Debug, Default)]
 pub struct SearchResult {
+    /// 搜索的根目录
+    pub root: PathBuf,
     pub files: Vec<PathBuf>,
+    /// 与 `files` 一一对应
+    pub details: Vec<FileDetails>,
     pub total_count: usize,
     pub total_size: u64,
     pub search_time_ms: u128,
+    /// 内容搜索的匹配结果，与 `files` 一一对应
+    pub content_matches: Vec<FileMatches>,
+    pub content_mode: Option<ContentMode>,
+    /// 搜索内容或对表达式求值时无法读取的文件和目录，这些条目视为不匹配
+    pub warnings: Vec<String>,
 }
 
 impl SearchResult {
@@ -54,7 +562,84 @@ impl SearchResult {
         Self::default()
     }
     
+    /// 添加一个流式搜索产生的匹配
+    pub fn push(&mut self, found: SearchMatch) {
+        self.files.push(found.path);
+        self.details.push(found.details);
+        self.total_size += found.details.size;
+        self.total_count = self.files.len();
+        if let Some(content) = found.content {
+            self.content_matches.push(content);
+        }
+    }
+    
+    /// 按指定的键排序，键相同时按路径排序
+    pub fn sort_by(&mut self, key: SortKey, descending: bool) {
+        let mut order: Vec<usize> = (0..self.files.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (path_a, path_b) = (&self.files[a], &self.files[b]);
+            let (details_a, details_b) = (&self.details[a], &self.details[b]);
+            let ordering = match key {
+                SortKey::Path => Ordering::Equal,
+                SortKey::Name => path_a.file_name().cmp(&path_b.file_name()),
+                SortKey::Size => details_a.size.cmp(&details_b.size),
+                SortKey::Modified => details_a.modified.cmp(&details_b.modified),
+                SortKey::Extension => path_a.extension().cmp(&path_b.extension()),
+                // 得分相同时较短的路径更接近查询
+                SortKey::Score => details_b.score
+                    .cmp(&details_a.score)
+                    .then_with(|| path_a.as_os_str().len().cmp(&path_b.as_os_str().len())),
+            };
+            let ordering = ordering.then_with(|| path_a.cmp(path_b));
+            if descending { ordering.reverse() } else { ordering }
+        });
+        
+        self.files = order.iter().map(|&i| self.files[i].clone()).collect();
+        self.details = order.iter().map(|&i| self.details[i]).collect();
+        if self.content_matches.len() == order.len() {
+            self.content_matches = order.iter().map(|&i| self.content_matches[i].clone()).collect();
+        }
+    }
+    
+    /// 按搜索条件中的排序和数量限制整理结果
+    pub(crate) fn apply_order(&mut self, sort: Option<(SortKey, bool)>, limit: Option<usize>) {
+        if let Some((key, descending)) = sort {
+            self.sort_by(key, descending);
+        }
+        self.total_count = self.files.len();
+        if let Some(limit) = limit {
+            self.truncate(limit);
+        }
+    }
+    
+    /// 只保留前 `limit` 个结果，并重新计算数量和总大小
+    pub fn truncate(&mut self, limit: usize) {
+        self.files.truncate(limit);
+        self.details.truncate(limit);
+        self.content_matches.truncate(limit);
+        self.total_count = self.files.len();
+        self.total_size = self.details.iter().map(|d| d.size).sum();
+    }
+    
+    /// 按显示方式格式化路径
+    pub fn display_path(&self, path: &Path, path_display: PathDisplay) -> String {
+        match path_display {
+            PathDisplay::Name => path.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "未知".to_string()),
+            PathDisplay::Relative => relative_path_string(path, &self.root),
+            PathDisplay::Absolute => fs::canonicalize(path)
+                .unwrap_or_else(|_| path.to_path_buf())
+                .display()
+                .to_string(),
+        }
+    }
+    
     pub fn print_results(&self) {
+        self.print_results_with(&ListFormat::default());
+    }
+    
+    pub fn print_results_with(&self, format: &ListFormat) {
         println!("搜索结果:");
         println!("- 找到文件数: {}", self.total_count);
         println!("- 总大小: {} 字节 ({:.2} MB)", 
@@ -62,21 +647,222 @@ impl SearchResult {
                  self.total_size as f64 / 1024.0 / 1024.0);
         println!("- 搜索耗时: {} ms", self.search_time_ms);
         
+        match self.content_mode {
+            Some(ContentMode::Lines) => {
+                let match_count: usize = self.content_matches.iter().map(|m| m.match_count).sum();
+                println!("- 匹配行数: {}", match_count);
+                println!();
+                for file_matches in &self.content_matches {
+                    file_matches.print_lines();
+                }
+                return;
+            }
+            Some(ContentMode::CountOnly) => {
+                println!();
+                for file_matches in &self.content_matches {
+                    println!("{}:{}", file_matches.path.display(), file_matches.match_count);
+                }
+                return;
+            }
+            Some(ContentMode::FilesWithMatches) | None => {}
+        }
+        
         if !self.files.is_empty() {
             println!("\n找到的文件:");
-            for (i, file) in self.files.iter().enumerate() {
-                let file_name = file.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("未知");
-                println!("  {}. {}", i + 1, file_name);
+            for (i, (file, details)) in self.files.iter().zip(&self.details).enumerate() {
+                let mut columns = Vec::new();
+                if format.show_size {
+                    columns.push(format!("{:>10}", format_size(details.size)));
+                }
+                if format.show_modified {
+                    let modified = details.modified
+                        .map(|time| DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_else(|| "-".repeat(16));
+                    columns.push(modified);
+                }
+                if let Some(score) = details.score {
+                    columns.push(format!("[{:>4}]", score));
+                }
+                columns.push(self.display_path(file, format.path_display));
+                println!("  {}. {}", i + 1, columns.join("  "));
             }
         }
     }
 }
 
-/// 在指定目录中搜索文件
-pub fn search_files(dir_path: &str, criteria: SearchCriteria) -> RtoolsResult<SearchResult> {
-    let start_time = std::time::Instant::now();
+/// 流式搜索产生的一个匹配
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub path: PathBuf,
+    pub entry_type: EntryType,
+    pub details: FileDetails,
+    /// 设置了内容搜索条件时的匹配结果
+    pub content: Option<FileMatches>,
+}
+
+/// 搜索进度
+#[derive(Debug, Clone, Default)]
+pub struct SearchProgress {
+    /// 已开始读取的目录数（包括搜索根目录）
+    pub dirs_visited: usize,
+    /// 已检查的目录条目数
+    pub entries_checked: usize,
+    pub matches: usize,
+    /// 最近开始读取的目录
+    pub current_dir: PathBuf,
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(&SearchProgress) + 'a>;
+
+/// 逐个产生匹配结果的搜索迭代器，按深度优先的顺序遍历目录
+///
+/// 找到匹配即返回，调用者可以随时停止迭代；遇到无法读取的条目时产生错误并继续遍历。
+/// 设置了 `limit` 时，产生指定数量的匹配后结束。
+pub struct SearchIter<'a> {
+    root: PathBuf,
+    criteria: SearchCriteria,
+    max_depth: usize,
+    /// 正在读取的目录及其中条目的深度
+    stack: Vec<(fs::ReadDir, usize)>,
+    /// 下一次调用 `next` 时需要打开的目录
+    pending_dir: Option<(PathBuf, usize)>,
+    progress: SearchProgress,
+    on_progress: Option<ProgressCallback<'a>>,
+    warnings: Vec<String>,
+}
+
+impl<'a> SearchIter<'a> {
+    /// 每开始读取一个目录时调用 `callback` 报告进度
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&SearchProgress) + 'a,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+    
+    pub fn progress(&self) -> &SearchProgress {
+        &self.progress
+    }
+    
+    pub fn criteria(&self) -> &SearchCriteria {
+        &self.criteria
+    }
+    
+    /// 搜索内容或对表达式求值时无法读取的文件和目录，和 `grep -r` 一样跳过这些条目而不中止搜索
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+    
+    fn open_dir(&mut self, dir: PathBuf, depth: usize) -> RtoolsResult<()> {
+        let entries = fs::read_dir(&dir)?;
+        self.stack.push((entries, depth));
+        self.progress.dirs_visited += 1;
+        self.progress.current_dir = dir;
+        if let Some(callback) = self.on_progress.as_mut() {
+            callback(&self.progress);
+        }
+        Ok(())
+    }
+    
+    /// 检查一个条目，匹配时返回匹配结果；是目录时安排在之后读取
+    fn check_entry(&mut self, entry: fs::DirEntry, depth: usize) -> RtoolsResult<Option<SearchMatch>> {
+        let path = entry.path();
+        self.progress.entries_checked += 1;
+        
+        // 除非明确搜索符号链接，否则按链接目标的类型处理
+        let mut entry_type = EntryType::from_file_type(entry.file_type()?);
+        if entry_type == EntryType::Symlink
+            && !self.criteria.wants_symlinks()
+            && let Ok(target) = fs::metadata(&path) {
+            entry_type = EntryType::from_file_type(target.file_type());
+        }
+        
+        if entry_type == EntryType::Dir && depth < self.max_depth {
+            self.pending_dir = Some((path.clone(), depth + 1));
+        }
+        
+        if !self.criteria.accepts_type(entry_type) {
+            return Ok(None);
+        }
+        let Some(score) = fuzzy_score(&path, &self.root, &self.criteria) else {
+            return Ok(None);
+        };
+        if !matches_criteria(&path, &self.root, entry_type, &self.criteria, &mut self.warnings)? {
+            return Ok(None);
+        }
+        
+        // 读取文件内容的开销最大，放在所有元数据条件之后
+        let content = match &self.criteria.content {
+            Some(query) if entry_type == EntryType::File => match search_content(&path, query) {
+                Ok(Some(file_matches)) => Some(file_matches),
+                Ok(None) => return Ok(None),
+                Err(e) => {
+                    self.warnings.push(format!("无法读取 {}: {}", path.display(), e));
+                    return Ok(None);
+                }
+            },
+            Some(_) => return Ok(None),
+            None => None,
+        };
+        
+        let metadata = fs::symlink_metadata(&path)?;
+        self.progress.matches += 1;
+        Ok(Some(SearchMatch {
+            path,
+            entry_type,
+            details: FileDetails {
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+                score,
+            },
+            content,
+        }))
+    }
+    
+    fn is_done(&self) -> bool {
+        self.criteria.effective_sort().is_none()
+            && self.criteria.limit.is_some_and(|limit| self.progress.matches >= limit)
+    }
+}
+
+impl Iterator for SearchIter<'_> {
+    type Item = RtoolsResult<SearchMatch>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.is_done() {
+                return None;
+            }
+            
+            // 目录在其自身被检查之后、同级的下一个条目之前读取，保持深度优先的顺序
+            if let Some((dir, depth)) = self.pending_dir.take()
+                && let Err(e) = self.open_dir(dir, depth) {
+                return Some(Err(e));
+            }
+            
+            let (entries, depth) = self.stack.last_mut()?;
+            let depth = *depth;
+            let entry = match entries.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            
+            match self.check_entry(entry, depth) {
+                Ok(Some(found)) => return Some(Ok(found)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// 创建流式搜索迭代器
+pub fn search_iter<'a>(dir_path: &str, criteria: SearchCriteria) -> RtoolsResult<SearchIter<'a>> {
     let path = Path::new(dir_path);
     
     if !path.exists() {
@@ -87,96 +873,232 @@ pub fn search_files(dir_path: &str, criteria: SearchCriteria) -> RtoolsResult<Se
         return Err(RtoolsError::NotADirectory(dir_path.to_string()));
     }
     
-    let mut result = SearchResult::new();
     let max_depth = criteria.max_depth.unwrap_or(usize::MAX);
+    Ok(SearchIter {
+        root: path.to_path_buf(),
+        criteria,
+        max_depth,
+        stack: Vec::new(),
+        pending_dir: Some((path.to_path_buf(), 0)),
+        progress: SearchProgress::default(),
+        on_progress: None,
+        warnings: Vec::new(),
+    })
+}
+
+/// 流式搜索，对每个匹配调用 `callback`，回调返回 `false` 时停止搜索
+///
+/// 返回找到的匹配数。
+pub fn search_files_with<F>(dir_path: &str, criteria: SearchCriteria, mut callback: F) -> RtoolsResult<usize>
+where
+    F: FnMut(SearchMatch) -> bool,
+{
+    let mut count = 0;
+    for found in search_iter(dir_path, criteria)? {
+        count += 1;
+        if !callback(found?) {
+            break;
+        }
+    }
+    Ok(count)
+}
+
+/// 在指定目录中搜索文件
+pub fn search_files(dir_path: &str, criteria: SearchCriteria) -> RtoolsResult<SearchResult> {
+    let start_time = std::time::Instant::now();
+    let sort = criteria.effective_sort();
+    let limit = criteria.limit;
+    
+    let mut result = SearchResult::new();
+    result.root = PathBuf::from(dir_path);
+    result.content_mode = criteria.content.as_ref().map(|query| query.mode);
     
-    search_files_recursive(path, &criteria, &mut result, 0, max_depth)?;
+    let mut iter = search_iter(dir_path, criteria)?;
+    for found in &mut iter {
+        result.push(found?);
+    }
+    result.warnings = iter.warnings().to_vec();
     
+    result.apply_order(sort, limit);
     result.search_time_ms = start_time.elapsed().as_millis();
-    result.total_count = result.files.len();
     
     Ok(result)
 }
 
-fn search_files_recursive(
-    dir_path: &Path,
+fn matches_criteria(
+    path: &Path,
+    root: &Path,
+    entry_type: EntryType,
     criteria: &SearchCriteria,
-    result: &mut SearchResult,
-    current_depth: usize,
-    max_depth: usize,
-) -> RtoolsResult<()> {
-    if current_depth > max_depth {
-        return Ok(());
-    }
+    warnings: &mut Vec<String>,
+) -> RtoolsResult<bool> {
+    let file_name = match path.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name,
+        None => return Ok(false),
+    };
     
-    let entries = fs::read_dir(dir_path)?;
+    if !matches_name(path, root, file_name, criteria) {
+        return Ok(false);
+    }
     
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
+    // 然后检查需要文件系统访问的条件
+    let mut metadata = None;
+    if criteria.needs_metadata() {
+        let entry_metadata = if entry_type == EntryType::Symlink {
+            fs::symlink_metadata(path)?
+        } else {
+            fs::metadata(path)?
+        };
         
-        if path.is_file() {
-            if matches_criteria(&path, criteria)? {
-                result.files.push(path.clone());
-                let metadata = fs::metadata(&path)?;
-                result.total_size += metadata.len();
-            }
-        } else if path.is_dir() && current_depth < max_depth {
-            search_files_recursive(&path, criteria, result, current_depth + 1, max_depth)?;
+        if !matches_metadata(path, entry_type, &entry_metadata, criteria)? {
+            return Ok(false);
+        }
+        metadata = Some(entry_metadata);
+    }
+    
+    // 最后对表达式求值，已读取的元数据直接复用
+    if let Some(ref expression) = criteria.expression {
+        let entry = EntryContext::new(path, root, file_name, entry_type).with_metadata(metadata);
+        let matched = expression.evaluate(&entry)?;
+        warnings.extend(entry.take_warnings());
+        if !matched {
+            return Ok(false);
         }
     }
     
-    Ok(())
+    Ok(true)
 }
 
-fn matches_criteria(path: &Path, criteria: &SearchCriteria) -> RtoolsResult<bool> {
+/// 计算模糊匹配得分: 未设置模糊查询时返回 `Some(None)`，不匹配时返回 `None`
+pub(crate) fn fuzzy_score(path: &Path, root: &Path, criteria: &SearchCriteria) -> Option<Option<i64>> {
+    let Some(fuzzy) = &criteria.fuzzy else {
+        return Some(None);
+    };
+    
+    let file_name = path.file_name()?.to_string_lossy();
+    let relative_path = relative_path_string(path, root);
+    fuzzy.score_path(&file_name, &relative_path).map(Some)
+}
+
+/// 检查只需要路径就能判断的条件: 扩展名、文件名和通配符/正则表达式
+pub(crate) fn matches_name(path: &Path, root: &Path, file_name: &str, criteria: &SearchCriteria) -> bool {
     // 首先检查扩展名（最快）
     if let Some(ref expected_ext) = criteria.extension {
-        if let Some(actual_ext) = path.extension() {
-            if let Some(ext_str) = actual_ext.to_str() {
-                if ext_str.to_lowercase() != expected_ext.to_lowercase() {
-                    return Ok(false);
-                }
-            } else {
-                return Ok(false);
-            }
-        } else {
-            return Ok(false);
+        let matched = path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.to_lowercase() == expected_ext.to_lowercase());
+        if !matched {
+            return false;
         }
     }
     
     // 然后检查文件名模式
     if let Some(ref pattern) = criteria.name_pattern {
-        if let Some(file_name) = path.file_name() {
-            if let Some(name_str) = file_name.to_str() {
-                if !name_str.to_lowercase().contains(&pattern.to_lowercase()) {
-                    return Ok(false);
-                }
-            } else {
-                return Ok(false);
-            }
+        let matched = if criteria.case_sensitive {
+            file_name.contains(pattern.as_str())
         } else {
-            return Ok(false);
+            file_name.to_lowercase().contains(&pattern.to_lowercase())
+        };
+        if !matched {
+            return false;
         }
     }
     
-    // 最后检查文件大小（需要文件系统访问）
-    if criteria.min_size.is_some() || criteria.max_size.is_some() {
-        let metadata = fs::metadata(path)?;
-        let file_size = metadata.len();
+    // 通配符和正则表达式作用于相对路径
+    if !criteria.include_patterns.is_empty() || !criteria.exclude_patterns.is_empty() {
+        let relative_path = relative_path_string(path, root);
         
-        if let Some(min_size) = criteria.min_size {
-            if file_size < min_size {
-                return Ok(false);
-            }
+        if !criteria.include_patterns.is_empty()
+            && !criteria.include_patterns.iter().any(|p| p.matches(file_name, &relative_path)) {
+            return false;
         }
         
-        if let Some(max_size) = criteria.max_size {
-            if file_size > max_size {
-                return Ok(false);
-            }
+        if criteria.exclude_patterns.iter().any(|p| p.matches(file_name, &relative_path)) {
+            return false;
+        }
+    }
+    
+    true
+}
+
+fn matches_metadata(
+    path: &Path,
+    entry_type: EntryType,
+    metadata: &Metadata,
+    criteria: &SearchCriteria,
+) -> RtoolsResult<bool> {
+    let file_size = metadata.len();
+    
+    if let Some(min_size) = criteria.min_size
+        && file_size < min_size {
+        return Ok(false);
+    }
+    
+    if let Some(max_size) = criteria.max_size
+        && file_size > max_size {
+        return Ok(false);
+    }
+    
+    if criteria.modified.is_set() && !criteria.modified.contains(metadata.modified()?) {
+        return Ok(false);
+    }
+    
+    if criteria.accessed.is_set() && !criteria.accessed.contains(metadata.accessed()?) {
+        return Ok(false);
+    }
+    
+    if criteria.created.is_set() && !created_time(metadata)?.is_some_and(|created| criteria.created.contains(created)) {
+        return Ok(false);
+    }
+    
+    if !matches_ownership(metadata, criteria) {
+        return Ok(false);
+    }
+    
+    if criteria.empty {
+        let is_empty = match entry_type {
+            EntryType::File => file_size == 0,
+            EntryType::Dir => fs::read_dir(path)?.next().is_none(),
+            _ => false,
+        };
+        if !is_empty {
+            return Ok(false);
         }
     }
     
     Ok(true)
-} 
\ No newline at end of file
+}
+
+/// 文件的创建时间；文件系统或平台不记录创建时间时返回 `None`，这样的条目不匹配创建时间条件
+pub(crate) fn created_time(metadata: &Metadata) -> io::Result<Option<SystemTime>> {
+    match metadata.created() {
+        Ok(created) => Ok(Some(created)),
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn matches_ownership(metadata: &Metadata, criteria: &SearchCriteria) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    
+    criteria.owner.is_none_or(|uid| metadata.uid() == uid)
+        && criteria.group.is_none_or(|gid| metadata.gid() == gid)
+        && criteria.permissions.iter().all(|p| p.matches(metadata.mode()))
+}
+
+/// 非Unix平台没有所有者和权限位，设置了这些条件时不匹配任何文件
+#[cfg(not(unix))]
+fn matches_ownership(_metadata: &Metadata, criteria: &SearchCriteria) -> bool {
+    criteria.owner.is_none() && criteria.group.is_none() && criteria.permissions.is_empty()
+}
+
+/// 计算相对于搜索根目录的路径，统一使用 `/` 作为分隔符
+pub(crate) fn relative_path_string(path: &Path, root: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,598 @@
+use std::cell::{OnceCell, RefCell};
+use std::fmt::Display;
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::time::SystemTime;
+#[cfg(unix)]
+use std::time::{Duration, UNIX_EPOCH};
+use crate::filesearch::{
+    EntryType, PermissionMatch, created_time, lookup_group_id, lookup_user_id, parse_size, parse_time_spec,
+    relative_path_string,
+};
+use crate::grep::{ContentMode, ContentQuery, search_content};
+use crate::pattern::NamePattern;
+use crate::{RtoolsResult, RtoolsError};
+
+/// 比较运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "<" => Some(Self::Less),
+            "<=" => Some(Self::LessOrEqual),
+            "=" | "==" => Some(Self::Equal),
+            "!=" => Some(Self::NotEqual),
+            ">=" => Some(Self::GreaterOrEqual),
+            ">" => Some(Self::Greater),
+            _ => None,
+        }
+    }
+
+    pub fn compare<T: PartialOrd>(&self, actual: T, expected: T) -> bool {
+        match self {
+            Self::Less => actual < expected,
+            Self::LessOrEqual => actual <= expected,
+            Self::Equal => actual == expected,
+            Self::NotEqual => actual != expected,
+            Self::GreaterOrEqual => actual >= expected,
+            Self::Greater => actual > expected,
+        }
+    }
+}
+
+/// 表达式中的单个条件
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    Extension(String),
+    /// 文件名包含子串、通配符或正则表达式
+    Name(NamePattern),
+    Type(EntryType),
+    Size(Comparison, u64),
+    Modified(Comparison, SystemTime),
+    Accessed(Comparison, SystemTime),
+    /// inode 变化时间（内容、权限、所有者或名称的变化），与 `find -ctime` 相同
+    Changed(Comparison, SystemTime),
+    /// 创建时间
+    Created(Comparison, SystemTime),
+    Owner(u32),
+    Group(u32),
+    Permission(PermissionMatch),
+    Empty,
+    /// 文件内容包含匹配（只判断是否存在匹配）
+    Content(ContentQuery),
+}
+
+impl Predicate {
+    /// 判断代价: 0 只需路径, 1 需要元数据, 2 需要读取目录, 3 需要读取文件内容
+    fn cost(&self) -> u8 {
+        match self {
+            Self::Extension(_) | Self::Name(_) | Self::Type(_) => 0,
+            Self::Empty => 2,
+            Self::Content(_) => 3,
+            _ => 1,
+        }
+    }
+
+    fn evaluate(&self, entry: &EntryContext) -> RtoolsResult<bool> {
+        let matched = match self {
+            Self::Extension(expected) => entry.path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(expected)),
+            Self::Name(pattern) => pattern.matches(entry.file_name, entry.relative_path()),
+            Self::Type(entry_type) => entry.entry_type == *entry_type,
+            Self::Size(op, size) => op.compare(entry.metadata()?.len(), *size),
+            Self::Modified(op, time) => op.compare(entry.metadata()?.modified()?, *time),
+            Self::Accessed(op, time) => op.compare(entry.metadata()?.accessed()?, *time),
+            Self::Changed(op, time) => changed_time(entry.metadata()?).is_some_and(|changed| op.compare(changed, *time)),
+            Self::Created(op, time) => created_time(entry.metadata()?)?.is_some_and(|created| op.compare(created, *time)),
+            Self::Owner(_) | Self::Group(_) | Self::Permission(_) => matches_ownership(self, entry.metadata()?),
+            // 无法读取的目录和文件按不匹配处理，并记录警告
+            Self::Empty => match entry.entry_type {
+                EntryType::File => entry.metadata()?.len() == 0,
+                EntryType::Dir => match fs::read_dir(entry.path) {
+                    Ok(mut entries) => entries.next().is_none(),
+                    Err(e) => entry.warn(e),
+                },
+                _ => false,
+            },
+            Self::Content(query) => {
+                entry.entry_type == EntryType::File && match search_content(entry.path, query) {
+                    Ok(found) => found.is_some(),
+                    Err(e) => entry.warn(e),
+                }
+            }
+        };
+        Ok(matched)
+    }
+}
+
+#[cfg(unix)]
+fn changed_time(metadata: &Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+
+    let seconds = u64::try_from(metadata.ctime()).ok()?;
+    let nanos = u32::try_from(metadata.ctime_nsec()).ok()?;
+    UNIX_EPOCH.checked_add(Duration::new(seconds, nanos))
+}
+
+/// 没有 inode 变化时间的平台上 `ctime` 条件不匹配任何条目
+#[cfg(not(unix))]
+fn changed_time(_metadata: &Metadata) -> Option<SystemTime> {
+    None
+}
+
+#[cfg(unix)]
+fn matches_ownership(predicate: &Predicate, metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match predicate {
+        Predicate::Owner(uid) => metadata.uid() == *uid,
+        Predicate::Group(gid) => metadata.gid() == *gid,
+        Predicate::Permission(permission) => permission.matches(metadata.mode()),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn matches_ownership(_predicate: &Predicate, _metadata: &Metadata) -> bool {
+    false
+}
+
+/// 被判断的目录条目，元数据和相对路径在第一次使用时才计算
+pub struct EntryContext<'a> {
+    pub path: &'a Path,
+    pub root: &'a Path,
+    pub file_name: &'a str,
+    pub entry_type: EntryType,
+    metadata: OnceCell<Metadata>,
+    relative_path: OnceCell<String>,
+    warnings: RefCell<Vec<String>>,
+}
+
+impl<'a> EntryContext<'a> {
+    pub fn new(path: &'a Path, root: &'a Path, file_name: &'a str, entry_type: EntryType) -> Self {
+        Self {
+            path,
+            root,
+            file_name,
+            entry_type,
+            metadata: OnceCell::new(),
+            relative_path: OnceCell::new(),
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// 提供已经读取过的元数据，避免重复访问文件系统
+    pub fn with_metadata(self, metadata: Option<Metadata>) -> Self {
+        if let Some(metadata) = metadata {
+            let _ = self.metadata.set(metadata);
+        }
+        self
+    }
+
+    pub fn metadata(&self) -> RtoolsResult<&Metadata> {
+        if let Some(metadata) = self.metadata.get() {
+            return Ok(metadata);
+        }
+
+        let metadata = if self.entry_type == EntryType::Symlink {
+            fs::symlink_metadata(self.path)?
+        } else {
+            fs::metadata(self.path)?
+        };
+        Ok(self.metadata.get_or_init(|| metadata))
+    }
+
+    /// 相对于搜索根目录的路径，使用 `/` 作为分隔符
+    pub fn relative_path(&self) -> &str {
+        self.relative_path.get_or_init(|| relative_path_string(self.path, self.root))
+    }
+
+    /// 求值时无法读取条目产生的警告
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    /// 记录无法读取条目的警告，返回 `false` 作为不匹配的结果
+    fn warn(&self, error: impl Display) -> bool {
+        self.warnings.borrow_mut().push(format!("无法读取 {}: {}", self.path.display(), error));
+        false
+    }
+}
+
+/// 由条件和 AND/OR/NOT 组成的过滤表达式
+///
+/// 语法示例: `(ext rs OR ext toml) AND NOT name test AND size > 10K`
+///
+/// - 条件: `ext <扩展名>`、`name <子串>`、`glob <通配符>`、`regex <正则>`、`type <f|d|l|s|p>`、
+///   `size <比较> <大小>`、`mtime|atime|ctime|btime <比较> <时间>`、`user <用户>`、`group <组>`、
+///   `perm <模式>`、`empty`、`content <文本>`
+/// - 比较运算符: `<`、`<=`、`=`、`!=`、`>=`、`>`；时间按时间点比较，`mtime > 7d` 表示最近7天内修改过，
+///   不支持 `=` 和 `!=`
+/// - 默认只匹配文件，`type` 条件（不在 `NOT` 之下时）使对应类型的条目也参与匹配
+/// - 与 `find` 相同，`ctime` 是 inode 变化时间（权限、所有者、名称或内容的变化），创建时间用 `btime`（或 `crtime`）
+/// - 逻辑运算: `NOT`/`!`、`AND`/`&&`（相邻条件之间可以省略）、`OR`/`||`，以及括号分组
+/// - 含空格或括号的值需要用引号括起来
+#[derive(Debug, Clone)]
+pub enum FilterExpr {
+    Predicate(Predicate),
+    Not(Box<FilterExpr>),
+    And(Vec<FilterExpr>),
+    Or(Vec<FilterExpr>),
+}
+
+impl FilterExpr {
+    /// 解析表达式，`case_sensitive` 决定名称和内容匹配是否区分大小写
+    pub fn parse(input: &str, case_sensitive: bool) -> RtoolsResult<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            case_sensitive,
+        };
+
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error(&format!("多余的内容 '{}'", token.text)));
+        }
+
+        Ok(expr.optimized())
+    }
+
+    /// 将 AND/OR 的子表达式按判断代价从低到高排序，使短路求值尽量跳过昂贵的条件
+    fn optimized(self) -> Self {
+        match self {
+            Self::And(children) => Self::And(Self::sort_by_cost(children)),
+            Self::Or(children) => Self::Or(Self::sort_by_cost(children)),
+            Self::Not(inner) => Self::Not(Box::new(inner.optimized())),
+            predicate => predicate,
+        }
+    }
+
+    fn sort_by_cost(children: Vec<FilterExpr>) -> Vec<FilterExpr> {
+        let mut children: Vec<FilterExpr> = children.into_iter().map(Self::optimized).collect();
+        children.sort_by_key(|child| child.cost());
+        children
+    }
+
+    /// 表达式中最昂贵条件的代价
+    fn cost(&self) -> u8 {
+        match self {
+            Self::Predicate(predicate) => predicate.cost(),
+            Self::Not(inner) => inner.cost(),
+            Self::And(children) | Self::Or(children) => {
+                children.iter().map(|c| c.cost()).max().unwrap_or(0)
+            }
+        }
+    }
+
+    /// 表达式中出现的类型条件，包括 NOT 之下的
+    pub fn mentioned_types(&self) -> Vec<EntryType> {
+        match self {
+            Self::Predicate(Predicate::Type(entry_type)) => vec![*entry_type],
+            Self::Predicate(_) => Vec::new(),
+            Self::Not(inner) => inner.mentioned_types(),
+            Self::And(children) | Self::Or(children) => {
+                children.iter().flat_map(|c| c.mentioned_types()).collect()
+            }
+        }
+    }
+
+    /// 表达式中不在 NOT 之下的类型条件，搜索除文件外只接受这些类型的条目
+    pub fn allowed_types(&self) -> Vec<EntryType> {
+        self.types_with_polarity(false)
+    }
+
+    fn types_with_polarity(&self, negated: bool) -> Vec<EntryType> {
+        match self {
+            Self::Predicate(Predicate::Type(entry_type)) if !negated => vec![*entry_type],
+            Self::Predicate(_) => Vec::new(),
+            Self::Not(inner) => inner.types_with_polarity(!negated),
+            Self::And(children) | Self::Or(children) => {
+                children.iter().flat_map(|c| c.types_with_polarity(negated)).collect()
+            }
+        }
+    }
+
+    pub fn evaluate(&self, entry: &EntryContext) -> RtoolsResult<bool> {
+        match self {
+            Self::Predicate(predicate) => predicate.evaluate(entry),
+            Self::Not(inner) => Ok(!inner.evaluate(entry)?),
+            Self::And(children) => {
+                for child in children {
+                    if !child.evaluate(entry)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Self::Or(children) => {
+                for child in children {
+                    if child.evaluate(entry)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word,
+    Quoted,
+    Operator,
+    LeftParen,
+    RightParen,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    position: usize,
+}
+
+const OPERATOR_CHARS: [char; 4] = ['<', '>', '=', '!'];
+
+fn tokenize(input: &str) -> RtoolsResult<Vec<Token>> {
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (position, c) = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let (kind, text, next) = match c {
+            '(' => (TokenKind::LeftParen, "(".to_string(), i + 1),
+            ')' => (TokenKind::RightParen, ")".to_string(), i + 1),
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&(_, ch)| ch == c)
+                    .map(|offset| i + 1 + offset)
+                    .ok_or_else(|| RtoolsError::ParseError(format!("位置 {}: 引号没有闭合", position)))?;
+                let text: String = chars[i + 1..end].iter().map(|&(_, ch)| ch).collect();
+                (TokenKind::Quoted, text, end + 1)
+            }
+            '&' | '|' if chars.get(i + 1).map(|&(_, ch)| ch) == Some(c) => {
+                let keyword = if c == '&' { "AND" } else { "OR" };
+                (TokenKind::Word, keyword.to_string(), i + 2)
+            }
+            c if OPERATOR_CHARS.contains(&c) => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&(_, ch)| !OPERATOR_CHARS.contains(&ch))
+                    .map_or(chars.len(), |offset| i + offset);
+                let text: String = chars[i..end].iter().map(|&(_, ch)| ch).collect();
+                if text == "!" {
+                    (TokenKind::Word, "NOT".to_string(), end)
+                } else if Comparison::parse(&text).is_some() {
+                    (TokenKind::Operator, text, end)
+                } else {
+                    return Err(RtoolsError::ParseError(format!("位置 {}: 无效的运算符 '{}'", position, text)));
+                }
+            }
+            _ => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&(_, ch)| ch.is_whitespace() || ch == '(' || ch == ')' || OPERATOR_CHARS.contains(&ch))
+                    .map_or(chars.len(), |offset| i + offset);
+                let text: String = chars[i..end].iter().map(|&(_, ch)| ch).collect();
+                (TokenKind::Word, text, end)
+            }
+        };
+
+        tokens.push(Token { kind, text, position });
+        i = next;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    case_sensitive: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek()
+            .is_some_and(|t| t.kind == TokenKind::Word && t.text.eq_ignore_ascii_case(keyword))
+    }
+
+    fn error(&self, message: &str) -> RtoolsError {
+        let position = self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(0, |t| t.position);
+        RtoolsError::ParseError(format!("过滤表达式位置 {}: {}", position, message))
+    }
+
+    fn parse_or(&mut self) -> RtoolsResult<FilterExpr> {
+        let mut children = vec![self.parse_and()?];
+        while self.peek_keyword("OR") {
+            self.position += 1;
+            children.push(self.parse_and()?);
+        }
+
+        Ok(if children.len() == 1 {
+            children.remove(0)
+        } else {
+            FilterExpr::Or(children)
+        })
+    }
+
+    fn parse_and(&mut self) -> RtoolsResult<FilterExpr> {
+        let mut children = vec![self.parse_unary()?];
+        loop {
+            if self.peek_keyword("AND") {
+                self.position += 1;
+            } else if self.peek().is_none()
+                || self.peek_keyword("OR")
+                || self.peek().is_some_and(|t| t.kind == TokenKind::RightParen) {
+                break;
+            }
+            children.push(self.parse_unary()?);
+        }
+
+        Ok(if children.len() == 1 {
+            children.remove(0)
+        } else {
+            FilterExpr::And(children)
+        })
+    }
+
+    fn parse_unary(&mut self) -> RtoolsResult<FilterExpr> {
+        if self.peek_keyword("NOT") {
+            self.position += 1;
+            return Ok(FilterExpr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        match self.peek().map(|t| t.kind.clone()) {
+            Some(TokenKind::LeftParen) => {
+                self.position += 1;
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(token) if token.kind == TokenKind::RightParen => Ok(expr),
+                    _ => {
+                        self.position -= 1;
+                        Err(self.error("缺少 ')'"))
+                    }
+                }
+            }
+            Some(TokenKind::Word) => self.parse_predicate(),
+            Some(_) => Err(self.error("需要条件")),
+            None => Err(self.error("表达式不完整")),
+        }
+    }
+
+    /// 读取条件的参数
+    fn value(&mut self, keyword: &str) -> RtoolsResult<String> {
+        match self.peek() {
+            Some(token) if matches!(token.kind, TokenKind::Word | TokenKind::Quoted) => {
+                let text = token.text.clone();
+                self.position += 1;
+                Ok(text)
+            }
+            _ => Err(self.error(&format!("'{}' 需要参数", keyword))),
+        }
+    }
+
+    /// 读取比较运算符，省略时为 `=`
+    fn comparison(&mut self) -> Comparison {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Operator => {
+                let op = Comparison::parse(&token.text).unwrap_or(Comparison::Equal);
+                self.position += 1;
+                op
+            }
+            _ => Comparison::Equal,
+        }
+    }
+
+    fn parse_predicate(&mut self) -> RtoolsResult<FilterExpr> {
+        let start = self.position;
+        let keyword = self.next().map(|t| t.text.to_lowercase()).unwrap_or_default();
+        let case_sensitive = self.case_sensitive;
+
+        // 解析参数时出错，错误位置指向该条件
+        let with_position = |parser: &mut Parser, e: RtoolsError| {
+            parser.position = start;
+            parser.error(&e.to_string())
+        };
+
+        let predicate = match keyword.as_str() {
+            "ext" => Predicate::Extension(self.value(&keyword)?),
+            "name" => Predicate::Name(NamePattern::substring(&self.value(&keyword)?, case_sensitive)),
+            "glob" => {
+                let value = self.value(&keyword)?;
+                Predicate::Name(NamePattern::glob(&value, case_sensitive).map_err(|e| with_position(self, e))?)
+            }
+            "regex" => {
+                let value = self.value(&keyword)?;
+                Predicate::Name(NamePattern::regex(&value, case_sensitive).map_err(|e| with_position(self, e))?)
+            }
+            "type" => {
+                let value = self.value(&keyword)?;
+                let entry_type = EntryType::parse(&value)
+                    .ok_or_else(|| self.error(&format!("无效的文件类型 '{}'", value)))?;
+                Predicate::Type(entry_type)
+            }
+            "size" => {
+                let op = self.comparison();
+                let value = self.value(&keyword)?;
+                Predicate::Size(op, parse_size(&value).map_err(|e| with_position(self, e))?)
+            }
+            "mtime" | "atime" | "ctime" | "btime" | "crtime" => {
+                let op = self.comparison();
+                // 文件时间精确到纳秒，按时间点判断相等没有意义
+                if matches!(op, Comparison::Equal | Comparison::NotEqual) {
+                    self.position = start;
+                    return Err(self.error(&format!("时间条件 '{}' 只支持 <、<=、>、>= 比较", keyword)));
+                }
+                let value = self.value(&keyword)?;
+                let time = parse_time_spec(&value).map_err(|e| with_position(self, e))?;
+                match keyword.as_str() {
+                    "mtime" => Predicate::Modified(op, time),
+                    "atime" => Predicate::Accessed(op, time),
+                    "ctime" => Predicate::Changed(op, time),
+                    _ => Predicate::Created(op, time),
+                }
+            }
+            "user" => {
+                let value = self.value(&keyword)?;
+                Predicate::Owner(lookup_user_id(&value).map_err(|e| with_position(self, e))?)
+            }
+            "group" => {
+                let value = self.value(&keyword)?;
+                Predicate::Group(lookup_group_id(&value).map_err(|e| with_position(self, e))?)
+            }
+            "perm" => {
+                let value = self.value(&keyword)?;
+                Predicate::Permission(PermissionMatch::parse(&value).map_err(|e| with_position(self, e))?)
+            }
+            "empty" => Predicate::Empty,
+            "content" => {
+                let value = self.value(&keyword)?;
+                let query = ContentQuery::literal(&value, case_sensitive)
+                    .map_err(|e| with_position(self, e))?
+                    .with_mode(ContentMode::FilesWithMatches);
+                Predicate::Content(query)
+            }
+            _ => {
+                self.position = start;
+                return Err(self.error(&format!("未知的条件 '{}'", keyword)));
+            }
+        };
+
+        Ok(FilterExpr::Predicate(predicate))
+    }
+}
@@ -0,0 +1,140 @@
+/// 每个匹配字符的基础得分
+const SCORE_MATCH: i64 = 16;
+/// 两个匹配字符之间出现间隔时的扣分，间隔每增加一个字符再扣 `SCORE_GAP_EXTENSION`
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+/// 匹配位于路径段开头（字符串开头或 `/` 之后）
+const BONUS_PATH_SEGMENT: i64 = 10;
+/// 匹配位于单词开头（`_`、`-`、`.`、空格之后）
+const BONUS_BOUNDARY: i64 = 8;
+/// 驼峰命名或字母到数字的切换处
+const BONUS_CAMEL: i64 = 7;
+/// 与前一个匹配字符相邻
+const BONUS_CONSECUTIVE: i64 = 4;
+/// 查询的第一个字符的位置奖励加倍，使开头对齐的结果排在前面
+const FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+/// 模糊查询（类似 fzf）: 查询中的字符按顺序出现在候选路径中即可匹配
+///
+/// 得分由匹配字符数、间隔扣分，以及在路径段、单词开头和驼峰位置的奖励组成，
+/// 使 `mrs` 更倾向于匹配 `src/main.rs` 而不是 `src/common/errors.txt`。
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyQuery {
+    pub query: String,
+    pub case_sensitive: bool,
+    chars: Vec<char>,
+}
+
+impl FuzzyQuery {
+    /// 创建模糊查询，忽略查询中的空白
+    pub fn new(query: &str, case_sensitive: bool) -> Self {
+        let chars = query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| if case_sensitive { c } else { fold_case(c) })
+            .collect();
+        Self {
+            query: query.to_string(),
+            case_sensitive,
+            chars,
+        }
+    }
+
+    /// 计算候选字符串的得分，不匹配时返回 `None`；空查询匹配任何字符串，得分为0
+    pub fn score(&self, candidate: &str) -> Option<i64> {
+        if self.chars.is_empty() {
+            return Some(0);
+        }
+
+        let text: Vec<char> = candidate.chars().collect();
+        let folded: Vec<char> = if self.case_sensitive {
+            text.clone()
+        } else {
+            text.iter().map(|&c| fold_case(c)).collect()
+        };
+
+        // 先做一次贪心的子序列检查，不匹配的候选不需要计算完整的得分
+        let mut remaining = self.chars.iter().peekable();
+        for c in &folded {
+            if remaining.peek() == Some(&c) {
+                remaining.next();
+            }
+        }
+        if remaining.peek().is_some() {
+            return None;
+        }
+
+        let bonuses: Vec<i64> = (0..text.len())
+            .map(|i| position_bonus(if i == 0 { None } else { Some(text[i - 1]) }, text[i]))
+            .collect();
+
+        // scores[j]: 查询当前字符匹配在 text[j] 时，到此为止的最高得分
+        let mut previous: Vec<Option<i64>> = vec![None; text.len()];
+        for (i, &query_char) in self.chars.iter().enumerate() {
+            let mut current: Vec<Option<i64>> = vec![None; text.len()];
+            // 以间隔方式从前一个字符转移过来时的最高得分（已计入间隔扣分）
+            let mut best_with_gap: Option<i64> = None;
+
+            for j in 0..text.len() {
+                if j >= 2 {
+                    let candidate = previous[j - 2].map(|s| s + SCORE_GAP_START);
+                    best_with_gap = max_option(best_with_gap.map(|s| s + SCORE_GAP_EXTENSION), candidate);
+                }
+
+                if folded[j] != query_char {
+                    continue;
+                }
+
+                let bonus = bonuses[j];
+                current[j] = if i == 0 {
+                    Some(SCORE_MATCH + bonus * FIRST_CHAR_MULTIPLIER)
+                } else {
+                    let consecutive = j
+                        .checked_sub(1)
+                        .and_then(|k| previous[k])
+                        .map(|s| s + SCORE_MATCH + bonus.max(BONUS_CONSECUTIVE));
+                    let gapped = best_with_gap.map(|s| s + SCORE_MATCH + bonus);
+                    max_option(consecutive, gapped)
+                };
+            }
+
+            previous = current;
+        }
+
+        previous.into_iter().flatten().max()
+    }
+
+    /// 按文件的相对路径计算得分；查询不包含 `/` 时也尝试只匹配文件名，并优先采用文件名的结果
+    pub fn score_path(&self, file_name: &str, relative_path: &str) -> Option<i64> {
+        let path_score = self.score(relative_path);
+        if self.query.contains('/') {
+            return path_score;
+        }
+
+        let name_score = self.score(file_name).map(|s| s + BONUS_PATH_SEGMENT);
+        max_option(name_score, path_score)
+    }
+}
+
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn max_option(a: Option<i64>, b: Option<i64>) -> Option<i64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// 根据前一个字符判断匹配位置的奖励
+fn position_bonus(previous: Option<char>, current: char) -> i64 {
+    match previous {
+        None | Some('/') | Some('\\') => BONUS_PATH_SEGMENT,
+        Some('_' | '-' | '.' | ' ') => BONUS_BOUNDARY,
+        Some(p) if p.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+        Some(p) if !p.is_ascii_digit() && current.is_ascii_digit() => BONUS_CAMEL,
+        Some(_) => 0,
+    }
+}
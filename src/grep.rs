@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use regex::{Regex, RegexBuilder};
+use crate::{RtoolsResult, RtoolsError};
+
+/// 判断是否为二进制文件时检查的字节数
+const BINARY_CHECK_BYTES: usize = 8192;
+
+/// 内容搜索的输出方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentMode {
+    /// 输出匹配的行及其上下文
+    #[default]
+    Lines,
+    /// 只统计每个文件的匹配行数
+    CountOnly,
+    /// 只列出包含匹配的文件，找到第一个匹配后即停止读取
+    FilesWithMatches,
+}
+
+/// 文件内容搜索条件
+#[derive(Debug, Clone)]
+pub struct ContentQuery {
+    regex: Regex,
+    pub pattern: String,
+    pub context_before: usize,
+    pub context_after: usize,
+    pub mode: ContentMode,
+}
+
+impl ContentQuery {
+    /// 按字面文本搜索
+    pub fn literal(text: &str, case_sensitive: bool) -> RtoolsResult<Self> {
+        Self::build(text, &regex::escape(text), case_sensitive)
+    }
+
+    /// 按正则表达式搜索
+    pub fn regex(pattern: &str, case_sensitive: bool) -> RtoolsResult<Self> {
+        Self::build(pattern, pattern, case_sensitive)
+    }
+
+    fn build(pattern: &str, regex: &str, case_sensitive: bool) -> RtoolsResult<Self> {
+        let regex = RegexBuilder::new(regex)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|e| RtoolsError::ParseError(format!("无效的搜索模式 '{}': {}", pattern, e)))?;
+
+        Ok(Self {
+            regex,
+            pattern: pattern.to_string(),
+            context_before: 0,
+            context_after: 0,
+            mode: ContentMode::default(),
+        })
+    }
+
+    pub fn with_context(mut self, before: usize, after: usize) -> Self {
+        self.context_before = before;
+        self.context_after = after;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: ContentMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.regex.is_match(line)
+    }
+}
+
+/// 输出的一行内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentLine {
+    pub line_number: usize,
+    pub text: String,
+    /// 为假时表示这是匹配行的上下文
+    pub is_match: bool,
+}
+
+/// 单个文件中的匹配结果
+#[derive(Debug, Clone)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub match_count: usize,
+    /// 匹配行和上下文行，按行号排序；只统计数量时为空
+    pub lines: Vec<ContentLine>,
+}
+
+impl FileMatches {
+    pub fn matched_lines(&self) -> impl Iterator<Item = &ContentLine> {
+        self.lines.iter().filter(|line| line.is_match)
+    }
+
+    /// 以 grep 的格式打印：匹配行用 `:` 分隔，上下文行用 `-` 分隔，不连续的片段之间输出 `--`
+    pub fn print_lines(&self) {
+        let mut previous: Option<usize> = None;
+        for line in &self.lines {
+            if let Some(prev) = previous
+                && line.line_number > prev + 1 {
+                println!("--");
+            }
+            let separator = if line.is_match { ':' } else { '-' };
+            println!("{}{}{}{}{}", self.path.display(), separator, line.line_number, separator, line.text);
+            previous = Some(line.line_number);
+        }
+    }
+}
+
+/// 判断文件开头是否包含NUL字节
+pub fn is_binary_file(path: &Path) -> RtoolsResult<bool> {
+    let mut buffer = Vec::with_capacity(BINARY_CHECK_BYTES);
+    File::open(path)?
+        .take(BINARY_CHECK_BYTES as u64)
+        .read_to_end(&mut buffer)?;
+    Ok(buffer.contains(&0))
+}
+
+/// 在文件中搜索内容，没有匹配或文件为二进制时返回 `None`
+pub fn search_content(path: &Path, query: &ContentQuery) -> RtoolsResult<Option<FileMatches>> {
+    if is_binary_file(path)? {
+        return Ok(None);
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut result = FileMatches {
+        path: path.to_path_buf(),
+        match_count: 0,
+        lines: Vec::new(),
+    };
+    let keep_lines = query.mode == ContentMode::Lines;
+    let mut before: VecDeque<ContentLine> = VecDeque::with_capacity(query.context_before + 1);
+    let mut after_remaining = 0;
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let text = String::from_utf8_lossy(&buffer);
+        let text = text.trim_end_matches(['\n', '\r']);
+
+        if query.is_match(text) {
+            result.match_count += 1;
+            match query.mode {
+                ContentMode::FilesWithMatches => break,
+                ContentMode::CountOnly => continue,
+                ContentMode::Lines => {}
+            }
+
+            result.lines.extend(before.drain(..));
+            result.lines.push(ContentLine {
+                line_number,
+                text: text.to_string(),
+                is_match: true,
+            });
+            after_remaining = query.context_after;
+        } else if !keep_lines {
+            continue;
+        } else if after_remaining > 0 {
+            after_remaining -= 1;
+            result.lines.push(ContentLine {
+                line_number,
+                text: text.to_string(),
+                is_match: false,
+            });
+        } else if query.context_before > 0 {
+            if before.len() == query.context_before {
+                before.pop_front();
+            }
+            before.push_back(ContentLine {
+                line_number,
+                text: text.to_string(),
+                is_match: false,
+            });
+        }
+    }
+
+    if result.match_count == 0 {
+        Ok(None)
+    } else {
+        Ok(Some(result))
+    }
+}
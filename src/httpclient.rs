@@ -211,10 +211,9 @@ pub async fn send_request(request: HttpRequest) -> RtoolsResult<HttpResponse> {
         http_response.content_type = content_type.to_str().ok().map(|s| s.to_string());
     }
     
-    if let Some(content_length) = response.headers().get("content-length") {
-        if let Ok(length) = content_length.to_str().unwrap_or("0").parse::<usize>() {
-            http_response.content_length = Some(length);
-        }
+    if let Some(content_length) = response.headers().get("content-length")
+        && let Ok(length) = content_length.to_str().unwrap_or("0").parse::<usize>() {
+        http_response.content_length = Some(length);
     }
     
     // 获取响应体
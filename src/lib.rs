@@ -37,8 +37,24 @@
 pub mod fileinfo;
 pub mod textstats;
 pub mod dirscan;
+pub mod snapshot;
+pub mod duplicates;
+pub mod cleanup;
 pub mod filesearch;
+pub mod filterexpr;
+pub mod fuzzy;
+pub mod fileindex;
+pub mod actions;
+pub mod pattern;
+pub mod grep;
 pub mod loganalyzer;
+pub mod logparser;
+pub mod logfilter;
+pub mod logfollow;
+pub mod syslog;
+pub mod accesslog;
+pub mod logsource;
+pub mod sketch;
 pub mod config;
 pub mod httpclient;
 pub mod network;
@@ -46,9 +62,30 @@ pub mod network;
 // 重新导出主要功能，方便用户使用
 pub use fileinfo::get_file_info;
 pub use textstats::{TextStats, analyze_file as analyze_text_file};
-pub use dirscan::{DirectoryStats, scan_directory};
-pub use filesearch::{SearchCriteria, SearchResult, search_files};
-pub use loganalyzer::{LogAnalysis, analyze_log_file};
+pub use dirscan::{DirectoryStats, ScanOptions, scan_directory, scan_directory_with_options, parse_age};
+pub use snapshot::{Snapshot, SnapshotDiff};
+pub use duplicates::{DuplicateOptions, DuplicateReport, find_duplicates};
+pub use cleanup::{CleanupKind, CleanupOptions, CleanupReport};
+pub use filesearch::{SearchCriteria, SearchResult, EntryType, PermissionMatch, TimeRange, search_files,
+                     SortKey, PathDisplay, ListFormat, FileDetails,
+                     SearchIter, SearchMatch, SearchProgress, search_iter, search_files_with,
+                     parse_size, parse_size_range, parse_time_spec, lookup_user_id, lookup_group_id};
+pub use actions::{ActionReport, SearchAction};
+pub use fileindex::{FileIndex, IndexUpdate};
+pub use filterexpr::{Comparison, FilterExpr, Predicate};
+pub use fuzzy::FuzzyQuery;
+pub use pattern::{NamePattern, PatternKind};
+pub use grep::{ContentLine, ContentMode, ContentQuery, FileMatches};
+pub use loganalyzer::{AnalyzeOptions, EntryAssembler, FileSummary, GroupStats, LogAggregator, LogAnalysis, LogEntry,
+                      LogLevel, LogReader, analyze_log_file, analyze_log_file_with, analyze_log_files, query_log_file,
+                      select_parser};
+pub use logfilter::{EntryFormat, LogFilter, parse_level, parse_time_bound, write_entries};
+pub use logfollow::{EntryFollower, FileFollower, RollingStats};
+pub use logparser::{JsonParser, LogParser, ParserRegistry, RegexParser};
+pub use syslog::{Rfc5424Parser, SyslogParser};
+pub use accesslog::{AccessLogParser, AccessStats, LatencyStats};
+pub use logsource::{Compression, open_log_file, resolve_log_paths, sort_by_rotation};
+pub use sketch::{DistinctCounter, Reservoir, TopK};
 pub use config::{ConfigManager, ConfigValue};
 pub use httpclient::{HttpRequest, HttpResponse, HttpMethod, send_request, get, post, check_url};
 pub use network::{ConnectivityResult, PortScanResult, DnsResult, test_tcp_connection, scan_ports, dns_lookup, ping_host};
@@ -100,6 +137,7 @@ pub enum ToolType {
     TextStats,
     DirScan,
     FileSearch,
+    Index,
     LogAnalyzer,
     Config,
     HttpClient,
@@ -113,6 +151,7 @@ impl ToolType {
             "textstats" => Some(Self::TextStats),
             "dirscan" => Some(Self::DirScan),
             "filesearch" => Some(Self::FileSearch),
+            "index" => Some(Self::Index),
             "loganalyzer" => Some(Self::LogAnalyzer),
             "config" => Some(Self::Config),
             "httpclient" => Some(Self::HttpClient),
@@ -125,9 +164,10 @@ impl ToolType {
         match self {
             Self::FileInfo => "fileinfo <文件路径>   - 显示文件信息",
             Self::TextStats => "textstats <文件路径>  - 分析文本文件统计信息",
-            Self::DirScan => "dirscan <目录路径> [深度] - 扫描目录统计信息",
+            Self::DirScan => "dirscan <目录路径> [深度] [选项] - 扫描目录统计信息",
             Self::FileSearch => "filesearch <目录路径> [选项] - 搜索文件",
-            Self::LogAnalyzer => "loganalyzer <日志文件> - 分析日志文件",
+            Self::Index => "index <build|update|info> <目录路径> [选项] - 建立文件名索引",
+            Self::LogAnalyzer => "loganalyzer <日志文件|目录|通配符>... [选项] - 分析日志文件",
             Self::Config => "config <配置文件> - 管理配置文件",
             Self::HttpClient => "httpclient <URL> [选项] - HTTP客户端工具",
             Self::Network => "network <主机> [选项] - 网络连接测试工具",
@@ -140,6 +180,7 @@ impl ToolType {
             Self::TextStats => "rtools textstats src/main.rs",
             Self::DirScan => "rtools dirscan src/ 2",
             Self::FileSearch => "rtools filesearch src/ --ext rs",
+            Self::Index => "rtools index build ~/projects",
             Self::LogAnalyzer => "rtools loganalyzer app.log",
             Self::Config => "rtools config config.json",
             Self::HttpClient => "rtools httpclient https://api.github.com",
@@ -155,6 +196,7 @@ pub fn get_help_text() -> String {
         ToolType::TextStats,
         ToolType::DirScan,
         ToolType::FileSearch,
+        ToolType::Index,
         ToolType::LogAnalyzer,
         ToolType::Config,
         ToolType::HttpClient,
@@ -187,6 +229,7 @@ mod tests {
         assert_eq!(ToolType::parse("textstats"), Some(ToolType::TextStats));
         assert_eq!(ToolType::parse("dirscan"), Some(ToolType::DirScan));
         assert_eq!(ToolType::parse("filesearch"), Some(ToolType::FileSearch));
+        assert_eq!(ToolType::parse("index"), Some(ToolType::Index));
         assert_eq!(ToolType::parse("loganalyzer"), Some(ToolType::LogAnalyzer));
         assert_eq!(ToolType::parse("config"), Some(ToolType::Config));
         assert_eq!(ToolType::parse("unknown"), None);
@@ -1,8 +1,21 @@
-use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
-use chrono::{DateTime, Utc, NaiveDateTime};
-use crate::{RtoolsResult, RtoolsError};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde_json::json;
+use crate::accesslog::{AccessCollector, AccessStats};
+use crate::logfilter::LogFilter;
+use crate::logparser::{DEFAULT_SAMPLE_LINES, LogParser, ParserRegistry};
+use crate::logsource::{Compression, open_log_file};
+use crate::sketch::{DEFAULT_TOP_K, DistinctCounter, TopK};
+use crate::syslog::{FACILITY_FIELD, HOST_FIELD};
+use crate::RtoolsResult;
+
+/// 每个条目最多保存的后续行数，超过的行只计数，避免格式变化后其余所有行都堆积在一个条目中
+pub const MAX_CONTINUATION_LINES: usize = 1000;
 
 /// 日志级别
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -27,6 +40,29 @@ impl LogLevel {
         }
     }
     
+    /// 按 bunyan/pino 的数字级别解析: 10 trace, 20 debug, 30 info, 40 warn, 50 error, 60 fatal
+    pub fn from_number(level: i64) -> Self {
+        match level {
+            ..=29 => Self::Debug,
+            30..=39 => Self::Info,
+            40..=49 => Self::Warning,
+            50..=59 => Self::Error,
+            _ => Self::Critical,
+        }
+    }
+    
+    /// 级别名称，未知级别返回原始文本
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warning => "WARNING",
+            Self::Error => "ERROR",
+            Self::Critical => "CRITICAL",
+            Self::Unknown(s) => s,
+        }
+    }
+    
     pub fn severity(&self) -> u8 {
         match self {
             Self::Debug => 0,
@@ -47,17 +83,243 @@ pub struct LogEntry {
     pub message: String,
     pub source: Option<String>,
     pub line_number: Option<usize>,
+    /// 结构化日志中时间、级别和消息以外的字段，嵌套对象的键用 `.` 连接
+    pub fields: BTreeMap<String, String>,
+    /// 属于该条目的后续行，如异常堆栈
+    pub continuation: Vec<String>,
+    /// 超过 `MAX_CONTINUATION_LINES` 而没有保存的后续行数
+    pub omitted_lines: usize,
+    /// 条目第一行的原始文本，由 `EntryAssembler` 填写
+    pub raw: String,
+}
+
+impl LogEntry {
+    pub fn new(timestamp: Option<DateTime<Utc>>, level: LogLevel, message: String) -> Self {
+        Self {
+            timestamp,
+            level,
+            message,
+            source: None,
+            line_number: None,
+            fields: BTreeMap::new(),
+            continuation: Vec::new(),
+            omitted_lines: 0,
+            raw: String::new(),
+        }
+    }
+    
+    /// 条目的完整文本: 消息和所有后续行
+    pub fn full_text(&self) -> String {
+        let mut text = self.message.clone();
+        for line in &self.continuation {
+            text.push('\n');
+            text.push_str(line);
+        }
+        text
+    }
+    
+    /// 条目的原始文本: 第一行和所有后续行；没有原始文本时使用消息
+    pub fn original_text(&self) -> String {
+        let mut text = if self.raw.is_empty() { self.message.clone() } else { self.raw.clone() };
+        for line in &self.continuation {
+            text.push('\n');
+            text.push_str(line);
+        }
+        if self.omitted_lines > 0 {
+            text.push_str(&format!("\n... (省略 {} 行)", self.omitted_lines));
+        }
+        text
+    }
+    
+    /// 转换为JSON对象
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "line": self.line_number,
+            "timestamp": self.timestamp.map(|ts| ts.to_rfc3339()),
+            "level": self.level.name(),
+            "message": self.message,
+            "source": self.source,
+            "fields": self.fields,
+            "continuation": self.continuation,
+            "omitted_lines": self.omitted_lines,
+        })
+    }
+    
+    /// 查找字段: 先按完整的键查找，再查找最后一段与之相同的嵌套字段（`service` 可以找到 `fields.service`）
+    pub fn field(&self, name: &str) -> Option<&str> {
+        if let Some(value) = self.fields.get(name) {
+            return Some(value);
+        }
+        
+        let suffix = format!(".{}", name);
+        self.fields
+            .iter()
+            .find(|(key, _)| key.ends_with(&suffix))
+            .map(|(_, value)| value.as_str())
+    }
+    
+    /// 无法识别格式的行
+    pub fn unparsed(line: &str) -> Self {
+        Self::new(None, LogLevel::Unknown("unknown".to_string()), line.to_string())
+    }
+}
+
+/// 日志分析选项
+#[derive(Debug, Clone)]
+pub struct AnalyzeOptions {
+    /// 指定日志格式名称，未指定时自动检测
+    pub format: Option<String>,
+    /// 自动检测格式时采样的行数
+    pub sample_lines: usize,
+    /// 按该字段分组统计
+    pub group_by: Option<String>,
+    /// 把堆栈等后续行合并到所属的条目，默认开启
+    pub multiline: bool,
+    /// 条目起始行的模式；为空时能被解析器解析的行是起始行
+    pub start_patterns: Vec<Regex>,
+    /// 只分析或查询通过过滤条件的条目
+    pub filter: LogFilter,
+    /// 消息、错误模式、主机和分组最多保留的个数，超过后计数是估计值
+    pub top_k: usize,
+    /// 分析时把文件分成几块并行处理
+    pub jobs: usize,
+}
+
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            sample_lines: DEFAULT_SAMPLE_LINES,
+            group_by: None,
+            multiline: true,
+            start_patterns: Vec::new(),
+            filter: LogFilter::default(),
+            top_k: DEFAULT_TOP_K,
+            jobs: 1,
+        }
+    }
+}
+
+impl AnalyzeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn with_format(mut self, format: &str) -> Self {
+        self.format = Some(format.to_string());
+        self
+    }
+    
+    pub fn with_sample_lines(mut self, lines: usize) -> Self {
+        self.sample_lines = lines;
+        self
+    }
+    
+    pub fn with_group_by(mut self, field: &str) -> Self {
+        self.group_by = Some(field.to_string());
+        self
+    }
+    
+    pub fn with_multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+    
+    /// 添加条目起始行的模式，同时开启多行合并
+    pub fn with_start_pattern(mut self, pattern: Regex) -> Self {
+        self.start_patterns.push(pattern);
+        self.multiline = true;
+        self
+    }
+    
+    pub fn with_filter(mut self, filter: LogFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+    
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k.max(1);
+        self
+    }
+    
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+    
+    /// 判断一行是否是上一条目的后续行: 不匹配起始模式（没有起始模式时不能被解析）的行，
+    /// 以及既不能被解析也不匹配起始模式的缩进行和 `Caused by:` 开头的行
+    fn is_continuation(&self, line: &str, parsed: bool) -> bool {
+        let matches_start = self.start_patterns.iter().any(|pattern| pattern.is_match(line));
+        // 能被解析的缩进行（如缩进的 JSON）是独立的条目
+        if line.starts_with([' ', '\t']) || line.starts_with("Caused by:") {
+            return !parsed && !matches_start;
+        }
+        if self.start_patterns.is_empty() {
+            !parsed
+        } else {
+            !matches_start
+        }
+    }
+}
+
+/// 按字段分组的统计
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupStats {
+    /// 字段值，没有该字段的条目归入 `None`
+    pub key: Option<String>,
+    pub count: usize,
+    /// 其中错误及以上级别的条目数
+    pub errors: usize,
+}
+
+/// 多个文件一起分析时每个文件的概况
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSummary {
+    pub path: PathBuf,
+    pub compression: Compression,
+    pub format: Option<String>,
+    pub total_entries: usize,
+    /// 其中错误及以上级别的条目数
+    pub errors: usize,
+    pub unparsed_lines: usize,
+    pub time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
 }
 
 /// 日志分析结果
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct LogAnalysis {
+    /// 使用的日志格式，没有识别出格式时为空
+    pub format: Option<String>,
+    /// 无法按该格式解析的行数，不包括合并到条目中的后续行
+    pub unparsed_lines: usize,
+    /// 包含后续行的条目数和后续行的总行数
+    pub multiline_entries: usize,
+    pub continuation_lines: usize,
+    /// 没有通过过滤条件的条目数
+    pub filtered_entries: usize,
     pub total_entries: usize,
     pub level_distribution: HashMap<LogLevel, usize>,
     pub time_distribution: HashMap<String, usize>,
     pub error_patterns: HashMap<String, usize>,
+    /// 出现次数最多的消息，最多 `top_k` 条
     pub top_messages: Vec<(String, usize)>,
+    /// 不同消息的条数（估计值）
+    pub distinct_messages: usize,
+    /// 不同的键超过了 `top_k`，消息、错误模式、主机和分组的计数是估计值
+    pub approximate: bool,
     pub time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// syslog 设施分布，只统计带有设施字段的条目
+    pub facility_distribution: HashMap<String, usize>,
+    /// 主机分布，只统计带有主机字段的条目
+    pub host_distribution: HashMap<String, usize>,
+    /// 访问日志统计，只在有带状态码的条目时存在
+    pub access: Option<AccessStats>,
+    /// 分组字段及各组的统计，按条目数从多到少排序
+    pub group_field: Option<String>,
+    pub groups: Vec<GroupStats>,
+    /// 分析的各个文件，按轮转的先后排列；只有多个文件一起分析时才有内容
+    pub files: Vec<FileSummary>,
 }
 
 impl LogAnalysis {
@@ -67,7 +329,21 @@ impl LogAnalysis {
     
     pub fn print_analysis(&self) {
         println!("日志分析结果:");
+        println!("- 日志格式: {}", self.format.as_deref().unwrap_or("未识别"));
         println!("- 总条目数: {}", self.total_entries);
+        if self.unparsed_lines > 0 {
+            println!("- 无法解析的行: {}", self.unparsed_lines);
+        }
+        if self.multiline_entries > 0 {
+            println!("- 多行条目: {} (合并 {} 行)", self.multiline_entries, self.continuation_lines);
+        }
+        if self.filtered_entries > 0 {
+            println!("- 被过滤的条目: {}", self.filtered_entries);
+        }
+        println!("- 不同消息数: 约 {}", self.distinct_messages);
+        if self.approximate {
+            println!("- 注意: 不同的值太多，排行中的计数是估计值");
+        }
         
         if let Some((start, end)) = self.time_range {
             let duration = end - start;
@@ -84,6 +360,9 @@ impl LogAnalysis {
             println!("  {:?}: {} ({:.1}%)", level, count, percentage);
         }
         
+        print_distribution("设施分布", &self.facility_distribution, self.total_entries);
+        print_distribution("主机分布", &self.host_distribution, self.total_entries);
+        
         if !self.error_patterns.is_empty() {
             println!("\n错误模式统计:");
             let mut sorted_patterns: Vec<(&String, &usize)> = self.error_patterns.iter().collect();
@@ -94,6 +373,37 @@ impl LogAnalysis {
             }
         }
         
+        if let Some(field) = &self.group_field {
+            println!("\n按 {} 分组:", field);
+            for group in self.groups.iter().take(20) {
+                let percentage = (group.count as f64 / self.total_entries as f64) * 100.0;
+                println!("  {}: {} ({:.1}%), 错误 {}",
+                         group.key.as_deref().unwrap_or("(无)"), group.count, percentage, group.errors);
+            }
+        }
+        
+        if self.files.len() > 1 {
+            println!("\n按文件:");
+            for file in &self.files {
+                let mut line = format!("  {}: {} 条, 错误 {}", file.path.display(), file.total_entries, file.errors);
+                if file.unparsed_lines > 0 {
+                    line.push_str(&format!(", 无法解析 {} 行", file.unparsed_lines));
+                }
+                line.push_str(&format!(", 格式 {}", file.format.as_deref().unwrap_or("未识别")));
+                if file.compression != Compression::None {
+                    line.push_str(&format!(" ({})", file.compression.name()));
+                }
+                if let Some((start, end)) = file.time_range {
+                    line.push_str(&format!(", {} 到 {}", start.format("%Y-%m-%d %H:%M:%S"), end.format("%Y-%m-%d %H:%M:%S")));
+                }
+                println!("{}", line);
+            }
+        }
+        
+        if let Some(access) = &self.access {
+            access.print_stats();
+        }
+        
         if !self.top_messages.is_empty() {
             println!("\n最常见的消息:");
             for (i, (message, count)) in self.top_messages.iter().enumerate().take(5) {
@@ -103,173 +413,635 @@ impl LogAnalysis {
     }
 }
 
-/// 分析日志文件
-pub fn analyze_log_file(file_path: &str) -> RtoolsResult<LogAnalysis> {
-    let path = Path::new(file_path);
+/// 按数量从多到少输出分布，最多输出10项
+fn print_distribution(title: &str, distribution: &HashMap<String, usize>, total: usize) {
+    if distribution.is_empty() {
+        return;
+    }
+    
+    println!("\n{}:", title);
+    let mut sorted: Vec<(&String, &usize)> = distribution.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
     
-    if !path.exists() {
-        return Err(RtoolsError::FileNotFound(file_path.to_string()));
+    for (key, count) in sorted.into_iter().take(10) {
+        let percentage = (*count as f64 / total as f64) * 100.0;
+        println!("  {}: {} ({:.1}%)", key, count, percentage);
     }
+}
+
+/// 分析日志文件，使用内置解析器并自动检测格式
+pub fn analyze_log_file(file_path: &str) -> RtoolsResult<LogAnalysis> {
+    analyze_log_file_with(file_path, &ParserRegistry::default(), &AnalyzeOptions::default())
+}
+
+/// 使用指定的解析器注册表和选项分析日志文件
+///
+/// 文件逐行读取，统计使用固定大小的结构，内存占用不随文件大小增长；压缩的文件自动解压。
+/// `jobs` 大于1时把未压缩的文件按行分成多块并行分析，再合并结果。
+pub fn analyze_log_file_with(
+    file_path: &str,
+    registry: &ParserRegistry,
+    options: &AnalyzeOptions,
+) -> RtoolsResult<LogAnalysis> {
+    Ok(aggregate_file(Path::new(file_path), registry, options)?.analysis())
+}
+
+/// 把多个日志文件（如轮转的 `app.log.2.gz`、`app.log.1`、`app.log`）合并为一个分析结果
+///
+/// 每个文件单独检测格式，结果中的 `files` 列出各文件的概况，顺序与 `paths` 相同。
+pub fn analyze_log_files<P: AsRef<Path>>(
+    paths: &[P],
+    registry: &ParserRegistry,
+    options: &AnalyzeOptions,
+) -> RtoolsResult<LogAnalysis> {
+    let mut total = LogAggregator::new(None, options);
+    let mut files = Vec::new();
+    let mut formats: Vec<String> = Vec::new();
     
-    let content = fs::read_to_string(path)?;
-    let lines: Vec<&str> = content.lines().collect();
+    for path in paths {
+        let path = path.as_ref();
+        let aggregator = aggregate_file(path, registry, options)?;
+        let summary = aggregator.file_summary(path, Compression::of_file(path)?);
+        if let Some(format) = &summary.format
+            && !formats.contains(format)
+        {
+            formats.push(format.clone());
+        }
+        files.push(summary);
+        total.merge(&aggregator);
+    }
     
-    let mut analysis = LogAnalysis::new();
+    let mut analysis = total.analysis();
+    analysis.format = (!formats.is_empty()).then(|| formats.join(", "));
+    analysis.files = files;
+    Ok(analysis)
+}
+
+/// 读取日志文件并组合成条目，返回通过过滤条件的条目
+pub fn query_log_file(
+    file_path: &str,
+    registry: &ParserRegistry,
+    options: &AnalyzeOptions,
+) -> RtoolsResult<Vec<LogEntry>> {
     let mut entries = Vec::new();
-    
-    for line in lines.iter() {
-        if let Some(entry) = parse_log_line(line) {
+    for entry in LogReader::new(open_log_file(file_path)?, registry, options)? {
+        let entry = entry?;
+        if options.filter.matches(&entry) {
             entries.push(entry);
         }
     }
+    Ok(entries)
+}
+
+/// 统计一个文件中的条目
+fn aggregate_file(path: &Path, registry: &ParserRegistry, options: &AnalyzeOptions) -> RtoolsResult<LogAggregator> {
+    let mut reader = LogReader::new(open_log_file(path)?, registry, options)?;
+    let parser = reader.parser();
     
-    analysis.total_entries = entries.len();
+    // 压缩的文件不能从中间开始读，只能顺序处理
+    if options.jobs > 1 && Compression::of_file(path)? == Compression::None {
+        return aggregate_parallel(path, parser, options);
+    }
     
-    if entries.is_empty() {
-        return Ok(analysis);
+    let mut aggregator = LogAggregator::new(parser.map(|p| p.name()), options);
+    for entry in &mut reader {
+        aggregator.add_matching(&entry?, &options.filter);
+    }
+    aggregator.add_line_counts(reader.unparsed_lines(), reader.continuation_lines());
+    Ok(aggregator)
+}
+
+/// 读取一行，去掉行尾的换行符，无效的UTF-8按替换字符处理；读到末尾时返回 `None`
+pub(crate) fn read_line<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<Option<String>> {
+    buf.clear();
+    if reader.read_until(b'\n', buf)? == 0 {
+        return Ok(None);
     }
     
-    // 分析日志级别分布
-    for entry in &entries {
-        *analysis.level_distribution.entry(entry.level.clone()).or_insert(0) += 1;
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+    }
+    Ok(Some(String::from_utf8_lossy(buf).into_owned()))
+}
+
+/// 逐行读取日志并组合成条目的迭代器
+///
+/// 自动检测格式时先读取开头的若干行作为样本，之后这些行和其余的行一样被组合成条目，
+/// 任何时候只有当前的条目在内存中。
+pub struct LogReader<'a, R> {
+    reader: R,
+    parser: Option<&'a dyn LogParser>,
+    /// 检测格式时读取、还没有处理的行
+    sample: VecDeque<String>,
+    assembler: EntryAssembler<'a>,
+    buf: Vec<u8>,
+    finished: bool,
+}
+
+impl<'a, R: BufRead> LogReader<'a, R> {
+    /// 按选项选择解析器，未指定格式时用开头的行自动检测
+    pub fn new(mut reader: R, registry: &'a ParserRegistry, options: &'a AnalyzeOptions) -> RtoolsResult<Self> {
+        let mut sample = VecDeque::new();
+        let mut buf = Vec::new();
+        if options.format.is_none() {
+            while sample.len() < options.sample_lines {
+                match read_line(&mut reader, &mut buf)? {
+                    Some(line) => sample.push_back(line),
+                    None => break,
+                }
+            }
+        }
+        
+        let parser = select_parser(registry, options, sample.make_contiguous())?;
+        Ok(Self {
+            sample,
+            ..Self::with_parser(reader, parser, options)
+        })
     }
     
-    // 分析时间分布
-    let mut timestamps: Vec<DateTime<Utc>> = Vec::new();
-    for entry in &entries {
-        if let Some(ts) = entry.timestamp {
-            timestamps.push(ts);
-            let hour_key = ts.format("%Y-%m-%d %H").to_string();
-            *analysis.time_distribution.entry(hour_key).or_insert(0) += 1;
+    /// 使用已经选定的解析器
+    pub fn with_parser(reader: R, parser: Option<&'a dyn LogParser>, options: &'a AnalyzeOptions) -> Self {
+        Self {
+            reader,
+            parser,
+            sample: VecDeque::new(),
+            assembler: EntryAssembler::new(parser, options),
+            buf: Vec::new(),
+            finished: false,
         }
     }
     
-    // 计算时间范围
-    if !timestamps.is_empty() {
-        timestamps.sort();
-        analysis.time_range = Some((timestamps[0], timestamps[timestamps.len() - 1]));
+    pub fn parser(&self) -> Option<&'a dyn LogParser> {
+        self.parser
     }
     
-    // 分析错误模式
-    for entry in &entries {
-        if entry.level.severity() >= LogLevel::Error.severity() {
-            let words: Vec<&str> = entry.message.split_whitespace().collect();
-            for word in words {
-                if word.len() > 3 && word.chars().any(|c| c.is_uppercase()) {
-                    *analysis.error_patterns.entry(word.to_string()).or_insert(0) += 1;
-                }
+    pub fn unparsed_lines(&self) -> usize {
+        self.assembler.unparsed_lines()
+    }
+    
+    pub fn continuation_lines(&self) -> usize {
+        self.assembler.continuation_lines()
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<'_, R> {
+    type Item = RtoolsResult<LogEntry>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let line = match self.sample.pop_front() {
+                Some(line) => line,
+                None => match read_line(&mut self.reader, &mut self.buf) {
+                    Ok(Some(line)) => line,
+                    Ok(None) => {
+                        self.finished = true;
+                        return self.assembler.finish().map(Ok);
+                    }
+                    Err(e) => {
+                        self.finished = true;
+                        return Some(Err(e.into()));
+                    }
+                },
+            };
+            if let Some(entry) = self.assembler.push(&line) {
+                return Some(Ok(entry));
             }
         }
+        None
     }
+}
+
+/// 把文件按行分成 `jobs` 块，每块在单独的线程中统计，最后合并
+fn aggregate_parallel(
+    path: &Path,
+    parser: Option<&dyn LogParser>,
+    options: &AnalyzeOptions,
+) -> RtoolsResult<LogAggregator> {
+    let bounds = chunk_bounds(path, parser, options)?;
     
-    // 分析常见消息
-    let mut message_counts: HashMap<String, usize> = HashMap::new();
-    for entry in &entries {
-        let key = entry.message.clone();
-        *message_counts.entry(key).or_insert(0) += 1;
+    let results: Vec<RtoolsResult<LogAggregator>> = thread::scope(|scope| {
+        let handles: Vec<_> = bounds
+            .windows(2)
+            .map(|range| {
+                let (start, end) = (range[0], range[1]);
+                scope.spawn(move || aggregate_chunk(path, start, end, parser, options))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("分析线程异常退出"))
+            .collect()
+    });
+    
+    let mut aggregator = LogAggregator::new(parser.map(|p| p.name()), options);
+    for chunk in results {
+        aggregator.merge(&chunk?);
     }
+    Ok(aggregator)
+}
+
+/// 分块的字节边界，包括开头的0和末尾的文件大小
+///
+/// 每个边界移到行首；开启多行合并时再跳过后续行，使每块从一个条目的起始行开始，
+/// 这样多行条目不会被分到两块中。
+fn chunk_bounds(path: &Path, parser: Option<&dyn LogParser>, options: &AnalyzeOptions) -> RtoolsResult<Vec<u64>> {
+    let len = fs::metadata(path)?.len();
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = Vec::new();
+    let mut bounds = vec![0];
     
-    let mut sorted_messages: Vec<(String, usize)> = message_counts.into_iter().collect();
-    sorted_messages.sort_by(|a, b| b.1.cmp(&a.1));
-    analysis.top_messages = sorted_messages;
+    for i in 1..options.jobs as u64 {
+        let target = len * i / options.jobs as u64;
+        let previous = *bounds.last().unwrap_or(&0);
+        if target <= previous {
+            continue;
+        }
+        
+        // 从前一个字节开始读到换行符，正好在行首时只读到这个换行符
+        reader.seek(SeekFrom::Start(target - 1))?;
+        let mut position = target - 1 + reader.read_until(b'\n', &mut buf)? as u64;
+        
+        if options.multiline {
+            loop {
+                buf.clear();
+                let read = reader.read_until(b'\n', &mut buf)? as u64;
+                if read == 0 {
+                    break;
+                }
+                let line = String::from_utf8_lossy(&buf);
+                let line = line.trim_end_matches(['\n', '\r']);
+                let parsed = parser.and_then(|p| p.parse(line)).is_some();
+                if !line.trim().is_empty() && !options.is_continuation(line, parsed) {
+                    break;
+                }
+                position += read;
+            }
+        }
+        
+        if position > previous && position < len {
+            bounds.push(position);
+        }
+    }
     
-    Ok(analysis)
+    bounds.push(len);
+    Ok(bounds)
 }
 
-/// 解析单行日志
-fn parse_log_line(line: &str) -> Option<LogEntry> {
-    // 尝试多种常见的日志格式
-    if let Some(entry) = parse_standard_format(line) {
-        return Some(entry);
-    }
+/// 统计文件中 `start..end` 字节范围内的行
+fn aggregate_chunk(
+    path: &Path,
+    start: u64,
+    end: u64,
+    parser: Option<&dyn LogParser>,
+    options: &AnalyzeOptions,
+) -> RtoolsResult<LogAggregator> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
     
-    if let Some(entry) = parse_simple_format(line) {
-        return Some(entry);
+    let mut reader = LogReader::with_parser(BufReader::new(file.take(end - start)), parser, options);
+    let mut aggregator = LogAggregator::new(parser.map(|p| p.name()), options);
+    for entry in &mut reader {
+        aggregator.add_matching(&entry?, &options.filter);
     }
-    
-    // 如果无法解析，创建一个默认条目
-    Some(LogEntry {
-        timestamp: None,
-        level: LogLevel::Unknown("unknown".to_string()),
-        message: line.to_string(),
-        source: None,
-        line_number: None,
-    })
+    aggregator.add_line_counts(reader.unparsed_lines(), reader.continuation_lines());
+    Ok(aggregator)
+}
+
+/// 选择解析器: 指定了格式时按名称查找，否则用开头的若干行自动检测
+pub fn select_parser<'r, S: AsRef<str>>(
+    registry: &'r ParserRegistry,
+    options: &AnalyzeOptions,
+    sample: &[S],
+) -> RtoolsResult<Option<&'r dyn LogParser>> {
+    match &options.format {
+        Some(name) => Ok(Some(registry.require(name)?)),
+        None => Ok(registry.detect(&sample[..sample.len().min(options.sample_lines)])),
+    }
+}
+
+/// 把逐行输入的日志组合成条目
+///
+/// 开启多行合并时，一个条目要等到下一个起始行出现（或调用 `finish`）才会返回，
+/// 因为在此之前还可能有属于它的后续行。
+pub struct EntryAssembler<'a> {
+    parser: Option<&'a dyn LogParser>,
+    options: &'a AnalyzeOptions,
+    pending: Option<LogEntry>,
+    /// 上一条目是否是起始行，后续行只合并到起始行的条目中
+    pending_is_start: bool,
+    line_number: usize,
+    unparsed_lines: usize,
+    continuation_lines: usize,
 }
 
-/// 解析标准格式: [2023-01-01 12:00:00] [INFO] message
-fn parse_standard_format(line: &str) -> Option<LogEntry> {
-    let line = line.trim();
-    if !line.starts_with('[') {
-        return None;
+impl<'a> EntryAssembler<'a> {
+    pub fn new(parser: Option<&'a dyn LogParser>, options: &'a AnalyzeOptions) -> Self {
+        Self {
+            parser,
+            options,
+            pending: None,
+            pending_is_start: false,
+            line_number: 0,
+            unparsed_lines: 0,
+            continuation_lines: 0,
+        }
     }
     
-    let parts: Vec<&str> = line.split(']').collect();
-    if parts.len() < 3 {
-        return None;
+    /// 输入下一行，返回已经完整的条目
+    pub fn push(&mut self, line: &str) -> Option<LogEntry> {
+        self.line_number += 1;
+        let parsed = self.parser.and_then(|p| p.parse(line));
+        
+        if self.options.multiline {
+            if line.trim().is_empty() {
+                return None;
+            }
+            
+            let is_continuation = self.options.is_continuation(line, parsed.is_some());
+            if is_continuation && self.pending_is_start
+                && let Some(pending) = self.pending.as_mut()
+            {
+                if pending.continuation.len() < MAX_CONTINUATION_LINES {
+                    pending.continuation.push(line.to_string());
+                } else {
+                    pending.omitted_lines += 1;
+                }
+                self.continuation_lines += 1;
+                return None;
+            }
+            self.pending_is_start = !is_continuation;
+        }
+        
+        let mut entry = match parsed {
+            Some(entry) => entry,
+            None => {
+                self.unparsed_lines += 1;
+                LogEntry::unparsed(line)
+            }
+        };
+        entry.line_number = Some(self.line_number);
+        entry.raw = line.to_string();
+        
+        if self.options.multiline {
+            self.pending.replace(entry)
+        } else {
+            Some(entry)
+        }
     }
     
-    // 解析时间戳
-    let timestamp_str = parts[0].trim_start_matches('[');
-    let timestamp = parse_timestamp(timestamp_str);
+    /// 输入结束，返回最后一个条目
+    pub fn finish(&mut self) -> Option<LogEntry> {
+        self.pending_is_start = false;
+        self.pending.take()
+    }
     
-    // 解析日志级别
-    let level_str = parts[1].trim_start_matches('[').trim();
-    let level = LogLevel::parse(level_str);
+    pub fn unparsed_lines(&self) -> usize {
+        self.unparsed_lines
+    }
     
-    // 解析消息
-    let message = parts[2..].join("]").trim().to_string();
+    pub fn continuation_lines(&self) -> usize {
+        self.continuation_lines
+    }
+}
+
+/// 用指定的解析器分析日志行，没有解析器或解析失败的行作为未知级别的条目统计
+pub fn analyze_lines(lines: &[&str], parser: Option<&dyn LogParser>, options: &AnalyzeOptions) -> LogAnalysis {
+    let mut assembler = EntryAssembler::new(parser, options);
+    let mut aggregator = LogAggregator::new(parser.map(|p| p.name()), options);
     
-    Some(LogEntry {
-        timestamp,
-        level,
-        message,
-        source: None,
-        line_number: None,
-    })
+    for line in lines {
+        if let Some(entry) = assembler.push(line) {
+            aggregator.add_matching(&entry, &options.filter);
+        }
+    }
+    if let Some(entry) = assembler.finish() {
+        aggregator.add_matching(&entry, &options.filter);
+    }
+    
+    let mut analysis = aggregator.analysis();
+    analysis.unparsed_lines = assembler.unparsed_lines();
+    analysis.continuation_lines = assembler.continuation_lines();
+    analysis
+}
+
+/// 逐条累计的日志统计，`analysis` 可以随时取得到目前为止的分析结果
+///
+/// 消息、错误模式、主机和分组用 `TopK` 只保留最多的 `top_k` 个，内存占用有上限。
+#[derive(Debug, Clone)]
+pub struct LogAggregator {
+    analysis: LogAnalysis,
+    messages: TopK,
+    distinct_messages: DistinctCounter,
+    error_patterns: TopK,
+    hosts: TopK,
+    /// 各组的条目数和错误数，没有分组字段的条目单独计数
+    groups: TopK,
+    group_errors: TopK,
+    ungrouped: GroupStats,
+    access: AccessCollector,
 }
 
-/// 解析简单格式: 2023-01-01 12:00:00 INFO message
-fn parse_simple_format(line: &str) -> Option<LogEntry> {
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.len() < 3 {
-        return None;
+impl LogAggregator {
+    pub fn new(format: Option<&str>, options: &AnalyzeOptions) -> Self {
+        let analysis = LogAnalysis {
+            format: format.map(str::to_string),
+            group_field: options.group_by.clone(),
+            ..LogAnalysis::default()
+        };
+        Self {
+            analysis,
+            messages: TopK::new(options.top_k),
+            distinct_messages: DistinctCounter::new(),
+            error_patterns: TopK::new(options.top_k),
+            hosts: TopK::new(options.top_k),
+            groups: TopK::new(options.top_k),
+            group_errors: TopK::new(options.top_k),
+            ungrouped: GroupStats {
+                key: None,
+                count: 0,
+                errors: 0,
+            },
+            access: AccessCollector::with_top_k(options.top_k),
+        }
     }
     
-    // 尝试解析时间戳
-    let timestamp_str = format!("{} {}", parts[0], parts[1]);
-    let timestamp = parse_timestamp(&timestamp_str);
+    /// 累计一个条目
+    pub fn add(&mut self, entry: &LogEntry) {
+        let analysis = &mut self.analysis;
+        analysis.total_entries += 1;
+        if !entry.continuation.is_empty() {
+            analysis.multiline_entries += 1;
+        }
+        
+        // 日志级别、设施和主机分布
+        *analysis.level_distribution.entry(entry.level.clone()).or_insert(0) += 1;
+        if let Some(facility) = entry.fields.get(FACILITY_FIELD) {
+            *analysis.facility_distribution.entry(facility.clone()).or_insert(0) += 1;
+        }
+        if let Some(host) = entry.fields.get(HOST_FIELD) {
+            self.hosts.add(host);
+        }
+        
+        // 时间分布和时间范围
+        if let Some(ts) = entry.timestamp {
+            let hour_key = ts.format("%Y-%m-%d %H").to_string();
+            *analysis.time_distribution.entry(hour_key).or_insert(0) += 1;
+            analysis.time_range = Some(match analysis.time_range {
+                Some((start, end)) => (start.min(ts), end.max(ts)),
+                None => (ts, ts),
+            });
+        }
+        
+        // 错误模式
+        let is_error = entry.level.severity() >= LogLevel::Error.severity();
+        if is_error {
+            for word in entry.message.split_whitespace() {
+                if word.len() > 3 && word.chars().any(|c| c.is_uppercase()) {
+                    self.error_patterns.add(word);
+                }
+            }
+        }
+        
+        self.messages.add(&entry.message);
+        self.distinct_messages.add(entry.message.as_str());
+        self.access.add(entry);
+        
+        if let Some(field) = &analysis.group_field {
+            match entry.field(field) {
+                Some(key) => {
+                    self.groups.add(key);
+                    if is_error {
+                        self.group_errors.add(key);
+                    }
+                }
+                None => {
+                    self.ungrouped.count += 1;
+                    if is_error {
+                        self.ungrouped.errors += 1;
+                    }
+                }
+            }
+        }
+    }
     
-    // 解析日志级别
-    let level = LogLevel::parse(parts[2]);
+    /// 条目通过过滤条件时累计并返回 `true`，否则只记录被过滤的条目数
+    pub fn add_matching(&mut self, entry: &LogEntry, filter: &LogFilter) -> bool {
+        let matches = filter.matches(entry);
+        if matches {
+            self.add(entry);
+        } else {
+            self.analysis.filtered_entries += 1;
+        }
+        matches
+    }
     
-    // 解析消息
-    let message = parts[3..].join(" ");
+    /// 记录组合条目时无法解析的行数和合并的后续行数
+    fn add_line_counts(&mut self, unparsed_lines: usize, continuation_lines: usize) {
+        self.analysis.unparsed_lines += unparsed_lines;
+        self.analysis.continuation_lines += continuation_lines;
+    }
     
-    Some(LogEntry {
-        timestamp,
-        level,
-        message,
-        source: None,
-        line_number: None,
-    })
-}
-
-/// 解析时间戳
-fn parse_timestamp(timestamp_str: &str) -> Option<DateTime<Utc>> {
-    // 尝试多种时间格式
-    let formats = [
-        "%Y-%m-%d %H:%M:%S",
-        "%Y-%m-%d %H:%M:%S%.3f",
-        "%Y-%m-%dT%H:%M:%S",
-        "%Y-%m-%dT%H:%M:%SZ",
-    ];
+    fn file_summary(&self, path: &Path, compression: Compression) -> FileSummary {
+        let analysis = &self.analysis;
+        let errors = analysis
+            .level_distribution
+            .iter()
+            .filter(|(level, _)| level.severity() >= LogLevel::Error.severity())
+            .map(|(_, count)| count)
+            .sum();
+        FileSummary {
+            path: path.to_path_buf(),
+            compression,
+            format: analysis.format.clone(),
+            total_entries: analysis.total_entries,
+            errors,
+            unparsed_lines: analysis.unparsed_lines,
+            time_range: analysis.time_range,
+        }
+    }
     
-    for format in &formats {
-        if let Ok(naive) = NaiveDateTime::parse_from_str(timestamp_str, format) {
-            return Some(DateTime::from_naive_utc_and_offset(naive, Utc));
+    /// 合并另一个统计，用于并行处理或多个文件后汇总
+    pub fn merge(&mut self, other: &LogAggregator) {
+        let analysis = &mut self.analysis;
+        let theirs = &other.analysis;
+        analysis.unparsed_lines += theirs.unparsed_lines;
+        analysis.continuation_lines += theirs.continuation_lines;
+        analysis.total_entries += theirs.total_entries;
+        analysis.multiline_entries += theirs.multiline_entries;
+        analysis.filtered_entries += theirs.filtered_entries;
+        merge_counts(&mut analysis.level_distribution, &theirs.level_distribution);
+        merge_counts(&mut analysis.facility_distribution, &theirs.facility_distribution);
+        merge_counts(&mut analysis.time_distribution, &theirs.time_distribution);
+        if let Some((start, end)) = theirs.time_range {
+            analysis.time_range = Some(match analysis.time_range {
+                Some((s, e)) => (s.min(start), e.max(end)),
+                None => (start, end),
+            });
         }
+        
+        self.messages.merge(&other.messages);
+        self.distinct_messages.merge(&other.distinct_messages);
+        self.error_patterns.merge(&other.error_patterns);
+        self.hosts.merge(&other.hosts);
+        self.groups.merge(&other.groups);
+        self.group_errors.merge(&other.group_errors);
+        self.ungrouped.count += other.ungrouped.count;
+        self.ungrouped.errors += other.ungrouped.errors;
+        self.access.merge(&other.access);
     }
     
-    None
-} 
\ No newline at end of file
+    pub fn total_entries(&self) -> usize {
+        self.analysis.total_entries
+    }
+    
+    pub fn level_distribution(&self) -> &HashMap<LogLevel, usize> {
+        &self.analysis.level_distribution
+    }
+    
+    /// 到目前为止的分析结果
+    pub fn analysis(&self) -> LogAnalysis {
+        let mut analysis = self.analysis.clone();
+        
+        analysis.top_messages = top_counts(&self.messages).collect();
+        analysis.distinct_messages = self.distinct_messages.estimate();
+        analysis.error_patterns = top_counts(&self.error_patterns).collect();
+        analysis.host_distribution = top_counts(&self.hosts).collect();
+        
+        // 条目数相同时按字段值排序
+        let mut groups: Vec<GroupStats> = top_counts(&self.groups)
+            .map(|(key, count)| {
+                let errors = self.group_errors.estimate(&key).unwrap_or(0) as usize;
+                GroupStats {
+                    key: Some(key),
+                    count,
+                    errors: errors.min(count),
+                }
+            })
+            .collect();
+        if self.ungrouped.count > 0 {
+            groups.push(self.ungrouped.clone());
+        }
+        groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+        analysis.groups = groups;
+        
+        analysis.approximate = [&self.messages, &self.error_patterns, &self.hosts, &self.groups]
+            .iter()
+            .any(|counts| !counts.is_exact());
+        analysis.access = self.access.stats();
+        analysis
+    }
+}
+
+/// 按次数从多到少列出 `TopK` 中的所有键
+fn top_counts(counts: &TopK) -> impl Iterator<Item = (String, usize)> {
+    counts.top(counts.len()).into_iter().map(|(key, count)| (key, count as usize))
+}
+
+fn merge_counts<K: Clone + Eq + std::hash::Hash>(counts: &mut HashMap<K, usize>, other: &HashMap<K, usize>) {
+    for (key, count) in other {
+        *counts.entry(key.clone()).or_insert(0) += count;
+    }
+}
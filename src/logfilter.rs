@@ -0,0 +1,176 @@
+use std::io::{self, Write};
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::Regex;
+use crate::dirscan::parse_age;
+use crate::loganalyzer::{LogEntry, LogLevel};
+use crate::logparser::parse_timestamp;
+use crate::{RtoolsResult, RtoolsError};
+
+/// 日志条目的过滤条件，所有条件都满足时条目才通过
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// 最低级别，未知级别的条目只在最低级别为调试时通过
+    pub min_level: Option<LogLevel>,
+    /// 时间范围（包含两端），设置后没有时间戳的条目不通过
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// 在消息和后续行中搜索的正则表达式
+    pub pattern: Option<Regex>,
+    /// 来源包含该文本
+    pub source: Option<String>,
+    /// 字段等于指定的值，字段按 `LogEntry::field` 的规则查找
+    pub fields: Vec<(String, String)>,
+}
+
+impl LogFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    pub fn with_since(mut self, time: DateTime<Utc>) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    pub fn with_until(mut self, time: DateTime<Utc>) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: Regex) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn with_field(mut self, name: &str, value: &str) -> Self {
+        self.fields.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// 没有任何条件
+    pub fn is_empty(&self) -> bool {
+        self.min_level.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.pattern.is_none()
+            && self.source.is_none()
+            && self.fields.is_empty()
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if let Some(level) = &self.min_level {
+            let passes = match entry.level {
+                LogLevel::Unknown(_) => *level == LogLevel::Debug,
+                _ => entry.level.severity() >= level.severity(),
+            };
+            if !passes {
+                return false;
+            }
+        }
+
+        if self.since.is_some() || self.until.is_some() {
+            let Some(timestamp) = entry.timestamp else {
+                return false;
+            };
+            if self.since.is_some_and(|since| timestamp < since) || self.until.is_some_and(|until| timestamp > until) {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = &self.pattern
+            && !pattern.is_match(&entry.message)
+            && !entry.continuation.iter().any(|line| pattern.is_match(line))
+        {
+            return false;
+        }
+
+        if let Some(source) = &self.source
+            && !entry.source.as_deref().is_some_and(|s| s.contains(source.as_str()))
+        {
+            return false;
+        }
+
+        self.fields
+            .iter()
+            .all(|(name, value)| entry.field(name) == Some(value.as_str()))
+    }
+}
+
+/// 解析最低级别，不接受未知的级别名称
+pub fn parse_level(s: &str) -> RtoolsResult<LogLevel> {
+    match LogLevel::parse(s) {
+        LogLevel::Unknown(_) => Err(RtoolsError::InvalidArgument(format!(
+            "无效的日志级别 '{}'，可选: debug, info, warning, error, critical", s
+        ))),
+        level => Ok(level),
+    }
+}
+
+/// 解析时间范围的边界: `1h`、`30m`、`2d` 等表示距现在的时长，其余按日志时间戳的格式（视为UTC）解析，
+/// 也接受 `2023-01-01` 和 `2023-01-01 12:00`
+pub fn parse_time_bound(s: &str) -> RtoolsResult<DateTime<Utc>> {
+    let s = s.trim();
+
+    if s.starts_with(|c: char| c.is_ascii_digit()) && !s.contains(['-', '/', ':']) {
+        let age = chrono::Duration::from_std(parse_age(s)?)
+            .map_err(|_| RtoolsError::InvalidArgument(format!("时长超出范围: {}", s)))?;
+        return Utc::now()
+            .checked_sub_signed(age)
+            .ok_or_else(|| RtoolsError::InvalidArgument(format!("时长超出范围: {}", s)));
+    }
+
+    parse_timestamp(s)
+        .or_else(|| parse_timestamp(&format!("{}:00", s)))
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|naive| DateTime::from_naive_utc_and_offset(naive, Utc))
+        })
+        .ok_or_else(|| RtoolsError::InvalidArgument(format!("无效的时间: {}", s)))
+}
+
+/// 查询结果的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntryFormat {
+    /// 原始的日志行，多行条目输出所有后续行
+    #[default]
+    Text,
+    /// 每行一个JSON对象
+    Json,
+}
+
+impl EntryFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// 按指定格式输出条目
+pub fn write_entries<'a, W: Write>(
+    writer: &mut W,
+    entries: impl IntoIterator<Item = &'a LogEntry>,
+    format: EntryFormat,
+) -> io::Result<()> {
+    for entry in entries {
+        match format {
+            EntryFormat::Text => writeln!(writer, "{}", entry.original_text())?,
+            EntryFormat::Json => writeln!(writer, "{}", entry.to_json())?,
+        }
+    }
+    Ok(())
+}
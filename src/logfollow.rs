@@ -0,0 +1,329 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use crate::RtoolsResult;
+use crate::loganalyzer::{AnalyzeOptions, EntryAssembler, LogEntry, LogLevel, read_line, select_parser};
+use crate::logparser::{LogParser, ParserRegistry};
+
+/// 像 `tail -F` 一样跟踪文件新增的行
+///
+/// 每次调用 `read_lines` 读取上次之后写入的完整行，不完整的最后一行留到下次。
+/// 文件被截断时从头开始读；文件被轮转（路径指向了新文件）时先读完旧文件剩余的内容，再从头读新文件；
+/// 文件不存在时等待它被创建。
+#[derive(Debug)]
+pub struct FileFollower {
+    path: PathBuf,
+    file: Option<File>,
+    identity: Option<(u64, u64)>,
+    position: u64,
+    partial: Vec<u8>,
+    /// 第一次打开文件时是否从末尾开始
+    start_at_end: bool,
+}
+
+impl FileFollower {
+    /// 从文件当前的末尾开始跟踪
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            file: None,
+            identity: None,
+            position: 0,
+            partial: Vec::new(),
+            start_at_end: true,
+        }
+    }
+
+    /// 从文件开头开始读取
+    pub fn from_start<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            start_at_end: false,
+            ..Self::new(path)
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取新增的完整行
+    pub fn read_lines(&mut self) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+
+        if self.file.is_none() && !self.open()? {
+            // 之后创建的文件从头读取
+            self.start_at_end = false;
+            return Ok(lines);
+        }
+
+        // 文件变短说明被截断，从头开始读
+        if let Some(file) = &self.file
+            && file.metadata()?.len() < self.position
+        {
+            self.position = 0;
+            self.partial.clear();
+        }
+        self.read_available(&mut lines)?;
+
+        // 路径指向了另一个文件说明发生了轮转，旧文件已经读完
+        let rotated = match fs::metadata(&self.path) {
+            Ok(metadata) => file_identity(&metadata).is_some_and(|id| Some(id) != self.identity),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e),
+        };
+        if rotated {
+            if !self.partial.is_empty() {
+                let rest = std::mem::take(&mut self.partial);
+                lines.push(decode_line(&rest));
+            }
+            self.file = None;
+            self.start_at_end = false;
+            if self.open()? {
+                self.read_available(&mut lines)?;
+            }
+        }
+
+        Ok(lines)
+    }
+
+    /// 打开文件，文件不存在时返回 `false`
+    fn open(&mut self) -> io::Result<bool> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        let metadata = file.metadata()?;
+        self.identity = file_identity(&metadata);
+        self.position = if self.start_at_end { metadata.len() } else { 0 };
+        self.start_at_end = false;
+        self.partial.clear();
+        self.file = Some(file);
+        Ok(true)
+    }
+
+    fn read_available(&mut self, lines: &mut Vec<String>) -> io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+
+        file.seek(SeekFrom::Start(self.position))?;
+        let read = file.read_to_end(&mut self.partial)?;
+        self.position += read as u64;
+
+        if let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') {
+            let rest = self.partial.split_off(end + 1);
+            let complete = std::mem::replace(&mut self.partial, rest);
+            lines.extend(complete[..end].split(|&b| b == b'\n').map(decode_line));
+        }
+        Ok(())
+    }
+}
+
+/// 跟踪日志文件，把新增的行组合成条目
+///
+/// 没有指定格式时用文件开头的若干行检测格式；文件为空或不存在时用之后最先读到的一批行检测，
+/// 格式确定之前不会组合条目。
+pub struct EntryFollower<'a> {
+    follower: FileFollower,
+    registry: &'a ParserRegistry,
+    options: &'a AnalyzeOptions,
+    parser: Option<&'a dyn LogParser>,
+    /// 格式确定之后才创建
+    assembler: Option<EntryAssembler<'a>>,
+    idle: bool,
+}
+
+impl<'a> EntryFollower<'a> {
+    /// 从文件当前的末尾开始跟踪
+    pub fn new<P: AsRef<Path>>(path: P, registry: &'a ParserRegistry, options: &'a AnalyzeOptions) -> RtoolsResult<Self> {
+        let path = path.as_ref();
+        let mut sample = Vec::new();
+        if options.format.is_none()
+            && let Ok(file) = File::open(path)
+        {
+            let mut reader = BufReader::new(file);
+            let mut buf = Vec::new();
+            while sample.len() < options.sample_lines {
+                match read_line(&mut reader, &mut buf)? {
+                    Some(line) => sample.push(line),
+                    None => break,
+                }
+            }
+        }
+
+        let parser = select_parser(registry, options, &sample)?;
+        let resolved = parser.is_some() || !sample.is_empty();
+        Ok(Self {
+            follower: FileFollower::new(path),
+            registry,
+            options,
+            parser,
+            assembler: resolved.then(|| EntryAssembler::new(parser, options)),
+            idle: false,
+        })
+    }
+
+    pub fn parser(&self) -> Option<&'a dyn LogParser> {
+        self.parser
+    }
+
+    /// 格式是否已经确定，检测不出格式时也算确定
+    pub fn is_resolved(&self) -> bool {
+        self.assembler.is_some()
+    }
+
+    /// 上一次 `poll` 是否没有读到新的行
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// 读取新增的行，返回已经完整的条目；没有新的行时不再等待后续行，返回最后一个条目
+    pub fn poll(&mut self) -> RtoolsResult<Vec<LogEntry>> {
+        let lines = self.follower.read_lines()?;
+        self.idle = lines.is_empty();
+
+        if self.assembler.is_none() && !lines.is_empty() {
+            self.parser = select_parser(self.registry, self.options, &lines)?;
+            self.assembler = Some(EntryAssembler::new(self.parser, self.options));
+        }
+        let Some(assembler) = self.assembler.as_mut() else {
+            return Ok(Vec::new());
+        };
+
+        let mut entries: Vec<LogEntry> = lines.iter().filter_map(|line| assembler.push(line)).collect();
+        if lines.is_empty() {
+            entries.extend(assembler.finish());
+        }
+        Ok(entries)
+    }
+}
+
+fn decode_line(bytes: &[u8]) -> String {
+    let line = String::from_utf8_lossy(bytes);
+    line.strip_suffix('\r').unwrap_or(&line).to_string()
+}
+
+#[cfg(unix)]
+fn file_identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// 最近一段时间内各级别的条目数，按条目到达的时间计算
+///
+/// 条目按到达的秒数分桶计数，内存只和窗口的秒数有关，与条目的多少无关。
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    window: chrono::Duration,
+    /// 每秒一个桶: (Unix 秒数, 各级别的条目数)
+    buckets: VecDeque<(i64, HashMap<LogLevel, usize>)>,
+    /// 窗口内各级别的条目数，等于所有桶之和
+    counts: HashMap<LogLevel, usize>,
+    total: usize,
+}
+
+impl RollingStats {
+    pub fn new(window: std::time::Duration) -> Self {
+        Self {
+            window: chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX),
+            buckets: VecDeque::new(),
+            counts: HashMap::new(),
+            total: 0,
+        }
+    }
+
+    pub fn window(&self) -> chrono::Duration {
+        self.window
+    }
+
+    /// 记录在指定时间到达的条目，时间应当不早于之前记录的条目
+    pub fn add_at(&mut self, time: DateTime<Utc>, level: LogLevel) {
+        let second = time.timestamp();
+        match self.buckets.back_mut() {
+            Some((last, bucket)) if *last >= second => *bucket.entry(level.clone()).or_insert(0) += 1,
+            _ => self.buckets.push_back((second, HashMap::from([(level.clone(), 1)]))),
+        }
+        *self.counts.entry(level).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    pub fn add(&mut self, level: LogLevel) {
+        self.add_at(Utc::now(), level);
+    }
+
+    /// 丢弃窗口之外的条目
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        let Some(start) = now.checked_sub_signed(self.window) else {
+            return;
+        };
+        while let Some((second, _)) = self.buckets.front()
+            && *second <= start.timestamp()
+        {
+            let Some((_, bucket)) = self.buckets.pop_front() else {
+                break;
+            };
+            for (level, count) in bucket {
+                self.total -= count;
+                if let Some(remaining) = self.counts.get_mut(&level) {
+                    *remaining -= count;
+                    if *remaining == 0 {
+                        self.counts.remove(&level);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 窗口内的条目数
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// 窗口内各级别的条目数
+    pub fn level_counts(&self) -> HashMap<LogLevel, usize> {
+        self.counts.clone()
+    }
+
+    /// 窗口内错误及以上级别条目所占的比例，没有条目时为0
+    pub fn error_rate(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let errors: usize = self
+            .counts
+            .iter()
+            .filter(|(level, _)| level.severity() >= LogLevel::Error.severity())
+            .map(|(_, count)| count)
+            .sum();
+        errors as f64 / self.total as f64
+    }
+
+    /// 一行摘要，如 `最近 5 分钟: 120 条 (INFO 100, WARNING 17, ERROR 3), 错误率 2.5%`
+    pub fn summary(&self) -> String {
+        let mut counts: Vec<(LogLevel, usize)> = self.level_counts().into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name().cmp(b.0.name())));
+        let levels: Vec<String> = counts
+            .iter()
+            .map(|(level, count)| {
+                let name = if level.name().is_empty() { "UNKNOWN" } else { level.name() };
+                format!("{} {}", name, count)
+            })
+            .collect();
+
+        let mut summary = format!("最近 {} 分钟: {} 条", self.window.num_minutes(), self.total());
+        if !levels.is_empty() {
+            summary.push_str(&format!(" ({}), 错误率 {:.1}%", levels.join(", "), self.error_rate() * 100.0));
+        }
+        summary
+    }
+}
@@ -0,0 +1,467 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+use serde_json::{Map, Value};
+use crate::config::ConfigManager;
+use crate::accesslog::AccessLogParser;
+use crate::loganalyzer::{LogEntry, LogLevel};
+use crate::syslog::{Rfc5424Parser, SyslogParser};
+use crate::{RtoolsResult, RtoolsError};
+
+/// 自动检测格式时默认采样的行数
+pub const DEFAULT_SAMPLE_LINES: usize = 50;
+
+/// 日志格式解析器
+///
+/// 实现者只需要识别自己的格式：无法解析的行返回 `None`，
+/// 自动检测时以采样行中成功解析的行数来选择解析器。
+pub trait LogParser: Send + Sync {
+    /// 格式名称，用于 `--format` 选项和注册表查找
+    fn name(&self) -> &str;
+
+    /// 格式的简短说明
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// 解析一行日志，不属于该格式时返回 `None`
+    fn parse(&self, line: &str) -> Option<LogEntry>;
+}
+
+/// 解析标准格式: `[2023-01-01 12:00:00] [INFO] message`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StandardParser;
+
+impl LogParser for StandardParser {
+    fn name(&self) -> &str {
+        "standard"
+    }
+
+    fn description(&self) -> &str {
+        "[2023-01-01 12:00:00] [INFO] message"
+    }
+
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let line = line.trim();
+        if !line.starts_with('[') {
+            return None;
+        }
+
+        let parts: Vec<&str> = line.split(']').collect();
+        if parts.len() < 3 {
+            return None;
+        }
+
+        let timestamp = parse_timestamp(parts[0].trim_start_matches('['));
+        let level = LogLevel::parse(parts[1].trim_start_matches('[').trim());
+        let message = parts[2..].join("]").trim().to_string();
+
+        Some(LogEntry::new(timestamp, level, message))
+    }
+}
+
+/// 解析简单格式: `2023-01-01 12:00:00 INFO message`，要求行首是有效的时间戳
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimpleParser;
+
+impl LogParser for SimpleParser {
+    fn name(&self) -> &str {
+        "simple"
+    }
+
+    fn description(&self) -> &str {
+        "2023-01-01 12:00:00 INFO message"
+    }
+
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 3 {
+            return None;
+        }
+
+        let timestamp = parse_timestamp(&format!("{} {}", parts[0], parts[1]))?;
+        let level = LogLevel::parse(parts[2]);
+        let message = parts[3..].join(" ");
+
+        Some(LogEntry::new(Some(timestamp), level, message))
+    }
+}
+
+/// JSON 日志中时间戳字段的默认名称，按顺序查找
+pub const DEFAULT_TIMESTAMP_FIELDS: &[&str] = &["ts", "timestamp", "time", "@timestamp"];
+/// 级别字段的默认名称
+pub const DEFAULT_LEVEL_FIELDS: &[&str] = &["level", "lvl", "severity"];
+/// 消息字段的默认名称
+pub const DEFAULT_MESSAGE_FIELDS: &[&str] = &["msg", "message"];
+
+/// 解析每行一个JSON对象的日志 (NDJSON): `{"ts":"...","level":"info","msg":"...","fields":{...}}`
+///
+/// 时间戳、级别和消息以外的字段保存在 `LogEntry::fields` 中，嵌套对象展开为 `fields.service` 这样的键。
+/// 时间戳可以是RFC 3339字符串或Unix时间（秒或毫秒），级别可以是名称或 bunyan/pino 的数字级别。
+#[derive(Debug, Clone)]
+pub struct JsonParser {
+    pub timestamp_fields: Vec<String>,
+    pub level_fields: Vec<String>,
+    pub message_fields: Vec<String>,
+}
+
+impl Default for JsonParser {
+    fn default() -> Self {
+        Self {
+            timestamp_fields: to_strings(DEFAULT_TIMESTAMP_FIELDS),
+            level_fields: to_strings(DEFAULT_LEVEL_FIELDS),
+            message_fields: to_strings(DEFAULT_MESSAGE_FIELDS),
+        }
+    }
+}
+
+impl JsonParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 只从指定字段读取时间戳
+    pub fn with_timestamp_field(mut self, field: &str) -> Self {
+        self.timestamp_fields = vec![field.to_string()];
+        self
+    }
+
+    pub fn with_level_field(mut self, field: &str) -> Self {
+        self.level_fields = vec![field.to_string()];
+        self
+    }
+
+    pub fn with_message_field(mut self, field: &str) -> Self {
+        self.message_fields = vec![field.to_string()];
+        self
+    }
+}
+
+impl LogParser for JsonParser {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn description(&self) -> &str {
+        "{\"ts\":\"2023-01-01T12:00:00Z\",\"level\":\"info\",\"msg\":\"message\"}"
+    }
+
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let line = line.trim();
+        if !line.starts_with('{') {
+            return None;
+        }
+
+        let mut object = match serde_json::from_str::<Value>(line).ok()? {
+            Value::Object(object) => object,
+            _ => return None,
+        };
+
+        let timestamp = take_field(&mut object, &self.timestamp_fields).and_then(|value| json_timestamp(&value));
+        let level = match take_field(&mut object, &self.level_fields) {
+            Some(Value::Number(n)) => n.as_i64().map_or(LogLevel::Unknown(n.to_string()), LogLevel::from_number),
+            Some(value) => LogLevel::parse(&json_to_string(&value)),
+            None => LogLevel::Unknown(String::new()),
+        };
+        let message = take_field(&mut object, &self.message_fields)
+            .map(|value| json_to_string(&value))
+            .unwrap_or_default();
+
+        let mut entry = LogEntry::new(timestamp, level, message);
+        flatten_fields("", object, &mut entry);
+        Some(entry)
+    }
+}
+
+fn to_strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// 取出第一个存在的字段
+fn take_field(object: &mut Map<String, Value>, names: &[String]) -> Option<Value> {
+    names.iter().find_map(|name| object.remove(name))
+}
+
+/// 字符串直接使用其内容，其他值使用JSON表示
+fn json_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 解析RFC 3339或其他常见格式的字符串，以及Unix时间戳（大于 10^12 的按毫秒处理）
+fn json_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::String(s) => parse_timestamp(s),
+        Value::Number(n) => {
+            let seconds = n.as_f64()?;
+            let millis = if seconds.abs() >= 1e12 { seconds } else { seconds * 1000.0 };
+            DateTime::from_timestamp_millis(millis as i64)
+        }
+        _ => None,
+    }
+}
+
+/// 把嵌套对象展开为以 `.` 连接的键，数组和标量值保存为字符串
+fn flatten_fields(prefix: &str, object: Map<String, Value>, entry: &mut LogEntry) {
+    for (key, value) in object {
+        let key = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+        match value {
+            Value::Object(nested) => flatten_fields(&key, nested, entry),
+            Value::Null => {}
+            other => {
+                entry.fields.insert(key, json_to_string(&other));
+            }
+        }
+    }
+}
+
+/// `RegexParser` 识别的命名分组，其余命名分组保存为字段
+pub const REGEX_GROUPS: [&str; 4] = ["timestamp", "level", "message", "source"];
+
+/// 用带命名分组的正则表达式解析自定义格式
+///
+/// `timestamp`、`level`、`message` 和 `source` 分组填入条目的对应字段，其他命名分组保存在 `LogEntry::fields` 中；
+/// 没有 `message` 分组时整行作为消息。时间戳格式使用 chrono 的格式字符串，未指定时按常见格式解析。
+#[derive(Debug, Clone)]
+pub struct RegexParser {
+    name: String,
+    regex: Regex,
+    pub timestamp_format: Option<String>,
+}
+
+impl RegexParser {
+    /// 编译正则表达式，语法错误返回包含出错位置（从0开始的字符位置）的 `ParseError`
+    pub fn new(name: &str, pattern: &str) -> RtoolsResult<Self> {
+        if let Err(e) = regex_syntax::Parser::new().parse(pattern) {
+            let span = match &e {
+                regex_syntax::Error::Parse(e) => Some(*e.span()),
+                regex_syntax::Error::Translate(e) => Some(*e.span()),
+                _ => None,
+            };
+            let message = match &e {
+                regex_syntax::Error::Parse(e) => e.kind().to_string(),
+                regex_syntax::Error::Translate(e) => e.kind().to_string(),
+                other => other.to_string(),
+            };
+            return Err(match span {
+                Some(span) => {
+                    let position = pattern[..span.start.offset].chars().count();
+                    RtoolsError::ParseError(format!("正则表达式位置 {}: {}", position, message))
+                }
+                None => RtoolsError::ParseError(format!("正则表达式: {}", message)),
+            });
+        }
+
+        let regex = Regex::new(pattern).map_err(|e| RtoolsError::ParseError(format!("正则表达式: {}", e)))?;
+        if regex.capture_names().flatten().next().is_none() {
+            return Err(RtoolsError::ParseError(format!(
+                "正则表达式 '{}' 没有命名分组，需要使用 (?P<message>...) 这样的分组", pattern
+            )));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            regex,
+            timestamp_format: None,
+        })
+    }
+
+    /// 指定时间戳格式，如 `%d.%m.%Y %H:%M:%S`；格式中有 `%z` 等时区说明时按该时区转换为UTC
+    pub fn with_timestamp_format(mut self, format: &str) -> Self {
+        self.timestamp_format = Some(format.to_string());
+        self
+    }
+
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    /// 从配置文件 (JSON/TOML/INI) 加载格式，每个格式是一个表或节:
+    ///
+    /// ```toml
+    /// [myapp]
+    /// pattern = '^(?P<timestamp>\S+ \S+) (?P<level>\w+) (?P<message>.*)$'
+    /// timestamp_format = "%Y/%m/%d %H:%M:%S"
+    /// ```
+    pub fn load_from_file(file_path: &str) -> RtoolsResult<Vec<Self>> {
+        let config = ConfigManager::load_from_file(file_path)?;
+
+        // JSON/TOML 中每个格式是嵌套的表，INI 中的节展开为 `名称.键`
+        let mut names: Vec<&str> = config
+            .keys()
+            .into_iter()
+            .filter_map(|key| match config.get(key).and_then(|value| value.as_map()) {
+                Some(_) => Some(key.as_str()),
+                None => key.rsplit_once('.').map(|(name, _)| name),
+            })
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+
+        let mut parsers = Vec::new();
+        for name in names {
+            let setting = |key: &str| config.get(&format!("{}.{}", name, key)).and_then(|value| value.as_string());
+            let pattern = setting("pattern").ok_or_else(|| {
+                RtoolsError::ConfigError(format!("{}: 格式 '{}' 缺少 pattern", file_path, name))
+            })?;
+            // 保留 ParseError 及其中的出错位置，只在前面加上文件和格式名称
+            let parser = Self::new(name, pattern).map_err(|e| match e {
+                RtoolsError::ParseError(message) => {
+                    RtoolsError::ParseError(format!("{}: 格式 '{}': {}", file_path, name, message))
+                }
+                other => other,
+            })?;
+            parsers.push(match setting("timestamp_format") {
+                Some(format) => parser.with_timestamp_format(format),
+                None => parser,
+            });
+        }
+
+        if parsers.is_empty() {
+            return Err(RtoolsError::ConfigError(format!("{}: 没有定义任何日志格式", file_path)));
+        }
+        Ok(parsers)
+    }
+
+    fn parse_time(&self, text: &str) -> Option<DateTime<Utc>> {
+        let Some(format) = &self.timestamp_format else {
+            return parse_timestamp(text);
+        };
+
+        if let Ok(time) = DateTime::parse_from_str(text, format) {
+            return Some(time.with_timezone(&Utc));
+        }
+        NaiveDateTime::parse_from_str(text, format)
+            .ok()
+            .map(|naive| DateTime::from_naive_utc_and_offset(naive, Utc))
+    }
+}
+
+impl LogParser for RegexParser {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let captures = self.regex.captures(line)?;
+        let group = |name: &str| captures.name(name).map(|m| m.as_str());
+
+        let timestamp = group("timestamp").and_then(|text| self.parse_time(text));
+        let level = group("level").map_or(LogLevel::Unknown(String::new()), LogLevel::parse);
+        let message = group("message").unwrap_or(line).trim().to_string();
+
+        let mut entry = LogEntry::new(timestamp, level, message);
+        entry.source = group("source").map(str::to_string);
+        for name in self.regex.capture_names().flatten() {
+            if !REGEX_GROUPS.contains(&name)
+                && let Some(value) = group(name)
+            {
+                entry.fields.insert(name.to_string(), value.to_string());
+            }
+        }
+        Some(entry)
+    }
+}
+
+/// 日志解析器注册表
+///
+/// `ParserRegistry::default()` 包含所有内置解析器，库的使用者可以用 `register` 添加自定义解析器。
+/// 自动检测时按注册顺序比较，成功解析行数相同时先注册的优先。
+pub struct ParserRegistry {
+    parsers: Vec<Box<dyn LogParser>>,
+}
+
+impl ParserRegistry {
+    /// 创建不包含任何解析器的注册表
+    pub fn empty() -> Self {
+        Self { parsers: Vec::new() }
+    }
+
+    /// 注册解析器，同名的解析器会被替换
+    pub fn register(&mut self, parser: Box<dyn LogParser>) {
+        match self.parsers.iter().position(|p| p.name() == parser.name()) {
+            Some(index) => self.parsers[index] = parser,
+            None => self.parsers.push(parser),
+        }
+    }
+
+    pub fn with_parser(mut self, parser: Box<dyn LogParser>) -> Self {
+        self.register(parser);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn LogParser> {
+        self.parsers.iter().find(|p| p.name() == name).map(|p| p.as_ref())
+    }
+
+    /// 按名称查找，找不到时返回列出可用格式的错误
+    pub fn require(&self, name: &str) -> RtoolsResult<&dyn LogParser> {
+        self.get(name).ok_or_else(|| {
+            RtoolsError::InvalidArgument(format!("未知的日志格式 '{}'，可选: {}", name, self.names().join(", ")))
+        })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.parsers.iter().map(|p| p.name()).collect()
+    }
+
+    pub fn parsers(&self) -> impl Iterator<Item = &dyn LogParser> {
+        self.parsers.iter().map(|p| p.as_ref())
+    }
+
+    /// 根据采样行检测格式，返回成功解析行数最多的解析器；没有解析器能解析任何一行时返回 `None`
+    pub fn detect<S: AsRef<str>>(&self, sample: &[S]) -> Option<&dyn LogParser> {
+        let lines: Vec<&str> = sample
+            .iter()
+            .map(|line| line.as_ref())
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        let mut best: Option<(&dyn LogParser, usize)> = None;
+        for parser in self.parsers() {
+            let parsed = lines.iter().filter(|line| parser.parse(line).is_some()).count();
+            if parsed > 0 && best.is_none_or(|(_, count)| parsed > count) {
+                best = Some((parser, parsed));
+            }
+        }
+
+        best.map(|(parser, _)| parser)
+    }
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .with_parser(Box::new(StandardParser))
+            .with_parser(Box::new(SimpleParser))
+            .with_parser(Box::new(JsonParser::new()))
+            .with_parser(Box::new(SyslogParser::new()))
+            .with_parser(Box::new(Rfc5424Parser))
+            .with_parser(Box::new(AccessLogParser::combined()))
+            .with_parser(Box::new(AccessLogParser::common()))
+    }
+}
+
+/// 解析常见格式的时间戳，带时区的RFC 3339时间转换为UTC
+pub fn parse_timestamp(timestamp_str: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(timestamp_str) {
+        return Some(time.with_timezone(&Utc));
+    }
+
+    let formats = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M:%S%.3f",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M:%SZ",
+    ];
+
+    formats
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(timestamp_str, format).ok())
+        .map(|naive| DateTime::from_naive_utc_and_offset(naive, Utc))
+}
@@ -0,0 +1,191 @@
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use crate::pattern::NamePattern;
+use crate::{RtoolsResult, RtoolsError};
+
+/// 日志文件的压缩格式，按文件开头的魔数识别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// 根据文件开头的字节判断压缩格式
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Self::Gzip
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Self::Zstd
+        } else if header.starts_with(b"BZh") {
+            Self::Bzip2
+        } else {
+            Self::None
+        }
+    }
+
+    /// 读取文件开头判断压缩格式
+    pub fn of_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut header = [0u8; 4];
+        let mut file = File::open(path)?;
+        let mut read = 0;
+        while read < header.len() {
+            match file.read(&mut header[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        Ok(Self::detect(&header[..read]))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Bzip2 => "bzip2",
+        }
+    }
+
+    /// 压缩文件常用的扩展名
+    fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+            Self::Bzip2 => Some("bz2"),
+        }
+    }
+}
+
+/// 打开日志文件用于逐行读取，gzip、zstd 和 bzip2 压缩的文件自动解压
+pub fn open_log_file<P: AsRef<Path>>(path: P) -> RtoolsResult<Box<dyn BufRead + Send>> {
+    let path = path.as_ref();
+
+    if !path.exists() {
+        return Err(RtoolsError::FileNotFound(path.display().to_string()));
+    }
+
+    let file = File::open(path)?;
+    Ok(match Compression::of_file(path)? {
+        Compression::None => Box::new(BufReader::new(file)),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::new(file)?)),
+        Compression::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(file))),
+    })
+}
+
+/// 把命令行给出的文件、目录和通配符展开为日志文件列表，按轮转的先后排序
+///
+/// 目录展开为其中的文件（不递归，跳过隐藏文件）；通配符只能出现在文件名部分，如 `/var/log/app.log*`。
+pub fn resolve_log_paths<S: AsRef<str>>(inputs: &[S]) -> RtoolsResult<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for input in inputs {
+        let input = input.as_ref();
+        let path = Path::new(input);
+
+        if path.is_dir() {
+            paths.extend(list_files(path, |_| true)?);
+        } else if input.contains(['*', '?', '[', '{']) {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            if dir.to_string_lossy().contains(['*', '?', '[', '{']) {
+                return Err(RtoolsError::InvalidArgument(format!("通配符只能用于文件名: {}", input)));
+            }
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let pattern = NamePattern::glob(&name, true)?;
+            let matched = list_files(dir, |file_name| pattern.matches(file_name, file_name))?;
+            if matched.is_empty() {
+                return Err(RtoolsError::FileNotFound(input.to_string()));
+            }
+            paths.extend(matched);
+        } else if path.exists() {
+            paths.push(path.to_path_buf());
+        } else {
+            return Err(RtoolsError::FileNotFound(input.to_string()));
+        }
+    }
+
+    sort_by_rotation(&mut paths);
+    paths.dedup();
+    Ok(paths)
+}
+
+fn list_files(dir: &Path, accept: impl Fn(&str) -> bool) -> RtoolsResult<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Err(RtoolsError::DirectoryNotFound(dir.display().to_string()));
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with('.') && entry.file_type()?.is_file() && accept(&name) {
+            files.push(entry.path());
+        }
+    }
+    Ok(files)
+}
+
+/// 按轮转的先后排序: 同一日志的轮转文件排在一起，最旧的在前
+///
+/// `app.log.3.gz`、`app.log.2`、`app.log.1` 按编号从大到小排列，`app.log-20230101` 按日期排列，
+/// 没有后缀的 `app.log` 是当前文件，排在最后。
+pub fn sort_by_rotation(paths: &mut [PathBuf]) {
+    paths.sort_by_cached_key(|path| {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let (base, age) = rotation_suffix(&name);
+        (path.parent().map(Path::to_path_buf), base, age, name)
+    });
+}
+
+/// 轮转后缀表示的新旧程度，越小越旧
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum RotationAge {
+    /// `.N` 编号，编号越大越旧
+    Numbered(Reverse<u64>),
+    /// `-YYYYMMDD` 等日期后缀
+    Dated(String),
+    Current,
+}
+
+/// 拆出文件名中的轮转后缀，返回日志的基本名称和新旧程度
+fn rotation_suffix(name: &str) -> (String, RotationAge) {
+    // 先去掉压缩扩展名
+    let mut stem = name;
+    for compression in [Compression::Gzip, Compression::Zstd, Compression::Bzip2] {
+        if let Some(ext) = compression.extension()
+            && let Some(rest) = stem.strip_suffix(ext).and_then(|s| s.strip_suffix('.'))
+        {
+            stem = rest;
+            break;
+        }
+    }
+
+    if let Some((base, number)) = stem.rsplit_once('.')
+        && !number.is_empty()
+        && number.len() < 8
+        && number.bytes().all(|b| b.is_ascii_digit())
+    {
+        return (base.to_string(), RotationAge::Numbered(Reverse(number.parse().unwrap_or(0))));
+    }
+
+    if let Some((base, date)) = stem.rsplit_once(['-', '.', '_'])
+        && date.len() >= 8
+        && date.bytes().all(|b| b.is_ascii_digit())
+    {
+        return (base.to_string(), RotationAge::Dated(date.to_string()));
+    }
+
+    (stem.to_string(), RotationAge::Current)
+}
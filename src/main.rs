@@ -1,10 +1,24 @@
 use std::env;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
 use std::process;
-use rtools::{ToolType, get_help_text, get_file_info, analyze_text_file, scan_directory, 
-             search_files, SearchCriteria, analyze_log_file, ConfigManager,
+use regex::Regex;
+use rtools::{ToolType, get_help_text, get_file_info, analyze_text_file,
+             scan_directory_with_options, ScanOptions, Snapshot,
+             find_duplicates, DuplicateOptions, CleanupOptions, parse_age,
+             search_files, SearchCriteria, SearchResult, ActionReport, NamePattern, PatternKind,
+             ContentQuery, ContentMode, EntryType, PermissionMatch, FilterExpr,
+             SortKey, PathDisplay, ListFormat, SearchAction, FileIndex, FuzzyQuery,
+             parse_time_spec, parse_size_range, lookup_user_id, lookup_group_id,
+             analyze_log_files, resolve_log_paths, Compression, AnalyzeOptions, JsonParser, ParserRegistry, AccessLogParser, RegexParser, ConfigManager,
+             LogReader, open_log_file, LogFilter, EntryFormat, LogEntry, LogLevel, LogAggregator,
+             EntryFollower, RollingStats, RtoolsResult, parse_level, parse_time_bound, write_entries, RtoolsError,
              HttpRequest, HttpMethod, send_request,
              test_tcp_connection, scan_ports, dns_lookup, ping_host};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// 跟踪日志文件时没有新内容后等待的时间
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -49,16 +63,11 @@ fn handle_tool(tool_type: ToolType, args: &[String]) {
             handle_textstats(&args[2]);
         }
         ToolType::DirScan => {
-            if args.len() < 3 || args.len() > 4 {
-                eprintln!("使用方法: {} dirscan <目录路径> [最大深度]", args[0]);
+            if args.len() < 3 {
+                eprintln!("使用方法: {} dirscan <目录路径> [最大深度] [选项]", args[0]);
                 process::exit(1);
             }
-            let max_depth = if args.len() == 4 {
-                args[3].parse::<usize>().ok()
-            } else {
-                None
-            };
-            handle_dirscan(&args[2], max_depth);
+            handle_dirscan(&args[2..]);
         }
         ToolType::FileSearch => {
             if args.len() < 3 {
@@ -67,12 +76,19 @@ fn handle_tool(tool_type: ToolType, args: &[String]) {
             }
             handle_filesearch(&args[2..]);
         }
+        ToolType::Index => {
+            if args.len() < 4 {
+                eprintln!("使用方法: {} index <build|update|info> <目录路径> [--output <索引文件>]", args[0]);
+                process::exit(1);
+            }
+            handle_index(&args[2..]);
+        }
         ToolType::LogAnalyzer => {
-            if args.len() != 3 {
-                eprintln!("使用方法: {} loganalyzer <日志文件>", args[0]);
+            if args.len() < 3 {
+                eprintln!("使用方法: {} loganalyzer <日志文件|目录|通配符>... [选项]", args[0]);
                 process::exit(1);
             }
-            handle_loganalyzer(&args[2]);
+            handle_loganalyzer(&args[2..]);
         }
         ToolType::Config => {
             if args.len() < 3 {
@@ -124,16 +140,231 @@ fn handle_textstats(file_path: &str) {
     }
 }
 
-fn handle_dirscan(dir_path: &str, max_depth: Option<usize>) {
-    match scan_directory(dir_path, max_depth) {
-        Ok(stats) => {
-            stats.print_stats();
+fn handle_dirscan(args: &[String]) {
+    if args.is_empty() {
+        eprintln!("错误: 需要指定扫描目录");
+        process::exit(1);
+    }
+    
+    let dir_path = &args[0];
+    let mut options = ScanOptions::new();
+    let mut snapshot_file: Option<String> = None;
+    let mut diff_file: Option<String> = None;
+    let mut hash_files = false;
+    let mut find_dups = false;
+    let mut hard_links = false;
+    let mut cleanup: Option<CleanupOptions> = None;
+    let mut delete = false;
+    let mut assume_yes = false;
+    
+    // 解析选项
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--depth" | "-d" => {
+                if i + 1 < args.len() {
+                    if let Ok(depth) = args[i + 1].parse::<usize>() {
+                        options = options.with_max_depth(depth);
+                        i += 2;
+                    } else {
+                        eprintln!("错误: --depth 需要指定数字");
+                        process::exit(1);
+                    }
+                } else {
+                    eprintln!("错误: --depth 需要指定深度");
+                    process::exit(1);
+                }
+            }
+            "--top" => {
+                if i + 1 < args.len() {
+                    if let Ok(top_n) = args[i + 1].parse::<usize>() {
+                        options = options.with_top_n(top_n);
+                        i += 2;
+                    } else {
+                        eprintln!("错误: --top 需要指定数字");
+                        process::exit(1);
+                    }
+                } else {
+                    eprintln!("错误: --top 需要指定条目数");
+                    process::exit(1);
+                }
+            }
+            "--snapshot" => {
+                if i + 1 < args.len() {
+                    snapshot_file = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("错误: --snapshot 需要指定快照文件");
+                    process::exit(1);
+                }
+            }
+            "--diff" => {
+                if i + 1 < args.len() {
+                    diff_file = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("错误: --diff 需要指定旧的快照文件");
+                    process::exit(1);
+                }
+            }
+            "--hash" => {
+                hash_files = true;
+                i += 1;
+            }
+            "--duplicates" => {
+                find_dups = true;
+                i += 1;
+            }
+            "--hard-links" => {
+                hard_links = true;
+                i += 1;
+            }
+            "--cleanup" => {
+                cleanup.get_or_insert_with(CleanupOptions::new);
+                i += 1;
+            }
+            "--stale" => {
+                if i + 1 < args.len() {
+                    match parse_age(&args[i + 1]) {
+                        Ok(age) => {
+                            cleanup = Some(cleanup.unwrap_or_default().with_stale_after(age));
+                            i += 2;
+                        }
+                        Err(e) => {
+                            eprintln!("错误: {}", e);
+                            process::exit(1);
+                        }
+                    }
+                } else {
+                    eprintln!("错误: --stale 需要指定时长 (如 180d)");
+                    process::exit(1);
+                }
+            }
+            "--delete" => {
+                cleanup.get_or_insert_with(CleanupOptions::new);
+                delete = true;
+                i += 1;
+            }
+            "--yes" | "-y" => {
+                assume_yes = true;
+                i += 1;
+            }
+            arg => {
+                // 兼容旧的位置参数形式: dirscan <目录路径> <最大深度>
+                if i == 1 && let Ok(depth) = arg.parse::<usize>() {
+                    options = options.with_max_depth(depth);
+                    i += 1;
+                } else {
+                    eprintln!("未知选项: {}", args[i]);
+                    process::exit(1);
+                }
+            }
         }
+    }
+    
+    if find_dups {
+        let mut dup_options = DuplicateOptions::new().with_hard_links(hard_links);
+        dup_options.max_depth = options.max_depth;
+        match find_duplicates(dir_path, &dup_options) {
+            Ok(report) => report.print_report(),
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    
+    if let Some(cleanup_options) = cleanup {
+        options = options.with_cleanup(cleanup_options);
+        let report = match scan_directory_with_options(dir_path, &options) {
+            Ok(stats) => stats.cleanup.expect("启用清理时总会生成报告"),
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                process::exit(1);
+            }
+        };
+        report.print_report();
+        
+        if delete && !report.items.is_empty() {
+            // 先预演，确认后才真正删除
+            println!("\n预演删除:");
+            report.delete(true).print_result();
+            
+            if !assume_yes && !confirm("\n确认删除以上内容? [y/N] ") {
+                println!("已取消删除");
+                return;
+            }
+            
+            println!();
+            let result = report.delete(false);
+            result.print_result();
+            if !result.failed.is_empty() {
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    
+    let old_snapshot = diff_file.map(|file| match Snapshot::load_from_file(&file) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(1);
+        }
+    });
+    
+    // 旧快照带有内容摘要时，新快照也需要计算摘要才能可靠比较
+    if let Some(old) = &old_snapshot {
+        hash_files |= old.has_hashes();
+    }
+    if snapshot_file.is_some() || old_snapshot.is_some() {
+        options = options.with_snapshot(hash_files);
+    }
+    
+    let stats = match scan_directory_with_options(dir_path, &options) {
+        Ok(stats) => stats,
         Err(e) => {
             eprintln!("错误: {}", e);
             process::exit(1);
         }
+    };
+    for warning in &stats.warnings {
+        eprintln!("警告: {}", warning);
+    }
+    
+    match &old_snapshot {
+        Some(old) => {
+            if let Some(new) = &stats.snapshot {
+                old.diff(new).print_diff();
+            }
+        }
+        None => stats.print_stats(),
+    }
+    
+    if let (Some(file), Some(snapshot)) = (snapshot_file, &stats.snapshot) {
+        match snapshot.save_to_file(&file) {
+            Ok(_) => println!("\n快照已保存到: {}", file),
+            Err(e) => {
+                eprintln!("保存错误: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// 在终端上询问用户确认，只有输入 y/yes 时返回真
+fn confirm(prompt: &str) -> bool {
+    print!("{}", prompt);
+    if io::stdout().flush().is_err() {
+        return false;
+    }
+    
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
     }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
 fn handle_filesearch(args: &[String]) {
@@ -144,6 +375,24 @@ fn handle_filesearch(args: &[String]) {
     
     let dir_path = &args[0];
     let mut criteria = SearchCriteria::new();
+    let mut case_sensitive = false;
+    // (是否为排除模式, 模式类型, 模式字符串)，在解析完所有选项后按大小写设置编译
+    let mut patterns: Vec<(bool, PatternKind, String)> = Vec::new();
+    // (是否为正则表达式, 搜索内容)
+    let mut content: Option<(bool, String)> = None;
+    let mut context_before = 0;
+    let mut context_after = 0;
+    let mut expression: Option<String> = None;
+    let mut sort_key: Option<SortKey> = None;
+    let mut descending = false;
+    let mut list_format = ListFormat::new();
+    let mut action: Option<SearchAction> = None;
+    let mut dry_run = false;
+    let mut assume_yes = false;
+    let mut use_index = false;
+    let mut fuzzy: Option<String> = None;
+    let mut index_file: Option<String> = None;
+    let mut content_mode = ContentMode::Lines;
     
     // 解析选项
     let mut i = 1;
@@ -169,14 +418,12 @@ fn handle_filesearch(args: &[String]) {
             }
             "--size" => {
                 if i + 1 < args.len() {
-                    let size_arg = &args[i + 1];
-                    if let Some(pos) = size_arg.find('-') {
-                        let min = size_arg[..pos].parse::<u64>().ok();
-                        let max = size_arg[pos+1..].parse::<u64>().ok();
-                        criteria = criteria.with_size_range(min, max);
-                    } else {
-                        let size = size_arg.parse::<u64>().ok();
-                        criteria = criteria.with_size_range(size, size);
+                    match parse_size_range(&args[i + 1]) {
+                        Ok((min, max)) => criteria = criteria.with_size_range(min, max),
+                        Err(e) => {
+                            eprintln!("错误: --size {}", e);
+                            process::exit(1);
+                        }
                     }
                     i += 2;
                 } else {
@@ -184,6 +431,279 @@ fn handle_filesearch(args: &[String]) {
                     process::exit(1);
                 }
             }
+            "--glob" | "-g" | "--regex" | "-r" | "--exclude" | "-x" | "--exclude-regex" => {
+                if i + 1 < args.len() {
+                    let exclude = args[i].starts_with("--exclude") || args[i] == "-x";
+                    let kind = if args[i].contains("regex") || args[i] == "-r" {
+                        PatternKind::Regex
+                    } else {
+                        PatternKind::Glob
+                    };
+                    patterns.push((exclude, kind, args[i + 1].clone()));
+                    i += 2;
+                } else {
+                    eprintln!("错误: {} 需要指定模式", args[i]);
+                    process::exit(1);
+                }
+            }
+            "--content" | "--content-regex" => {
+                if i + 1 < args.len() {
+                    content = Some((args[i] == "--content-regex", args[i + 1].clone()));
+                    i += 2;
+                } else {
+                    eprintln!("错误: {} 需要指定搜索内容", args[i]);
+                    process::exit(1);
+                }
+            }
+            "--context" | "-C" | "--after" | "-A" | "--before" | "-B" => {
+                if i + 1 < args.len() {
+                    if let Ok(lines) = args[i + 1].parse::<usize>() {
+                        match args[i].as_str() {
+                            "--after" | "-A" => context_after = lines,
+                            "--before" | "-B" => context_before = lines,
+                            _ => {
+                                context_before = lines;
+                                context_after = lines;
+                            }
+                        }
+                        i += 2;
+                    } else {
+                        eprintln!("错误: {} 需要指定行数", args[i]);
+                        process::exit(1);
+                    }
+                } else {
+                    eprintln!("错误: {} 需要指定行数", args[i]);
+                    process::exit(1);
+                }
+            }
+            "--count" => {
+                content_mode = ContentMode::CountOnly;
+                i += 1;
+            }
+            "--files-with-matches" | "-l" => {
+                content_mode = ContentMode::FilesWithMatches;
+                i += 1;
+            }
+            "--modified-after" | "--modified-before" | "--accessed-after" | "--accessed-before"
+            | "--created-after" | "--created-before" => {
+                if i + 1 < args.len() {
+                    let time = match parse_time_spec(&args[i + 1]) {
+                        Ok(time) => time,
+                        Err(e) => {
+                            eprintln!("错误: {}", e);
+                            process::exit(1);
+                        }
+                    };
+                    let range = match args[i].as_str() {
+                        "--modified-after" | "--modified-before" => &mut criteria.modified,
+                        "--accessed-after" | "--accessed-before" => &mut criteria.accessed,
+                        _ => &mut criteria.created,
+                    };
+                    if args[i].ends_with("after") {
+                        range.after = Some(time);
+                    } else {
+                        range.before = Some(time);
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("错误: {} 需要指定时间 (如 7d 或 2024-01-31)", args[i]);
+                    process::exit(1);
+                }
+            }
+            "--type" | "-t" => {
+                if i + 1 < args.len() {
+                    for name in args[i + 1].split(',') {
+                        match EntryType::parse(name) {
+                            Some(entry_type) => criteria = criteria.with_entry_type(entry_type),
+                            None => {
+                                eprintln!("错误: 无效的文件类型: {} (可用: f, d, l, s, p)", name);
+                                process::exit(1);
+                            }
+                        }
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("错误: --type 需要指定文件类型");
+                    process::exit(1);
+                }
+            }
+            "--user" | "--group" => {
+                if i + 1 < args.len() {
+                    let id = if args[i] == "--user" {
+                        lookup_user_id(&args[i + 1])
+                    } else {
+                        lookup_group_id(&args[i + 1])
+                    };
+                    match id {
+                        Ok(id) if args[i] == "--user" => criteria = criteria.with_owner(id),
+                        Ok(id) => criteria = criteria.with_group(id),
+                        Err(e) => {
+                            eprintln!("错误: {}", e);
+                            process::exit(1);
+                        }
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("错误: {} 需要指定名称或ID", args[i]);
+                    process::exit(1);
+                }
+            }
+            "--perm" => {
+                if i + 1 < args.len() {
+                    match PermissionMatch::parse(&args[i + 1]) {
+                        Ok(permission) => criteria = criteria.with_permission(permission),
+                        Err(e) => {
+                            eprintln!("错误: {}", e);
+                            process::exit(1);
+                        }
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("错误: --perm 需要指定权限模式");
+                    process::exit(1);
+                }
+            }
+            "--executable" => {
+                criteria = criteria.with_permission(PermissionMatch::EXECUTABLE);
+                i += 1;
+            }
+            "--world-writable" => {
+                criteria = criteria.with_permission(PermissionMatch::WORLD_WRITABLE);
+                i += 1;
+            }
+            "--empty" => {
+                criteria = criteria.with_empty(true);
+                i += 1;
+            }
+            "--case-sensitive" | "-s" => {
+                case_sensitive = true;
+                i += 1;
+            }
+            "--sort" => {
+                if i + 1 < args.len() {
+                    match SortKey::parse(&args[i + 1]) {
+                        Some(key) => sort_key = Some(key),
+                        None => {
+                            eprintln!("错误: 无效的排序键 '{}'，可选: path, name, size, mtime, ext, score", args[i + 1]);
+                            process::exit(1);
+                        }
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("错误: --sort 需要指定排序键");
+                    process::exit(1);
+                }
+            }
+            "--desc" => {
+                descending = true;
+                i += 1;
+            }
+            "--limit" => {
+                match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+                    Some(limit) => criteria = criteria.with_limit(limit),
+                    None => {
+                        eprintln!("错误: --limit 需要指定数字");
+                        process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--path-style" => {
+                let path_display = match args.get(i + 1).map(String::as_str) {
+                    Some("name") => PathDisplay::Name,
+                    Some("relative") => PathDisplay::Relative,
+                    Some("absolute") => PathDisplay::Absolute,
+                    _ => {
+                        eprintln!("错误: --path-style 需要指定 name、relative 或 absolute");
+                        process::exit(1);
+                    }
+                };
+                list_format = list_format.with_path_display(path_display);
+                i += 2;
+            }
+            "--long" | "-L" => {
+                list_format = list_format.with_size(true).with_modified(true);
+                i += 1;
+            }
+            "--exec" => {
+                // 命令参数一直读到单独的 `;`（执行多次）或 `+`（批量执行）
+                let end = args[i + 1..].iter().position(|arg| arg == ";" || arg == "+");
+                let Some(end) = end.map(|offset| i + 1 + offset) else {
+                    eprintln!("错误: --exec 需要以 ';' 或 '+' 结束");
+                    process::exit(1);
+                };
+                let command = args[i + 1..end].to_vec();
+                match SearchAction::exec(command, args[end] == "+") {
+                    Ok(exec) => set_search_action(&mut action, exec),
+                    Err(e) => {
+                        eprintln!("错误: {}", e);
+                        process::exit(1);
+                    }
+                }
+                i = end + 1;
+            }
+            "--delete" => {
+                set_search_action(&mut action, SearchAction::Delete);
+                i += 1;
+            }
+            "--copy-to" | "--move-to" => {
+                let Some(target) = args.get(i + 1) else {
+                    eprintln!("错误: {} 需要指定目标目录", args[i]);
+                    process::exit(1);
+                };
+                let target = target.into();
+                let next = if args[i] == "--copy-to" {
+                    SearchAction::Copy { target }
+                } else {
+                    SearchAction::Move { target }
+                };
+                set_search_action(&mut action, next);
+                i += 2;
+            }
+            "--print0" | "-print0" => {
+                set_search_action(&mut action, SearchAction::Print0);
+                i += 1;
+            }
+            "--dry-run" => {
+                dry_run = true;
+                i += 1;
+            }
+            "--fuzzy" | "-f" => {
+                if i + 1 < args.len() {
+                    fuzzy = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("错误: --fuzzy 需要指定查询");
+                    process::exit(1);
+                }
+            }
+            "--index" => {
+                use_index = true;
+                i += 1;
+            }
+            "--index-file" => {
+                if i + 1 < args.len() {
+                    use_index = true;
+                    index_file = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("错误: --index-file 需要指定索引文件");
+                    process::exit(1);
+                }
+            }
+            "--yes" | "-y" => {
+                assume_yes = true;
+                i += 1;
+            }
+            "--expr" => {
+                if i + 1 < args.len() {
+                    expression = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("错误: --expr 需要指定表达式");
+                    process::exit(1);
+                }
+            }
             "--depth" | "-d" => {
                 if i + 1 < args.len() {
                     if let Ok(depth) = args[i + 1].parse::<usize>() {
@@ -205,19 +725,480 @@ fn handle_filesearch(args: &[String]) {
         }
     }
     
-    match search_files(dir_path, criteria) {
-        Ok(result) => {
-            result.print_results();
+    criteria = criteria.with_case_sensitive(case_sensitive);
+    for (exclude, kind, source) in patterns {
+        let pattern = match kind {
+            PatternKind::Regex => NamePattern::regex(&source, case_sensitive),
+            _ => NamePattern::glob(&source, case_sensitive),
+        };
+        match pattern {
+            Ok(pattern) if exclude => criteria = criteria.with_exclude(pattern),
+            Ok(pattern) => criteria = criteria.with_include(pattern),
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+    
+    if let Some((is_regex, text)) = content {
+        let query = if is_regex {
+            ContentQuery::regex(&text, case_sensitive)
+        } else {
+            ContentQuery::literal(&text, case_sensitive)
+        };
+        match query {
+            Ok(query) => {
+                criteria = criteria.with_content(
+                    query.with_context(context_before, context_after).with_mode(content_mode));
+            }
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+    
+    if let Some(expression) = expression {
+        match FilterExpr::parse(&expression, case_sensitive) {
+            Ok(expr) => criteria = criteria.with_expression(expr),
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                process::exit(1);
+            }
         }
+    }
+    
+    if let Some(query) = fuzzy {
+        criteria = criteria.with_fuzzy(FuzzyQuery::new(&query, case_sensitive));
+    }
+    
+    if let Some(key) = sort_key {
+        criteria = criteria.with_sort(key, descending);
+    } else if descending {
+        criteria = criteria.with_sort(SortKey::Path, true);
+    }
+    
+    let result = if use_index {
+        let index_path = index_file.map_or_else(|| FileIndex::default_path(dir_path), Into::into);
+        FileIndex::load_from_file(&index_path).and_then(|index| index.search(dir_path, &criteria))
+    } else {
+        search_files(dir_path, criteria)
+    };
+    let result = match result {
+        Ok(result) => result,
         Err(e) => {
             eprintln!("错误: {}", e);
             process::exit(1);
         }
+    };
+    for warning in &result.warnings {
+        eprintln!("警告: {}", warning);
+    }
+    
+    let Some(action) = action else {
+        result.print_results_with(&list_format);
+        return;
+    };
+    
+    // 删除前先预演，确认后才真正删除
+    if action == SearchAction::Delete && !dry_run && !result.files.is_empty() {
+        println!("预演删除:");
+        run_search_action(&action, &result, true);
+        if !assume_yes && !confirm("\n确认删除以上内容? [y/N] ") {
+            println!("已取消删除");
+            return;
+        }
+        println!();
     }
+    
+    let report = run_search_action(&action, &result, dry_run);
+    if !report.is_success() {
+        process::exit(1);
+    }
+}
+
+fn set_search_action(action: &mut Option<SearchAction>, next: SearchAction) {
+    if let Some(existing) = action {
+        eprintln!("错误: 不能同时指定{}和{}动作", existing.description(), next.description());
+        process::exit(1);
+    }
+    *action = Some(next);
 }
 
-fn handle_loganalyzer(log_file: &str) {
-    match analyze_log_file(log_file) {
+fn run_search_action(action: &SearchAction, result: &SearchResult, dry_run: bool) -> ActionReport {
+    match action.run(result, dry_run) {
+        Ok(report) => {
+            // 执行命令和输出路径时只保留命令本身的输出，失败时才报告；预演执行命令时列出命令行
+            let quiet = match action {
+                SearchAction::Exec { .. } => !dry_run,
+                SearchAction::Print0 => true,
+                _ => false,
+            };
+            if !quiet {
+                report.print_report();
+            } else {
+                for (path, error) in &report.failed {
+                    eprintln!("错误: {}: {}", path.display(), error);
+                }
+            }
+            report
+        }
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_index(args: &[String]) {
+    let action = args[0].as_str();
+    let dir_path = &args[1];
+    let mut output: Option<String> = None;
+    
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--output" | "-o" => {
+                if i + 1 < args.len() {
+                    output = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("错误: --output 需要指定索引文件");
+                    process::exit(1);
+                }
+            }
+            _ => {
+                eprintln!("未知选项: {}", args[i]);
+                process::exit(1);
+            }
+        }
+    }
+    
+    let index_path = output.map_or_else(|| FileIndex::default_path(dir_path), Into::into);
+    let index = match action {
+        "build" => FileIndex::build(dir_path),
+        "update" => FileIndex::load_from_file(&index_path).and_then(|mut index| {
+            index.update()?.print_update();
+            Ok(index)
+        }),
+        "info" => FileIndex::load_from_file(&index_path),
+        _ => {
+            eprintln!("错误: 未知的索引操作 '{}'，可选: build, update, info", action);
+            process::exit(1);
+        }
+    };
+    
+    let index = match index {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(1);
+        }
+    };
+    
+    index.print_summary();
+    if action != "info" {
+        match index.save_to_file(&index_path) {
+            Ok(_) => println!("\n索引已保存到: {}", index_path.display()),
+            Err(e) => {
+                eprintln!("保存错误: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn handle_loganalyzer(args: &[String]) {
+    let mut registry = ParserRegistry::default();
+    
+    if args[0] == "--list-formats" {
+        println!("支持的日志格式:");
+        for parser in registry.parsers() {
+            println!("  {:<12} {}", parser.name(), parser.description());
+        }
+        return;
+    }
+    
+    let mut inputs = vec![&args[0]];
+    let mut options = AnalyzeOptions::new();
+    let mut json_parser: Option<JsonParser> = None;
+    let mut access_format: Option<&String> = None;
+    let mut custom_pattern: Option<&String> = None;
+    let mut time_format: Option<&String> = None;
+    let mut filter = LogFilter::new();
+    let mut output: Option<EntryFormat> = None;
+    let mut summary = false;
+    let mut follow = false;
+    let mut window = Duration::from_secs(5 * 60);
+    let mut refresh = Duration::from_secs(10);
+    
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--format" => {
+                if i + 1 < args.len() {
+                    options = options.with_format(&args[i + 1]);
+                    i += 2;
+                } else {
+                    eprintln!("错误: --format 需要指定格式名称");
+                    process::exit(1);
+                }
+            }
+            "--sample" => {
+                match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+                    Some(lines) => options = options.with_sample_lines(lines),
+                    None => {
+                        eprintln!("错误: --sample 需要指定行数");
+                        process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--top-k" => {
+                match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+                    Some(top_k) if top_k > 0 => options = options.with_top_k(top_k),
+                    _ => {
+                        eprintln!("错误: --top-k 需要指定个数");
+                        process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--jobs" | "-j" => {
+                match args.get(i + 1).and_then(|n| n.parse::<usize>().ok()) {
+                    Some(jobs) if jobs > 0 => options = options.with_jobs(jobs),
+                    _ => {
+                        eprintln!("错误: --jobs 需要指定线程数");
+                        process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--group-by" => {
+                if i + 1 < args.len() {
+                    options = options.with_group_by(&args[i + 1]);
+                    i += 2;
+                } else {
+                    eprintln!("错误: --group-by 需要指定字段名");
+                    process::exit(1);
+                }
+            }
+            "--log-format" => {
+                if i + 1 < args.len() {
+                    access_format = Some(&args[i + 1]);
+                    i += 2;
+                } else {
+                    eprintln!("错误: --log-format 需要指定 nginx log_format 格式");
+                    process::exit(1);
+                }
+            }
+            "--regex" | "--time-format" | "--formats-file" => {
+                let Some(value) = args.get(i + 1) else {
+                    eprintln!("错误: {} 需要指定参数", args[i]);
+                    process::exit(1);
+                };
+                match args[i].as_str() {
+                    "--regex" => custom_pattern = Some(value),
+                    "--time-format" => time_format = Some(value),
+                    _ => match RegexParser::load_from_file(value) {
+                        Ok(parsers) => {
+                            for parser in parsers {
+                                registry.register(Box::new(parser));
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("错误: {}", e);
+                            process::exit(1);
+                        }
+                    },
+                }
+                i += 2;
+            }
+            "--level" | "--since" | "--until" | "--grep" | "--source" | "--field" | "--output" => {
+                let Some(value) = args.get(i + 1) else {
+                    eprintln!("错误: {} 需要指定参数", args[i]);
+                    process::exit(1);
+                };
+                let current = std::mem::take(&mut filter);
+                let result = match args[i].as_str() {
+                    "--level" => parse_level(value).map(|level| current.with_min_level(level)),
+                    "--since" => parse_time_bound(value).map(|time| current.with_since(time)),
+                    "--until" => parse_time_bound(value).map(|time| current.with_until(time)),
+                    "--grep" => Regex::new(value)
+                        .map(|regex| current.with_pattern(regex))
+                        .map_err(|e| RtoolsError::InvalidArgument(format!("无效的正则表达式: {}", e))),
+                    "--source" => Ok(current.with_source(value)),
+                    "--field" => match value.split_once('=') {
+                        Some((name, field_value)) => Ok(current.with_field(name, field_value)),
+                        None => Err(RtoolsError::InvalidArgument(format!("--field 需要 <字段>=<值> 形式: {}", value))),
+                    },
+                    _ => match EntryFormat::parse(value) {
+                        Some(format) => {
+                            output = Some(format);
+                            Ok(current)
+                        }
+                        None => Err(RtoolsError::InvalidArgument(format!("无效的输出格式 '{}'，可选: text, json", value))),
+                    },
+                };
+                match result {
+                    Ok(updated) => filter = updated,
+                    Err(e) => {
+                        eprintln!("错误: {}", e);
+                        process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--summary" => {
+                summary = true;
+                i += 1;
+            }
+            "--follow" | "-F" => {
+                follow = true;
+                i += 1;
+            }
+            "--window" => {
+                match args.get(i + 1).map(|s| parse_age(s)) {
+                    Some(Ok(duration)) if !duration.is_zero() => window = duration,
+                    _ => {
+                        eprintln!("错误: --window 需要指定时长，如 5m、1h");
+                        process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--refresh" => {
+                match args.get(i + 1).and_then(|n| n.parse::<u64>().ok()) {
+                    Some(seconds) if seconds > 0 => refresh = Duration::from_secs(seconds),
+                    _ => {
+                        eprintln!("错误: --refresh 需要指定秒数");
+                        process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--no-multiline" => {
+                options = options.with_multiline(false);
+                i += 1;
+            }
+            "--start-pattern" => {
+                let Some(pattern) = args.get(i + 1) else {
+                    eprintln!("错误: --start-pattern 需要指定正则表达式");
+                    process::exit(1);
+                };
+                match Regex::new(pattern) {
+                    Ok(regex) => options = options.with_start_pattern(regex),
+                    Err(e) => {
+                        eprintln!("错误: 无效的起始行模式: {}", e);
+                        process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--ts-field" | "--level-field" | "--msg-field" => {
+                let Some(field) = args.get(i + 1) else {
+                    eprintln!("错误: {} 需要指定字段名", args[i]);
+                    process::exit(1);
+                };
+                let parser = json_parser.take().unwrap_or_default();
+                json_parser = Some(match args[i].as_str() {
+                    "--ts-field" => parser.with_timestamp_field(field),
+                    "--level-field" => parser.with_level_field(field),
+                    _ => parser.with_message_field(field),
+                });
+                i += 2;
+            }
+            arg if !arg.starts_with('-') => {
+                inputs.push(&args[i]);
+                i += 1;
+            }
+            _ => {
+                eprintln!("未知选项: {}", args[i]);
+                process::exit(1);
+            }
+        }
+    }
+    
+    // 指定了JSON字段名时替换内置的json解析器
+    if let Some(parser) = json_parser {
+        registry.register(Box::new(parser));
+    }
+    
+    // 自定义的访问日志格式注册为 nginx，没有用 --format 指定其他格式时直接使用
+    if let Some(format) = access_format {
+        match AccessLogParser::new("nginx", format) {
+            Ok(parser) => registry.register(Box::new(parser)),
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                process::exit(1);
+            }
+        }
+        if options.format.is_none() {
+            options = options.with_format("nginx");
+        }
+    }
+    
+    // 命令行指定的正则表达式注册为 custom
+    if let Some(pattern) = custom_pattern {
+        match RegexParser::new("custom", pattern) {
+            Ok(parser) => {
+                let parser = match time_format {
+                    Some(format) => parser.with_timestamp_format(format),
+                    None => parser,
+                };
+                registry.register(Box::new(parser));
+            }
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                process::exit(1);
+            }
+        }
+        if options.format.is_none() {
+            options = options.with_format("custom");
+        }
+    } else if time_format.is_some() {
+        eprintln!("错误: --time-format 需要与 --regex 一起使用");
+        process::exit(1);
+    }
+    
+    // 指定了过滤条件或输出格式时输出匹配的条目，--summary 时分析匹配的条目
+    let query_mode = !summary && (!filter.is_empty() || output.is_some());
+    options = options.with_filter(filter);
+    if follow {
+        // 跟踪的文件可以还不存在，不展开通配符
+        if inputs.len() > 1 {
+            eprintln!("错误: --follow 只能跟踪一个文件");
+            process::exit(1);
+        }
+        if Compression::of_file(inputs[0]).is_ok_and(|c| c != Compression::None) {
+            eprintln!("错误: 不能跟踪压缩的文件: {}", inputs[0]);
+            process::exit(1);
+        }
+        if let Err(e) = follow_log(inputs[0], &registry, &options, output.unwrap_or_default(), window, refresh) {
+            eprintln!("错误: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    // 展开目录和通配符，轮转的文件按从旧到新的顺序处理
+    let paths = match resolve_log_paths(&inputs) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(1);
+        }
+    };
+    if query_mode {
+        if let Err(e) = print_matching_entries(&paths, &registry, &options, output.unwrap_or_default()) {
+            eprintln!("错误: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    match analyze_log_files(&paths, &registry, &options) {
         Ok(analysis) => {
             analysis.print_analysis();
         }
@@ -228,6 +1209,95 @@ fn handle_loganalyzer(log_file: &str) {
     }
 }
 
+/// 按顺序逐条输出各文件中通过过滤条件的条目，不把整个文件读入内存
+fn print_matching_entries(
+    paths: &[PathBuf],
+    registry: &ParserRegistry,
+    options: &AnalyzeOptions,
+    output: EntryFormat,
+) -> RtoolsResult<()> {
+    let mut stdout = io::BufWriter::new(io::stdout().lock());
+    
+    for path in paths {
+        for entry in LogReader::new(open_log_file(path)?, registry, options)? {
+            let entry = entry?;
+            if !options.filter.matches(&entry) {
+                continue;
+            }
+            match write_entries(&mut stdout, [&entry], output) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                result => result?,
+            }
+        }
+    }
+    
+    match stdout.flush() {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// 跟踪日志文件: 输出新增的匹配条目，并定期在标准错误输出最近一段时间的统计
+fn follow_log(
+    log_file: &str,
+    registry: &ParserRegistry,
+    options: &AnalyzeOptions,
+    output: EntryFormat,
+    window: Duration,
+    refresh: Duration,
+) -> RtoolsResult<()> {
+    let mut follower = EntryFollower::new(log_file, registry, options)?;
+    let mut rolling = RollingStats::new(window);
+    let mut aggregator: Option<LogAggregator> = None;
+    let mut last_refresh = Instant::now();
+    let mut stdout = io::stdout();
+    
+    eprintln!("正在跟踪 {} (格式: {})，按 Ctrl+C 退出", log_file, follower.parser().map_or("未识别", |p| p.name()));
+    loop {
+        let resolved = follower.is_resolved();
+        let entries = follower.poll()?;
+        // 文件为空或不存在时用最先读到的一批行检测格式，在此之前不统计
+        if !follower.is_resolved() {
+            std::thread::sleep(FOLLOW_POLL_INTERVAL);
+            continue;
+        }
+        if !resolved {
+            eprintln!("检测到格式: {}", follower.parser().map_or("未识别", |p| p.name()));
+        }
+        
+        let aggregator = aggregator.get_or_insert_with(|| LogAggregator::new(follower.parser().map(|p| p.name()), options));
+        
+        let matching: Vec<&LogEntry> = entries
+            .iter()
+            .filter(|entry| aggregator.add_matching(entry, &options.filter))
+            .collect();
+        for entry in &matching {
+            rolling.add(entry.level.clone());
+        }
+        match write_entries(&mut stdout, matching, output).and_then(|_| stdout.flush()) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        }
+        
+        if last_refresh.elapsed() >= refresh {
+            rolling.prune(chrono::Utc::now());
+            let errors: usize = aggregator
+                .level_distribution()
+                .iter()
+                .filter(|(level, _)| level.severity() >= LogLevel::Error.severity())
+                .map(|(_, count)| count)
+                .sum();
+            eprintln!("[{}] {}; 累计 {} 条, 错误 {}",
+                      chrono::Local::now().format("%H:%M:%S"), rolling.summary(), aggregator.total_entries(), errors);
+            last_refresh = Instant::now();
+        }
+        
+        if follower.is_idle() {
+            std::thread::sleep(FOLLOW_POLL_INTERVAL);
+        }
+    }
+}
+
 fn handle_config(args: &[String]) {
     if args.is_empty() {
         eprintln!("错误: 需要指定配置文件");
@@ -182,12 +182,8 @@ pub async fn scan_ports(host: &str, start_port: u16, end_port: u16, timeout: Dur
     // 等待所有任务完成
     for (port, task) in tasks {
         match task.await {
-            Ok(Ok(conn_result)) => {
-                if conn_result.is_reachable {
-                    result.open_ports.push(port);
-                } else {
-                    result.closed_ports.push(port);
-                }
+            Ok(Ok(conn_result)) if conn_result.is_reachable => {
+                result.open_ports.push(port);
             }
             _ => {
                 result.closed_ports.push(port);
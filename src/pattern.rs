@@ -0,0 +1,140 @@
+use regex::{Regex, RegexBuilder};
+use crate::{RtoolsResult, RtoolsError};
+
+/// 名称模式的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternKind {
+    /// 文件名包含指定子串
+    Substring,
+    /// 通配符模式，如 `*.rs`、`test_?.log`、`**/src/*.toml`
+    Glob,
+    /// 正则表达式，在相对路径中查找匹配
+    Regex,
+}
+
+/// 编译后的文件名/路径匹配模式
+///
+/// 不包含 `/` 的通配符只匹配文件名，包含 `/` 的通配符匹配相对于搜索根目录的完整路径；
+/// 正则表达式始终在相对路径上查找。
+#[derive(Debug, Clone)]
+pub struct NamePattern {
+    pub kind: PatternKind,
+    pub source: String,
+    pub case_sensitive: bool,
+    regex: Option<Regex>,
+    match_full_path: bool,
+}
+
+impl NamePattern {
+    pub fn substring(pattern: &str, case_sensitive: bool) -> Self {
+        Self {
+            kind: PatternKind::Substring,
+            source: pattern.to_string(),
+            case_sensitive,
+            regex: None,
+            match_full_path: false,
+        }
+    }
+
+    pub fn glob(pattern: &str, case_sensitive: bool) -> RtoolsResult<Self> {
+        let regex = build_regex(&glob_to_regex(pattern)?, case_sensitive)?;
+        Ok(Self {
+            kind: PatternKind::Glob,
+            source: pattern.to_string(),
+            case_sensitive,
+            regex: Some(regex),
+            match_full_path: pattern.contains('/'),
+        })
+    }
+
+    pub fn regex(pattern: &str, case_sensitive: bool) -> RtoolsResult<Self> {
+        let regex = build_regex(pattern, case_sensitive)?;
+        Ok(Self {
+            kind: PatternKind::Regex,
+            source: pattern.to_string(),
+            case_sensitive,
+            regex: Some(regex),
+            match_full_path: true,
+        })
+    }
+
+    /// 判断文件是否匹配，`relative_path` 使用 `/` 作为分隔符
+    pub fn matches(&self, file_name: &str, relative_path: &str) -> bool {
+        let target = if self.match_full_path { relative_path } else { file_name };
+
+        match &self.regex {
+            Some(regex) => regex.is_match(target),
+            None if self.case_sensitive => target.contains(&self.source),
+            None => target.to_lowercase().contains(&self.source.to_lowercase()),
+        }
+    }
+}
+
+fn build_regex(pattern: &str, case_sensitive: bool) -> RtoolsResult<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| RtoolsError::ParseError(format!("无效的模式 '{}': {}", pattern, e)))
+}
+
+/// 将通配符模式转换为锚定的正则表达式
+///
+/// 支持 `*`（不跨目录）、`**`（跨任意层目录）、`?`、`[abc]`/`[!abc]` 和 `{a,b}`。
+fn glob_to_regex(glob: &str) -> RtoolsResult<String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::from("^");
+    let mut brace_depth = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    // `**/` 匹配零层或多层目录
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == ']')
+                    .map(|pos| i + 1 + pos)
+                    .ok_or_else(|| RtoolsError::ParseError(format!("通配符中的 '[' 没有闭合: {}", glob)))?;
+                regex.push('[');
+                let mut class: String = chars[i + 1..end].iter().collect();
+                if let Some(rest) = class.strip_prefix('!') {
+                    class = format!("^{}", rest);
+                }
+                regex.push_str(&class.replace('\\', "\\\\"));
+                regex.push(']');
+                i = end + 1;
+                continue;
+            }
+            '{' => {
+                brace_depth += 1;
+                regex.push_str("(?:");
+            }
+            '}' if brace_depth > 0 => {
+                brace_depth -= 1;
+                regex.push(')');
+            }
+            ',' if brace_depth > 0 => regex.push('|'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+
+    if brace_depth > 0 {
+        return Err(RtoolsError::ParseError(format!("通配符中的 '{{' 没有闭合: {}", glob)));
+    }
+
+    regex.push('$');
+    Ok(regex)
+}
@@ -0,0 +1,250 @@
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// 默认保留的高频键个数
+pub const DEFAULT_TOP_K: usize = 1000;
+
+/// 用 Space-Saving 算法统计出现次数最多的键，最多保存 `capacity` 个计数器
+///
+/// 不同的键不超过容量时计数是精确的；超过后新键替换计数最小的键并继承其计数，
+/// 所以计数可能偏大（偏差不超过 `error`），但出现次数超过总数 1/`capacity` 的键一定会被保留。
+#[derive(Debug, Clone)]
+pub struct TopK {
+    capacity: usize,
+    index: HashMap<String, usize>,
+    counters: Vec<Counter>,
+    /// 按 (计数, 位置) 排序，用于找到计数最小的计数器
+    order: BTreeSet<(u64, usize)>,
+    evicted: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Counter {
+    key: String,
+    count: u64,
+    error: u64,
+}
+
+impl TopK {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            index: HashMap::new(),
+            counters: Vec::new(),
+            order: BTreeSet::new(),
+            evicted: false,
+        }
+    }
+
+    pub fn add(&mut self, key: &str) {
+        self.add_count(key, 1);
+    }
+
+    pub fn add_count(&mut self, key: &str, count: u64) {
+        if let Some(&i) = self.index.get(key) {
+            self.increase(i, count);
+            return;
+        }
+
+        if self.counters.len() < self.capacity {
+            let i = self.counters.len();
+            self.counters.push(Counter { key: key.to_string(), count, error: 0 });
+            self.index.insert(key.to_string(), i);
+            self.order.insert((count, i));
+            return;
+        }
+
+        // 替换计数最小的键
+        let (min_count, i) = self.order.pop_first().expect("容量至少为1");
+        let counter = &mut self.counters[i];
+        self.index.remove(&counter.key);
+        counter.key = key.to_string();
+        counter.count = min_count + count;
+        counter.error = min_count;
+        self.index.insert(key.to_string(), i);
+        self.order.insert((counter.count, i));
+        self.evicted = true;
+    }
+
+    fn increase(&mut self, i: usize, count: u64) {
+        let counter = &mut self.counters[i];
+        self.order.remove(&(counter.count, i));
+        counter.count += count;
+        self.order.insert((counter.count, i));
+    }
+
+    /// 键的估计次数，不在计数器中的键返回 `None`
+    pub fn estimate(&self, key: &str) -> Option<u64> {
+        self.index.get(key).map(|&i| self.counters[i].count)
+    }
+
+    /// 键的估计次数可能偏大的最大值
+    pub fn error(&self, key: &str) -> Option<u64> {
+        self.index.get(key).map(|&i| self.counters[i].error)
+    }
+
+    /// 计数是否精确（从未替换过计数器）
+    pub fn is_exact(&self) -> bool {
+        !self.evicted
+    }
+
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+
+    /// 按次数从多到少排列的键，次数相同时按键排序
+    pub fn top(&self, n: usize) -> Vec<(String, u64)> {
+        let mut items: Vec<(String, u64)> = self
+            .counters
+            .iter()
+            .map(|counter| (counter.key.clone(), counter.count))
+            .collect();
+        items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        items.truncate(n);
+        items
+    }
+
+    /// 合并另一个统计，用于并行处理后汇总
+    pub fn merge(&mut self, other: &TopK) {
+        for counter in &other.counters {
+            self.add_count(&counter.key, counter.count);
+        }
+        self.evicted |= other.evicted;
+    }
+}
+
+/// HyperLogLog 精度，寄存器数为 2^14，标准误差约 0.8%
+const HLL_PRECISION: u32 = 14;
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// 用 HyperLogLog 估计不同值的个数，占用固定的 16 KiB 内存
+#[derive(Debug, Clone)]
+pub struct DistinctCounter {
+    registers: Vec<u8>,
+}
+
+impl Default for DistinctCounter {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS],
+        }
+    }
+}
+
+impl DistinctCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add<T: Hash + ?Sized>(&mut self, value: &T) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// 估计的不同值个数，基数较小时使用线性计数
+    pub fn estimate(&self) -> usize {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            estimate.round() as usize
+        }
+    }
+
+    pub fn merge(&mut self, other: &DistinctCounter) {
+        for (register, &other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(other);
+        }
+    }
+}
+
+/// 蓄水池抽样: 保留最多 `capacity` 个均匀抽取的样本
+#[derive(Debug, Clone)]
+pub struct Reservoir {
+    capacity: usize,
+    seen: u64,
+    samples: Vec<f64>,
+    state: u64,
+}
+
+impl Reservoir {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            seen: 0,
+            samples: Vec::new(),
+            state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        self.seen += 1;
+        if self.samples.len() < self.capacity {
+            self.samples.push(value);
+        } else {
+            let j = self.next_random() % self.seen;
+            if (j as usize) < self.capacity {
+                self.samples[j as usize] = value;
+            }
+        }
+    }
+
+    /// 已经加入的值的个数
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    pub fn samples(&self) -> &[f64] {
+        &self.samples
+    }
+
+    /// 合并另一个蓄水池，按两边的总数比例抽取样本
+    pub fn merge(&mut self, other: &Reservoir) {
+        let total = self.seen + other.seen;
+        if total as usize <= self.capacity {
+            self.samples.extend_from_slice(&other.samples);
+            self.seen = total;
+            return;
+        }
+
+        let from_self = ((self.capacity as f64) * (self.seen as f64 / total as f64)).round() as usize;
+        let mut merged = self.take_random(from_self.min(self.samples.len()));
+        let mut other = other.clone();
+        merged.extend(other.take_random((self.capacity - merged.len()).min(other.samples.len())));
+        self.samples = merged;
+        self.seen = total;
+    }
+
+    /// 随机取出 `n` 个样本
+    fn take_random(&mut self, n: usize) -> Vec<f64> {
+        for i in 0..n {
+            let j = i + (self.next_random() as usize) % (self.samples.len() - i);
+            self.samples.swap(i, j);
+        }
+        self.samples[..n].to_vec()
+    }
+
+    /// xorshift64* 伪随机数，固定的种子使结果可以重现
+    fn next_random(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
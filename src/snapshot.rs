@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::dirscan::{FileInfo, format_size, hash_file};
+use crate::{RtoolsResult, RtoolsError};
+
+/// 快照中的单个文件记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// 相对于扫描根目录的路径
+    pub path: String,
+    pub size: u64,
+    /// 修改时间（Unix时间戳，秒）
+    pub modified: u64,
+    /// 文件内容的SHA-256摘要，只在启用哈希时记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl SnapshotEntry {
+    /// 判断两条记录是否描述了相同的文件内容
+    fn same_content(&self, other: &SnapshotEntry) -> bool {
+        if self.size != other.size {
+            return false;
+        }
+
+        match (&self.hash, &other.hash) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.modified == other.modified,
+        }
+    }
+
+    /// 用于识别移动/重命名的匹配键；`by_hash` 为真时按内容摘要匹配，否则按修改时间匹配
+    fn move_key(&self, by_hash: bool) -> MoveKey {
+        match &self.hash {
+            Some(hash) if by_hash => MoveKey::Hash(self.size, hash.clone()),
+            _ => MoveKey::Modified(self.size, self.modified),
+        }
+    }
+}
+
+/// 识别移动/重命名的匹配键: 大小加上内容摘要或修改时间
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum MoveKey {
+    Hash(u64, String),
+    Modified(u64, u64),
+}
+
+/// 一次目录扫描的快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub root: PathBuf,
+    pub created_at: DateTime<Utc>,
+    pub entries: Vec<SnapshotEntry>,
+}
+
+impl Snapshot {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            created_at: Utc::now(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// 记录一个扫描到的文件，`with_hash` 为真时计算内容摘要
+    ///
+    /// 无法读取文件内容时仍然记录该文件（不含摘要），并返回读取的错误。
+    pub fn record(&mut self, file_info: &FileInfo, with_hash: bool) -> RtoolsResult<()> {
+        let relative = file_info.path
+            .strip_prefix(&self.root)
+            .unwrap_or(&file_info.path);
+
+        let modified = file_info.modified
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let hash = if with_hash {
+            Some(hash_file(&file_info.path, None))
+        } else {
+            None
+        };
+
+        self.entries.push(SnapshotEntry {
+            path: relative.to_string_lossy().into_owned(),
+            size: file_info.size,
+            modified,
+            hash: hash.as_ref().and_then(|hash| hash.as_ref().ok().cloned()),
+        });
+
+        match hash {
+            Some(Err(e)) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+
+    /// 快照中是否记录了内容摘要
+    pub fn has_hashes(&self) -> bool {
+        self.entries.iter().any(|e| e.hash.is_some())
+    }
+
+    pub fn save_to_file(&self, file_path: &str) -> RtoolsResult<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| RtoolsError::ParseError(format!("快照序列化错误: {}", e)))?;
+        fs::write(file_path, content)?;
+        Ok(())
+    }
+
+    pub fn load_from_file(file_path: &str) -> RtoolsResult<Self> {
+        let path = Path::new(file_path);
+
+        if !path.exists() {
+            return Err(RtoolsError::FileNotFound(file_path.to_string()));
+        }
+
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| RtoolsError::ParseError(format!("快照解析错误: {}", e)))
+    }
+
+    /// 比较旧快照（self）和新快照，找出两次扫描之间的变化
+    pub fn diff(&self, newer: &Snapshot) -> SnapshotDiff {
+        let old_by_path: HashMap<&str, &SnapshotEntry> =
+            self.entries.iter().map(|e| (e.path.as_str(), e)).collect();
+        let new_by_path: HashMap<&str, &SnapshotEntry> =
+            newer.entries.iter().map(|e| (e.path.as_str(), e)).collect();
+
+        let mut diff = SnapshotDiff {
+            size_change: newer.total_size() as i64 - self.total_size() as i64,
+            ..SnapshotDiff::default()
+        };
+
+        let mut removed = Vec::new();
+        for entry in &self.entries {
+            match new_by_path.get(entry.path.as_str()) {
+                Some(new_entry) => {
+                    if !entry.same_content(new_entry) {
+                        diff.modified.push((entry.clone(), (*new_entry).clone()));
+                    }
+                }
+                None => removed.push(entry),
+            }
+        }
+
+        // 在删除的文件中按内容查找新增文件的来源，匹配上的视为移动/重命名；
+        // 只有两个快照都记录了摘要时才按摘要匹配，否则按大小和修改时间匹配
+        let by_hash = self.has_hashes() && newer.has_hashes();
+        let mut removed_by_key: HashMap<MoveKey, Vec<&SnapshotEntry>> = HashMap::new();
+        for entry in removed.iter().rev() {
+            removed_by_key.entry(entry.move_key(by_hash)).or_default().push(entry);
+        }
+
+        for entry in &newer.entries {
+            if old_by_path.contains_key(entry.path.as_str()) {
+                continue;
+            }
+
+            let source = removed_by_key
+                .get_mut(&entry.move_key(by_hash))
+                .and_then(|candidates| candidates.pop());
+
+            match source {
+                Some(old_entry) => diff.moved.push((old_entry.clone(), entry.clone())),
+                None => diff.added.push(entry.clone()),
+            }
+        }
+
+        let moved_from: HashSet<&str> = diff.moved.iter().map(|(old, _)| old.path.as_str()).collect();
+        diff.removed = removed
+            .into_iter()
+            .filter(|e| !moved_from.contains(&e.path.as_str()))
+            .cloned()
+            .collect();
+
+        diff
+    }
+}
+
+/// 两次快照之间的差异
+#[derive(Debug, Default)]
+pub struct SnapshotDiff {
+    pub added: Vec<SnapshotEntry>,
+    pub removed: Vec<SnapshotEntry>,
+    /// 同一路径内容发生变化的文件: (旧记录, 新记录)
+    pub modified: Vec<(SnapshotEntry, SnapshotEntry)>,
+    /// 内容未变但路径改变的文件: (旧记录, 新记录)
+    pub moved: Vec<(SnapshotEntry, SnapshotEntry)>,
+    /// 总大小的净变化（字节）
+    pub size_change: i64,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.moved.is_empty()
+    }
+
+    pub fn print_diff(&self) {
+        println!("目录变化:");
+        println!("- 新增: {}个文件", self.added.len());
+        println!("- 删除: {}个文件", self.removed.len());
+        println!("- 修改: {}个文件", self.modified.len());
+        println!("- 移动/重命名: {}个文件", self.moved.len());
+
+        let sign = if self.size_change < 0 { "-" } else { "+" };
+        println!("- 大小净变化: {}{}", sign, format_size(self.size_change.unsigned_abs()));
+
+        if self.is_empty() {
+            println!("\n没有发现变化");
+            return;
+        }
+
+        if !self.added.is_empty() {
+            println!("\n新增的文件:");
+            for entry in &self.added {
+                println!("  + {} ({} 字节)", entry.path, entry.size);
+            }
+        }
+
+        if !self.removed.is_empty() {
+            println!("\n删除的文件:");
+            for entry in &self.removed {
+                println!("  - {} ({} 字节)", entry.path, entry.size);
+            }
+        }
+
+        if !self.modified.is_empty() {
+            println!("\n修改的文件:");
+            for (old, new) in &self.modified {
+                println!("  ~ {} ({} -> {} 字节)", new.path, old.size, new.size);
+            }
+        }
+
+        if !self.moved.is_empty() {
+            println!("\n移动/重命名的文件:");
+            for (old, new) in &self.moved {
+                println!("  > {} -> {}", old.path, new.path);
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+use crate::loganalyzer::{LogEntry, LogLevel};
+use crate::logparser::LogParser;
+
+/// 保存设施名称的字段
+pub const FACILITY_FIELD: &str = "facility";
+/// 保存主机名的字段
+pub const HOST_FIELD: &str = "host";
+/// 保存应用名称的字段
+pub const APP_FIELD: &str = "app";
+/// 保存进程ID的字段
+pub const PROCID_FIELD: &str = "procid";
+/// 保存消息ID的字段 (RFC 5424)
+pub const MSGID_FIELD: &str = "msgid";
+
+/// RFC 5424 中表示空值的占位符
+const NIL_VALUE: &str = "-";
+
+/// 按 RFC 5424 第6.2.1节的编号排列的设施名称
+const FACILITY_NAMES: [&str; 24] = [
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news",
+    "uucp", "cron", "authpriv", "ftp", "ntp", "security", "console", "solaris-cron",
+    "local0", "local1", "local2", "local3", "local4", "local5", "local6", "local7",
+];
+
+/// 根据设施编号返回名称，超出范围时返回编号本身
+pub fn facility_name(facility: u8) -> String {
+    FACILITY_NAMES
+        .get(facility as usize)
+        .map_or_else(|| facility.to_string(), |name| name.to_string())
+}
+
+/// 把 syslog 严重性 (0-7) 映射为日志级别: emerg/alert/crit 为严重，notice 和 info 为信息
+pub fn severity_level(severity: u8) -> LogLevel {
+    match severity {
+        0..=2 => LogLevel::Critical,
+        3 => LogLevel::Error,
+        4 => LogLevel::Warning,
+        5 | 6 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+/// 解析行首的 `<PRI>`，返回设施、严重性和剩余部分
+fn parse_priority(line: &str) -> Option<(u8, u8, &str)> {
+    let rest = line.strip_prefix('<')?;
+    let (digits, rest) = rest.split_once('>')?;
+    if digits.is_empty() || digits.len() > 3 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let priority: u8 = digits.parse().ok().filter(|p| *p <= 191)?;
+    Some((priority / 8, priority % 8, rest))
+}
+
+/// 创建条目并记录优先级、主机和应用；`source` 为 `主机/应用`
+fn syslog_entry(
+    timestamp: Option<DateTime<Utc>>,
+    priority: Option<(u8, u8)>,
+    host: Option<&str>,
+    app: Option<&str>,
+    message: &str,
+) -> LogEntry {
+    let level = priority.map_or(LogLevel::Unknown(String::new()), |(_, severity)| severity_level(severity));
+    let mut entry = LogEntry::new(timestamp, level, message.to_string());
+
+    if let Some((facility, _)) = priority {
+        entry.fields.insert(FACILITY_FIELD.to_string(), facility_name(facility));
+    }
+    if let Some(host) = host {
+        entry.fields.insert(HOST_FIELD.to_string(), host.to_string());
+    }
+    if let Some(app) = app {
+        entry.fields.insert(APP_FIELD.to_string(), app.to_string());
+    }
+    entry.source = match (host, app) {
+        (Some(host), Some(app)) => Some(format!("{}/{}", host, app)),
+        (host, app) => host.or(app).map(str::to_string),
+    };
+
+    entry
+}
+
+/// 解析 BSD syslog (RFC 3164): `<34>Oct 11 22:14:15 mymachine su[123]: message`
+///
+/// `/var/log` 中的文件通常没有 `<PRI>`，此时级别为未知。传统时间戳不含年份，
+/// 默认使用当前年份，得到的时间比现在晚一天以上时视为去年的日志。
+/// 也接受 rsyslog 的高精度格式，即用 RFC 3339 时间戳代替传统时间戳。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyslogParser {
+    pub year: Option<i32>,
+}
+
+impl SyslogParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 指定传统时间戳的年份
+    pub fn with_year(mut self, year: i32) -> Self {
+        self.year = Some(year);
+        self
+    }
+
+    /// 解析时间戳，返回时间和剩余部分
+    fn parse_time<'a>(&self, line: &'a str) -> Option<(DateTime<Utc>, &'a str)> {
+        let (first, rest) = line.split_once(' ')?;
+        if let Ok(time) = DateTime::parse_from_rfc3339(first) {
+            return Some((time.with_timezone(&Utc), rest));
+        }
+
+        // 日期小于10时用空格补齐，如 `Oct  1`
+        let rest = rest.trim_start();
+        let (day, rest) = rest.split_once(' ')?;
+        let (time, rest) = rest.split_once(' ')?;
+
+        let now = Utc::now();
+        let year = self.year.unwrap_or(now.year());
+        let naive = NaiveDateTime::parse_from_str(&format!("{} {} {} {}", year, first, day, time), "%Y %b %d %H:%M:%S").ok()?;
+        let mut timestamp = DateTime::from_naive_utc_and_offset(naive, Utc);
+        if self.year.is_none() && timestamp > now + Duration::days(1) {
+            let naive = naive.with_year(year - 1)?;
+            timestamp = DateTime::from_naive_utc_and_offset(naive, Utc);
+        }
+
+        Some((timestamp, rest))
+    }
+}
+
+impl LogParser for SyslogParser {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    fn description(&self) -> &str {
+        "<34>Oct 11 22:14:15 mymachine su[123]: message (RFC 3164)"
+    }
+
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let line = line.trim_end();
+        let (priority, line) = match parse_priority(line) {
+            Some((facility, severity, rest)) => (Some((facility, severity)), rest),
+            None => (None, line),
+        };
+
+        let (timestamp, rest) = self.parse_time(line)?;
+        let (host, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+        if host.is_empty() {
+            return None;
+        }
+
+        // 标签形如 `su[123]:` 或 `kernel:`，没有标签时整个剩余部分都是消息
+        let (tag, message) = match rest.split_once(':') {
+            Some((tag, message)) if !tag.is_empty() && !tag.contains(char::is_whitespace) => {
+                (Some(tag), message.trim_start())
+            }
+            _ => (None, rest),
+        };
+        let (app, procid) = match tag {
+            Some(tag) => match tag.strip_suffix(']').and_then(|t| t.split_once('[')) {
+                Some((app, procid)) => (Some(app), Some(procid)),
+                None => (Some(tag), None),
+            },
+            None => (None, None),
+        };
+
+        let mut entry = syslog_entry(Some(timestamp), priority, Some(host), app, message);
+        if let Some(procid) = procid {
+            entry.fields.insert(PROCID_FIELD.to_string(), procid.to_string());
+        }
+        Some(entry)
+    }
+}
+
+/// 解析 RFC 5424 syslog: `<165>1 2003-10-11T22:14:15.003Z host app 1234 ID47 [id key="value"] message`
+///
+/// 结构化数据的参数保存为 `SD-ID.参数名` 字段，例如 `exampleSDID@32473.iut`。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rfc5424Parser;
+
+impl LogParser for Rfc5424Parser {
+    fn name(&self) -> &str {
+        "rfc5424"
+    }
+
+    fn description(&self) -> &str {
+        "<165>1 2003-10-11T22:14:15.003Z host app 1234 ID47 [id key=\"value\"] message"
+    }
+
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let (facility, severity, rest) = parse_priority(line.trim_end())?;
+        let rest = rest.strip_prefix("1 ")?;
+
+        let mut header = rest.splitn(6, ' ');
+        let timestamp = header.next()?;
+        let host = header.next()?;
+        let app = header.next()?;
+        let procid = header.next()?;
+        let msgid = header.next()?;
+        let rest = header.next().unwrap_or("");
+
+        let timestamp = if timestamp == NIL_VALUE {
+            None
+        } else {
+            Some(DateTime::parse_from_rfc3339(timestamp).ok()?.with_timezone(&Utc))
+        };
+        let (params, message) = parse_structured_data(rest)?;
+        // 消息可以以UTF-8的BOM开头
+        let message = message.trim_start_matches('\u{feff}');
+
+        let host = (host != NIL_VALUE).then_some(host);
+        let app = (app != NIL_VALUE).then_some(app);
+        let mut entry = syslog_entry(timestamp, Some((facility, severity)), host, app, message);
+        if procid != NIL_VALUE {
+            entry.fields.insert(PROCID_FIELD.to_string(), procid.to_string());
+        }
+        if msgid != NIL_VALUE {
+            entry.fields.insert(MSGID_FIELD.to_string(), msgid.to_string());
+        }
+        entry.fields.extend(params);
+        Some(entry)
+    }
+}
+
+/// 解析结构化数据，返回 `(SD-ID.参数名, 值)` 列表和之后的消息；格式错误时返回 `None`
+fn parse_structured_data(input: &str) -> Option<(Vec<(String, String)>, &str)> {
+    if let Some(rest) = input.strip_prefix(NIL_VALUE) {
+        if !rest.is_empty() && !rest.starts_with(' ') {
+            return None;
+        }
+        return Some((Vec::new(), rest.strip_prefix(' ').unwrap_or(rest)));
+    }
+
+    let mut params = Vec::new();
+    let mut rest = input;
+    if !rest.starts_with('[') {
+        return None;
+    }
+
+    while let Some(element) = rest.strip_prefix('[') {
+        let end = element.find([' ', ']'])?;
+        let id = &element[..end];
+        rest = &element[end..];
+
+        loop {
+            rest = rest.trim_start_matches(' ');
+            if let Some(after) = rest.strip_prefix(']') {
+                rest = after;
+                break;
+            }
+
+            let (name, after) = rest.split_once("=\"")?;
+            let (value, after) = parse_param_value(after)?;
+            params.push((format!("{}.{}", id, name), value));
+            rest = after;
+        }
+    }
+
+    Some((params, rest.strip_prefix(' ').unwrap_or(rest)))
+}
+
+/// 解析参数值直到未转义的 `"`，处理 `\"`、`\\` 和 `\]` 转义
+fn parse_param_value(input: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &input[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, escaped @ ('"' | '\\' | ']'))) => value.push(escaped),
+                Some((_, other)) => {
+                    value.push('\\');
+                    value.push(other);
+                }
+                None => return None,
+            },
+            _ => value.push(c),
+        }
+    }
+
+    None
+}
@@ -0,0 +1,154 @@
+use rtools::{search_files, EntryType, SearchAction, SearchCriteria, SortKey};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_copy_and_move_preserve_structure() {
+    let test_dir = "test_actions_copy_dir";
+    let copy_dir = "test_actions_copy_target";
+    let move_dir = "test_actions_move_target";
+    fs::create_dir_all(format!("{}/src/nested", test_dir)).unwrap();
+    fs::write(format!("{}/src/main.rs", test_dir), "fn main() {}").unwrap();
+    fs::write(format!("{}/src/nested/lib.rs", test_dir), "").unwrap();
+    fs::write(format!("{}/notes.txt", test_dir), "notes").unwrap();
+    
+    let result = search_files(test_dir, SearchCriteria::new().with_extension("rs".to_string())).unwrap();
+    
+    // 预演不会创建任何文件
+    let action = SearchAction::Copy { target: copy_dir.into() };
+    let report = action.run(&result, true).unwrap();
+    assert_eq!(report.processed.len(), 2);
+    assert!(!Path::new(copy_dir).exists());
+    
+    let report = action.run(&result, false).unwrap();
+    assert!(report.is_success());
+    assert_eq!(fs::read_to_string(format!("{}/src/main.rs", copy_dir)).unwrap(), "fn main() {}");
+    assert!(Path::new(&format!("{}/src/nested/lib.rs", copy_dir)).exists());
+    assert!(!Path::new(&format!("{}/notes.txt", copy_dir)).exists());
+    
+    // 目标已存在时不覆盖
+    let report = action.run(&result, false).unwrap();
+    assert_eq!(report.failed.len(), 2);
+    
+    let report = SearchAction::Move { target: move_dir.into() }.run(&result, false).unwrap();
+    assert!(report.is_success());
+    assert!(Path::new(&format!("{}/src/nested/lib.rs", move_dir)).exists());
+    assert!(!Path::new(&format!("{}/src/main.rs", test_dir)).exists());
+    assert!(Path::new(&format!("{}/notes.txt", test_dir)).exists());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+    fs::remove_dir_all(copy_dir).unwrap();
+    fs::remove_dir_all(move_dir).unwrap();
+}
+
+#[test]
+fn test_delete_action() {
+    let test_dir = "test_actions_delete_dir";
+    fs::create_dir_all(format!("{}/old/empty", test_dir)).unwrap();
+    fs::write(format!("{}/old/a.tmp", test_dir), "").unwrap();
+    fs::write(format!("{}/keep.txt", test_dir), "").unwrap();
+    
+    let criteria = SearchCriteria::new().with_name_pattern("old".to_string());
+    let dirs = search_files(test_dir, criteria.with_entry_type(EntryType::Dir)).unwrap();
+    let temps = search_files(test_dir, SearchCriteria::new().with_extension("tmp".to_string())).unwrap();
+    
+    let report = SearchAction::Delete.run(&temps, true).unwrap();
+    assert_eq!(report.processed.len(), 1);
+    assert!(Path::new(&format!("{}/old/a.tmp", test_dir)).exists());
+    
+    // 非空目录不会被删除
+    let report = SearchAction::Delete.run(&dirs, false).unwrap();
+    assert_eq!(report.failed.len(), 1);
+    
+    assert!(SearchAction::Delete.run(&temps, false).unwrap().is_success());
+    fs::remove_dir(format!("{}/old/empty", test_dir)).unwrap();
+    assert!(SearchAction::Delete.run(&dirs, false).unwrap().is_success());
+    assert!(!Path::new(&format!("{}/old", test_dir)).exists());
+    assert!(Path::new(&format!("{}/keep.txt", test_dir)).exists());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_action() {
+    let test_dir = "test_actions_exec_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    fs::write(format!("{}/a.txt", test_dir), "").unwrap();
+    fs::write(format!("{}/b.txt", test_dir), "").unwrap();
+    let result = search_files(test_dir, SearchCriteria::new()).unwrap();
+    
+    let exec = |command: &[&str], batch: bool| {
+        let command = command.iter().map(|s| s.to_string()).collect();
+        SearchAction::exec(command, batch).unwrap().run(&result, false).unwrap()
+    };
+    
+    let report = exec(&["test", "-f", "{}"], false);
+    assert!(report.is_success());
+    assert_eq!(report.processed.len(), 2);
+    
+    // 批量执行时两个路径作为同一条命令的参数，命令只在收到两个参数时成功
+    let two_args = ["sh", "-c", "test \"$#\" -eq 2", "sh", "{}"];
+    let report = exec(&two_args, true);
+    assert!(report.is_success(), "{:?}", report.failed);
+    assert_eq!(report.processed.len(), 2);
+    
+    let report = exec(&two_args, false);
+    assert_eq!(report.failed.len(), 2);
+    
+    let report = exec(&["false"], false);
+    assert_eq!(report.failed.len(), 2);
+    
+    // 预演时只列出命令行，不执行命令
+    let remove = SearchAction::exec(vec!["rm".to_string(), "{}".to_string()], true).unwrap();
+    let report = remove.run(&result, true).unwrap();
+    assert!(report.is_success());
+    assert_eq!(report.processed.len(), 2);
+    assert_eq!(report.commands.len(), 1);
+    assert!(report.commands[0].starts_with("rm ") && report.commands[0].contains("a.txt"));
+    assert!(Path::new(&format!("{}/a.txt", test_dir)).exists());
+    
+    let with_space = SearchAction::exec(vec!["echo".to_string(), "a b".to_string()], false).unwrap();
+    let report = with_space.run(&result, true).unwrap();
+    assert_eq!(report.commands.len(), 2);
+    assert!(report.commands[0].starts_with("echo 'a b' "));
+    
+    assert!(SearchAction::exec(Vec::new(), false).is_err());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_move_directory_with_contents() {
+    let test_dir = "test_actions_move_dir_src";
+    let move_dir = "test_actions_move_dir_target";
+    
+    // 结果中同时有目录和其中的文件；按名称排序时目录中的文件排在目录之前
+    for sort in [None, Some(SortKey::Name)] {
+        fs::create_dir_all(format!("{}/sub/deep", test_dir)).unwrap();
+        fs::write(format!("{}/sub/a.txt", test_dir), "a").unwrap();
+        fs::write(format!("{}/sub/deep/b.txt", test_dir), "b").unwrap();
+        fs::write(format!("{}/top.txt", test_dir), "top").unwrap();
+        
+        let mut criteria = SearchCriteria::new()
+            .with_entry_type(EntryType::Dir)
+            .with_entry_type(EntryType::File);
+        if let Some(key) = sort {
+            criteria = criteria.with_sort(key, false);
+        }
+        let result = search_files(test_dir, criteria).unwrap();
+        assert!(result.files.iter().any(|path| path.ends_with("sub")));
+        assert!(result.files.iter().any(|path| path.ends_with("a.txt")));
+        
+        let report = SearchAction::Move { target: move_dir.into() }.run(&result, false).unwrap();
+        assert!(report.is_success(), "{:?}", report.failed);
+        assert!(Path::new(&format!("{}/sub/a.txt", move_dir)).exists());
+        assert!(Path::new(&format!("{}/sub/deep/b.txt", move_dir)).exists());
+        assert!(Path::new(&format!("{}/top.txt", move_dir)).exists());
+        assert!(!Path::new(&format!("{}/sub", test_dir)).exists());
+        
+        fs::remove_dir_all(move_dir).unwrap();
+    }
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
@@ -0,0 +1,89 @@
+use rtools::{scan_directory_with_options, CleanupKind, CleanupOptions, CleanupReport, ScanOptions};
+use std::fs;
+use std::path::Path;
+
+fn cleanup_report(dir: &str) -> CleanupReport {
+    let options = ScanOptions::new().with_cleanup(CleanupOptions::new());
+    scan_directory_with_options(dir, &options).unwrap().cleanup.unwrap()
+}
+
+#[test]
+fn test_cleanup_report() {
+    let test_dir = "test_cleanup_dir";
+    fs::create_dir_all(format!("{}/empty/nested", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/data", test_dir)).unwrap();
+    fs::write(format!("{}/data/keep.txt", test_dir), "content").unwrap();
+    fs::write(format!("{}/data/empty.txt", test_dir), "").unwrap();
+    fs::write(format!("{}/app.lock", test_dir), "").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("missing_target", format!("{}/broken", test_dir)).unwrap();
+    
+    let report = cleanup_report(test_dir);
+    
+    let paths = |kind| -> Vec<String> {
+        report.items_of(kind).map(|item| item.path.display().to_string()).collect()
+    };
+    assert_eq!(paths(CleanupKind::EmptyFile), vec![format!("{}/data/empty.txt", test_dir)]);
+    assert_eq!(paths(CleanupKind::LockFile), vec![format!("{}/app.lock", test_dir)]);
+    // 递归为空的目录只报告最上层
+    assert_eq!(paths(CleanupKind::EmptyDirectory), vec![format!("{}/empty", test_dir)]);
+    #[cfg(unix)]
+    assert_eq!(paths(CleanupKind::BrokenSymlink), vec![format!("{}/broken", test_dir)]);
+    // 没有设置过期时长时不检查过期文件
+    assert_eq!(report.items_of(CleanupKind::StaleFile).count(), 0);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_cleanup_delete() {
+    let test_dir = "test_cleanup_delete_dir";
+    fs::create_dir_all(format!("{}/empty/nested", test_dir)).unwrap();
+    fs::write(format!("{}/keep.txt", test_dir), "content").unwrap();
+    fs::write(format!("{}/empty.txt", test_dir), "").unwrap();
+    
+    let report = cleanup_report(test_dir);
+    assert_eq!(report.items.len(), 2);
+    
+    // 预演不会删除任何内容
+    let dry_run = report.delete(true);
+    assert_eq!(dry_run.deleted.len(), 2);
+    assert!(Path::new(&format!("{}/empty.txt", test_dir)).exists());
+    assert!(Path::new(&format!("{}/empty/nested", test_dir)).exists());
+    
+    let result = report.delete(false);
+    assert_eq!(result.deleted.len(), 2);
+    assert!(result.failed.is_empty());
+    assert!(!Path::new(&format!("{}/empty.txt", test_dir)).exists());
+    assert!(!Path::new(&format!("{}/empty", test_dir)).exists());
+    assert!(Path::new(&format!("{}/keep.txt", test_dir)).exists());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cleanup_ignores_symlinked_directories() {
+    let test_dir = "test_cleanup_symlink_dir";
+    let outside = "test_cleanup_symlink_outside";
+    fs::create_dir_all(format!("{}/root", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/emptydir", outside)).unwrap();
+    fs::write(format!("{}/empty.txt", outside), "").unwrap();
+    fs::write(format!("{}/app.lock", outside), "").unwrap();
+    std::os::unix::fs::symlink(format!("../../{}", outside), format!("{}/root/link", test_dir)).unwrap();
+    // 指向根目录之外空文件的链接也不是候选项
+    std::os::unix::fs::symlink(format!("../../{}/empty.txt", outside), format!("{}/root/file_link", test_dir)).unwrap();
+    
+    let root = format!("{}/root", test_dir);
+    let report = cleanup_report(&root);
+    assert!(report.items.is_empty(), "{:?}", report.items);
+    
+    let result = report.delete(false);
+    assert!(result.deleted.is_empty());
+    assert!(Path::new(&format!("{}/empty.txt", outside)).exists());
+    assert!(Path::new(&format!("{}/emptydir", outside)).exists());
+    assert!(Path::new(&format!("{}/app.lock", outside)).exists());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+    fs::remove_dir_all(outside).unwrap();
+}
@@ -1,6 +1,5 @@
-use rtools::scan_directory;
+use rtools::{scan_directory, scan_directory_with_options, parse_age, ScanOptions};
 use std::fs;
-use std::path::Path;
 
 #[test]
 fn test_scan_directory() {
@@ -82,4 +81,79 @@ fn test_scan_file_as_directory() {
     
     // 清理测试文件
     fs::remove_file(test_file).unwrap();
-} 
\ No newline at end of file
+}
+
+#[test]
+fn test_scan_directory_rankings() {
+    // 创建测试目录结构
+    let test_dir = "test_rankings_dir";
+    let big_dir = format!("{}/big", test_dir);
+    let deep_dir = format!("{}/a/b/c", test_dir);
+    
+    fs::create_dir_all(&big_dir).unwrap();
+    fs::create_dir_all(&deep_dir).unwrap();
+    fs::write(format!("{}/large.bin", big_dir), vec![0u8; 4096]).unwrap();
+    fs::write(format!("{}/small1.txt", test_dir), "a").unwrap();
+    fs::write(format!("{}/small2.txt", test_dir), "ab").unwrap();
+    fs::write(format!("{}/small3.txt", test_dir), "abc").unwrap();
+    fs::write(format!("{}/deep.txt", deep_dir), "deep").unwrap();
+    
+    let options = ScanOptions::new().with_top_n(2);
+    let stats = scan_directory_with_options(test_dir, &options).unwrap();
+    
+    // 排行榜只保留N个条目，并按排序键降序排列
+    assert_eq!(stats.top_n(), 2);
+    assert_eq!(stats.largest_files.len(), 2);
+    let largest: Vec<&str> = stats.largest_files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(largest, vec!["large.bin", "deep.txt"]);
+    assert_eq!(stats.newest_files.len(), 2);
+    assert_eq!(stats.oldest_files.len(), 2);
+    
+    let largest_dir = stats.largest_dirs.iter().next().unwrap();
+    assert!(largest_dir.path.ends_with("big"));
+    assert_eq!(largest_dir.total_size, 4096);
+    
+    let deepest = stats.deepest_paths.iter().next().unwrap();
+    assert_eq!(deepest.name, "deep.txt");
+    assert_eq!(deepest.depth, 4);
+    
+    // 清理测试目录
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_scan_directory_histograms() {
+    let test_dir = "test_histogram_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    fs::write(format!("{}/tiny.txt", test_dir), "a").unwrap();
+    fs::write(format!("{}/small.bin", test_dir), vec![0u8; 2048]).unwrap();
+    fs::write(format!("{}/medium.bin", test_dir), vec![0u8; 5000]).unwrap();
+    
+    let stats = scan_directory(test_dir, None).unwrap();
+    
+    let sizes = &stats.size_histogram.buckets;
+    assert_eq!(sizes[0].label, "<1K");
+    assert_eq!((sizes[0].count, sizes[0].bytes), (1, 1));
+    assert_eq!((sizes[1].count, sizes[1].bytes), (1, 2048));
+    assert_eq!((sizes[2].count, sizes[2].bytes), (1, 5000));
+    assert_eq!(sizes.last().unwrap().label, ">1G");
+    assert_eq!(stats.size_histogram.total_count(), 3);
+    
+    // 刚创建的文件都属于"今天"
+    let ages = &stats.age_histogram.buckets;
+    assert_eq!(ages[0].count, 3);
+    assert_eq!(ages[0].bytes, 7049);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_parse_age() {
+    assert_eq!(parse_age("30s").unwrap().as_secs(), 30);
+    assert_eq!(parse_age("2h").unwrap().as_secs(), 7200);
+    assert_eq!(parse_age("7d").unwrap().as_secs(), 7 * 86400);
+    assert_eq!(parse_age("7").unwrap().as_secs(), 7 * 86400);
+    assert_eq!(parse_age("1w").unwrap().as_secs(), 7 * 86400);
+    assert!(parse_age("d").is_err());
+    assert!(parse_age("5x").is_err());
+}
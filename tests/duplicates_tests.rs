@@ -0,0 +1,60 @@
+use rtools::{find_duplicates, DuplicateOptions};
+use std::fs;
+
+#[test]
+fn test_find_duplicates() {
+    let test_dir = "test_duplicates_dir";
+    fs::create_dir_all(format!("{}/sub", test_dir)).unwrap();
+    
+    // 两组重复文件，其中一组超过部分摘要的长度
+    let large = vec![7u8; 10000];
+    let mut large_variant = large.clone();
+    large_variant[9999] = 8;
+    fs::write(format!("{}/a.txt", test_dir), "same content").unwrap();
+    fs::write(format!("{}/sub/a_copy.txt", test_dir), "same content").unwrap();
+    fs::write(format!("{}/b.txt", test_dir), "diff content").unwrap();
+    fs::write(format!("{}/large1.bin", test_dir), &large).unwrap();
+    fs::write(format!("{}/large2.bin", test_dir), &large).unwrap();
+    fs::write(format!("{}/large3.bin", test_dir), &large).unwrap();
+    fs::write(format!("{}/large_variant.bin", test_dir), &large_variant).unwrap();
+    fs::write(format!("{}/empty1.txt", test_dir), "").unwrap();
+    fs::write(format!("{}/empty2.txt", test_dir), "").unwrap();
+    
+    let report = find_duplicates(test_dir, &DuplicateOptions::new()).unwrap();
+    
+    assert_eq!(report.files_scanned, 9);
+    assert_eq!(report.sets.len(), 2);
+    // 按浪费空间降序排列
+    assert_eq!(report.sets[0].copies.len(), 3);
+    assert_eq!(report.sets[0].wasted_bytes(), 20000);
+    assert_eq!(report.sets[1].file_count(), 2);
+    assert_eq!(report.sets[1].wasted_bytes(), 12);
+    assert_eq!(report.wasted_bytes(), 20012);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_find_duplicates_hard_links() {
+    let test_dir = "test_duplicates_links_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    fs::write(format!("{}/original.txt", test_dir), "linked content").unwrap();
+    fs::hard_link(format!("{}/original.txt", test_dir), format!("{}/link.txt", test_dir)).unwrap();
+    fs::write(format!("{}/copy.txt", test_dir), "linked content").unwrap();
+    
+    // 不识别硬链接时三个路径都算作副本
+    let report = find_duplicates(test_dir, &DuplicateOptions::new()).unwrap();
+    assert_eq!(report.wasted_bytes(), 28);
+    
+    // 识别硬链接后只有一份额外副本
+    let options = DuplicateOptions::new().with_hard_links(true);
+    let report = find_duplicates(test_dir, &options).unwrap();
+    assert_eq!(report.hard_links, 1);
+    assert_eq!(report.sets.len(), 1);
+    assert_eq!(report.sets[0].copies.len(), 2);
+    assert_eq!(report.sets[0].file_count(), 3);
+    assert_eq!(report.wasted_bytes(), 14);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
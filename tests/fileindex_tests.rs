@@ -0,0 +1,128 @@
+use rtools::{search_files, EntryType, FileIndex, SearchCriteria, SortKey};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+fn names(result: &rtools::SearchResult) -> Vec<String> {
+    result.files.iter().map(|p| p.to_string_lossy().replace('\\', "/")).collect()
+}
+
+#[test]
+fn test_index_build_save_and_search() {
+    let test_dir = "test_index_build_dir";
+    fs::create_dir_all(format!("{}/src/nested", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/src other", test_dir)).unwrap();
+    fs::write(format!("{}/Cargo.toml", test_dir), "[package]").unwrap();
+    fs::write(format!("{}/src/main.rs", test_dir), "fn main() {}").unwrap();
+    fs::write(format!("{}/src/nested/lib.rs", test_dir), "").unwrap();
+    fs::write(format!("{}/src other/tab\tname.rs", test_dir), "x").unwrap();
+    
+    let index = FileIndex::build(test_dir).unwrap();
+    assert_eq!(index.dir_count(), 4);
+    assert_eq!(index.file_count(), 4);
+    
+    let index_path = FileIndex::default_path(test_dir);
+    index.save_to_file(&index_path).unwrap();
+    let loaded = FileIndex::load_from_file(&index_path).unwrap();
+    assert_eq!(loaded.dirs, index.dirs);
+    assert_eq!(loaded.root, index.root);
+    
+    let criteria = SearchCriteria::new()
+        .with_extension("rs".to_string())
+        .with_sort(SortKey::Path, false);
+    let result = loaded.search(test_dir, &criteria).unwrap();
+    assert_eq!(result.total_count, 3);
+    assert!(names(&result).iter().any(|name| name.ends_with("tab\tname.rs")));
+    
+    // 在子目录中搜索时不包含名称相近的兄弟目录
+    let sub_dir = format!("{}/src", test_dir);
+    let result = loaded.search(&sub_dir, &criteria).unwrap();
+    assert_eq!(names(&result), vec![
+        format!("{}/src/main.rs", test_dir),
+        format!("{}/src/nested/lib.rs", test_dir),
+    ]);
+    
+    let result = loaded.search(&sub_dir, &criteria.clone().with_max_depth(0)).unwrap();
+    assert_eq!(result.total_count, 1);
+    
+    let criteria = SearchCriteria::new().with_size_range(Some(5), None);
+    assert_eq!(loaded.search(test_dir, &criteria).unwrap().total_count, 2);
+    
+    // 索引不保存文件内容
+    let criteria = SearchCriteria::new().with_empty(true);
+    assert!(loaded.search(test_dir, &criteria).is_err());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_index_incremental_update() {
+    let test_dir = "test_index_update_dir";
+    fs::create_dir_all(format!("{}/keep", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/gone/deep", test_dir)).unwrap();
+    fs::write(format!("{}/keep/a.txt", test_dir), "a").unwrap();
+    
+    let mut index = FileIndex::build(test_dir).unwrap();
+    assert_eq!(index.dir_count(), 4);
+    
+    // 没有变化时所有目录都直接沿用
+    let update = index.update().unwrap();
+    assert_eq!((update.dirs_scanned, update.dirs_reused, update.dirs_removed), (0, 4, 0));
+    
+    // 部分文件系统的时间戳精度较低，等待后再修改以确保目录的修改时间发生变化
+    thread::sleep(Duration::from_millis(50));
+    fs::remove_dir_all(format!("{}/gone", test_dir)).unwrap();
+    fs::write(format!("{}/keep/b.txt", test_dir), "b").unwrap();
+    
+    let update = index.update().unwrap();
+    assert_eq!(update.dirs_scanned, 2);
+    assert_eq!(update.dirs_removed, 2);
+    assert_eq!(index.dir_count(), 2);
+    
+    let result = index.search(test_dir, &SearchCriteria::new().with_name_pattern("b".to_string())).unwrap();
+    assert_eq!(result.total_count, 1);
+    assert!(Path::new(&result.files[0]).ends_with("keep/b.txt"));
+    
+    // 原地改写文件不改变目录的修改时间，更新时仍会读取新的大小
+    fs::write(format!("{}/keep/a.txt", test_dir), "rewritten").unwrap();
+    let update = index.update().unwrap();
+    assert_eq!(update.dirs_scanned, 0);
+    let criteria = SearchCriteria::new().with_size_range(Some(5), None);
+    let result = index.search(test_dir, &criteria).unwrap();
+    assert_eq!(result.total_count, 1);
+    assert!(Path::new(&result.files[0]).ends_with("keep/a.txt"));
+    
+    assert!(FileIndex::load_from_file(Path::new("test_index_missing")).is_err());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_index_symlinks_like_disk_search() {
+    let test_dir = "test_index_symlink_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    fs::write(format!("{}/target.txt", test_dir), "content").unwrap();
+    std::os::unix::fs::symlink("target.txt", format!("{}/link.txt", test_dir)).unwrap();
+    std::os::unix::fs::symlink("missing.txt", format!("{}/broken.txt", test_dir)).unwrap();
+    
+    // 默认按链接目标的类型匹配，与直接搜索磁盘的结果相同
+    let criteria = SearchCriteria::new().with_sort(SortKey::Name, false);
+    let from_disk = search_files(test_dir, criteria.clone()).unwrap();
+    
+    let index = FileIndex::build(test_dir).unwrap();
+    let index_path = FileIndex::default_path(test_dir);
+    index.save_to_file(&index_path).unwrap();
+    let loaded = FileIndex::load_from_file(&index_path).unwrap();
+    assert_eq!(loaded.dirs, index.dirs);
+    
+    let from_index = loaded.search(test_dir, &criteria).unwrap();
+    assert_eq!(names(&from_index), names(&from_disk));
+    assert_eq!(from_index.total_count, 2);
+    
+    let criteria = SearchCriteria::new().with_entry_type(EntryType::Symlink);
+    assert_eq!(loaded.search(test_dir, &criteria).unwrap().total_count, 2);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
@@ -1,6 +1,5 @@
 use rtools::get_file_info;
 use std::fs;
-use std::path::Path;
 
 #[test]
 fn test_get_file_info_existing_file() {
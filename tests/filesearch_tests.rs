@@ -0,0 +1,493 @@
+use rtools::{FuzzyQuery, search_files, search_files_with, search_iter, parse_size, parse_size_range, parse_time_spec, ContentMode, ContentQuery, EntryType, FilterExpr, NamePattern,
+             PathDisplay, PermissionMatch, SearchCriteria, SearchResult, SortKey};
+use std::time::{Duration, SystemTime};
+use std::fs;
+
+fn relative_names(result: &SearchResult, root: &str) -> Vec<String> {
+    let mut names: Vec<String> = result.files
+        .iter()
+        .map(|p| p.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_name_patterns() {
+    let glob = NamePattern::glob("test_?.log", false).unwrap();
+    assert!(glob.matches("test_1.log", "logs/test_1.log"));
+    assert!(glob.matches("TEST_1.LOG", "logs/TEST_1.LOG"));
+    assert!(!glob.matches("test_10.log", "logs/test_10.log"));
+    
+    let path_glob = NamePattern::glob("**/src/*.toml", true).unwrap();
+    assert!(path_glob.matches("a.toml", "src/a.toml"));
+    assert!(path_glob.matches("a.toml", "crates/x/src/a.toml"));
+    assert!(!path_glob.matches("a.toml", "src/nested/a.toml"));
+    assert!(!path_glob.matches("A.toml", "SRC/A.toml"));
+    
+    let braces = NamePattern::glob("*.{rs,toml}", true).unwrap();
+    assert!(braces.matches("main.rs", "src/main.rs"));
+    assert!(!braces.matches("main.c", "src/main.c"));
+    
+    let regex = NamePattern::regex(r"^src/.*_tests?\.rs$", true).unwrap();
+    assert!(regex.matches("x_test.rs", "src/x_test.rs"));
+    assert!(!regex.matches("x_test.rs", "tests/x_test.rs"));
+    
+    assert!(NamePattern::regex("(unclosed", false).is_err());
+    assert!(NamePattern::glob("[abc", false).is_err());
+}
+
+#[test]
+fn test_search_include_exclude() {
+    let test_dir = "test_search_patterns_dir";
+    fs::create_dir_all(format!("{}/src", test_dir)).unwrap();
+    fs::write(format!("{}/Cargo.toml", test_dir), "").unwrap();
+    fs::write(format!("{}/src/main.rs", test_dir), "").unwrap();
+    fs::write(format!("{}/src/test_main.rs", test_dir), "").unwrap();
+    fs::write(format!("{}/src/Config.TOML", test_dir), "").unwrap();
+    
+    let criteria = SearchCriteria::new()
+        .with_include(NamePattern::glob("*.rs", false).unwrap())
+        .with_include(NamePattern::glob("*.toml", false).unwrap())
+        .with_exclude(NamePattern::glob("test_*", false).unwrap());
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["Cargo.toml", "src/Config.TOML", "src/main.rs"]);
+    
+    // 区分大小写时 Config.TOML 不再匹配
+    let criteria = SearchCriteria::new()
+        .with_include(NamePattern::glob("**/*.toml", true).unwrap());
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["Cargo.toml"]);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_content_search() {
+    let test_dir = "test_content_search_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    fs::write(format!("{}/app.log", test_dir), "line1\nERROR first\nline3\nline4\nline5\nline6\nerror second\n").unwrap();
+    fs::write(format!("{}/clean.log", test_dir), "all good\n").unwrap();
+    fs::write(format!("{}/data.bin", test_dir), b"ERROR\0binary").unwrap();
+    
+    // 忽略大小写的字面搜索，带一行上下文
+    let query = ContentQuery::literal("error", false).unwrap().with_context(1, 1);
+    let result = search_files(test_dir, SearchCriteria::new().with_content(query)).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["app.log"]);
+    assert_eq!(result.content_matches.len(), 1);
+    
+    let matches = &result.content_matches[0];
+    assert_eq!(matches.match_count, 2);
+    let numbers: Vec<usize> = matches.lines.iter().map(|l| l.line_number).collect();
+    assert_eq!(numbers, vec![1, 2, 3, 6, 7]);
+    let matched: Vec<&str> = matches.matched_lines().map(|l| l.text.as_str()).collect();
+    assert_eq!(matched, vec!["ERROR first", "error second"]);
+    
+    // 只统计数量时不保留行内容
+    let query = ContentQuery::regex("^ERROR", true).unwrap().with_mode(ContentMode::CountOnly);
+    let result = search_files(test_dir, SearchCriteria::new().with_content(query)).unwrap();
+    assert_eq!(result.content_matches[0].match_count, 1);
+    assert!(result.content_matches[0].lines.is_empty());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+/// 取得第一个匹配后删除目录中其余的条目再继续搜索；目录的条目名称已经读出，
+/// 这些条目仍会被检查但无法读取
+fn search_removing_rest(test_dir: &str, criteria: SearchCriteria) -> (usize, Vec<String>) {
+    let mut iter = search_iter(test_dir, criteria).unwrap();
+    let first = iter.next().unwrap().unwrap();
+    for entry in fs::read_dir(test_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path == first.path {
+            continue;
+        }
+        if path.is_dir() {
+            fs::remove_dir_all(&path).unwrap();
+        } else {
+            fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    let rest: Vec<_> = (&mut iter).collect::<Result<_, _>>().unwrap();
+    (rest.len() + 1, iter.warnings().to_vec())
+}
+
+#[test]
+fn test_content_search_skips_unreadable_files() {
+    let test_dir = "test_content_unreadable_dir";
+    let query = ContentQuery::literal("error", false).unwrap();
+    let expression = FilterExpr::parse("content error", false).unwrap();
+    
+    for criteria in [SearchCriteria::new().with_content(query), SearchCriteria::new().with_expression(expression)] {
+        fs::create_dir_all(test_dir).unwrap();
+        fs::write(format!("{}/a.log", test_dir), "ERROR here\n").unwrap();
+        fs::write(format!("{}/b.log", test_dir), "ERROR there\n").unwrap();
+        
+        let (found, warnings) = search_removing_rest(test_dir, criteria);
+        assert_eq!(found, 1);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].contains(".log"));
+        
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+    
+    // 无法读取的目录对 empty 条件同样按不匹配处理
+    fs::create_dir_all(format!("{}/one", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/two", test_dir)).unwrap();
+    let criteria = SearchCriteria::new()
+        .with_max_depth(0)
+        .with_expression(FilterExpr::parse("type d AND empty", false).unwrap());
+    let (found, warnings) = search_removing_rest(test_dir, criteria);
+    assert_eq!(found, 1);
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_parse_filters() {
+    let week_ago = parse_time_spec("7d").unwrap();
+    let elapsed = SystemTime::now().duration_since(week_ago).unwrap();
+    assert!(elapsed >= Duration::from_secs(7 * 86400));
+    assert!(elapsed < Duration::from_secs(7 * 86400 + 60));
+    assert!(parse_time_spec("2024-01-31").is_ok());
+    assert!(parse_time_spec("2024-01-31 08:30:00").is_ok());
+    assert!(parse_time_spec("yesterday").is_err());
+    
+    assert_eq!(PermissionMatch::parse("644").unwrap(), PermissionMatch::Exact(0o644));
+    assert_eq!(PermissionMatch::parse("-002").unwrap(), PermissionMatch::All(0o002));
+    assert_eq!(PermissionMatch::parse("/111").unwrap(), PermissionMatch::Any(0o111));
+    assert!(PermissionMatch::parse("9").is_err());
+    assert!(PermissionMatch::WORLD_WRITABLE.matches(0o100666));
+    assert!(!PermissionMatch::WORLD_WRITABLE.matches(0o100644));
+    assert!(PermissionMatch::EXECUTABLE.matches(0o100744));
+    
+    assert_eq!(EntryType::parse("d"), Some(EntryType::Dir));
+    assert_eq!(EntryType::parse("x"), None);
+}
+
+#[test]
+fn test_search_type_time_and_empty_filters() {
+    let test_dir = "test_search_filters_dir";
+    fs::create_dir_all(format!("{}/empty_dir", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/full_dir", test_dir)).unwrap();
+    fs::write(format!("{}/full_dir/data.txt", test_dir), "data").unwrap();
+    fs::write(format!("{}/empty.txt", test_dir), "").unwrap();
+    
+    // 空文件和空目录
+    let criteria = SearchCriteria::new()
+        .with_entry_type(EntryType::File)
+        .with_entry_type(EntryType::Dir)
+        .with_empty(true);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["empty.txt", "empty_dir"]);
+    
+    // 只搜索目录
+    let criteria = SearchCriteria::new().with_entry_type(EntryType::Dir);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["empty_dir", "full_dir"]);
+    
+    // 刚创建的文件都在最近一天内修改过
+    let criteria = SearchCriteria::new().with_modified(Some(parse_time_spec("1d").unwrap()), None);
+    assert_eq!(search_files(test_dir, criteria).unwrap().total_count, 2);
+    let criteria = SearchCriteria::new().with_modified(None, Some(parse_time_spec("1d").unwrap()));
+    assert_eq!(search_files(test_dir, criteria).unwrap().total_count, 0);
+    
+    // 不记录创建时间的文件系统上创建时间条件不匹配任何条目，而不是中止搜索
+    let birth_time_supported = fs::metadata(test_dir).unwrap().created().is_ok();
+    let criteria = SearchCriteria::new().with_created(Some(parse_time_spec("1d").unwrap()), None);
+    let expected = if birth_time_supported { 2 } else { 0 };
+    assert_eq!(search_files(test_dir, criteria).unwrap().total_count, expected);
+    let expr = FilterExpr::parse("btime > 1d", false).unwrap();
+    let result = search_files(test_dir, SearchCriteria::new().with_expression(expr)).unwrap();
+    assert_eq!(result.total_count, expected);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("512").unwrap(), 512);
+    assert_eq!(parse_size("10K").unwrap(), 10 * 1024);
+    assert_eq!(parse_size("10kb").unwrap(), 10_000);
+    assert_eq!(parse_size("1.5MiB").unwrap(), 1536 * 1024);
+    assert_eq!(parse_size("2G").unwrap(), 2 << 30);
+    assert!(parse_size("").is_err());
+    assert!(parse_size("M").is_err());
+    assert!(parse_size("10X").is_err());
+    assert!(parse_size("1.2.3K").is_err());
+    assert!(parse_size("99999999999T").is_err());
+    
+    // 单个值与 find -size 相同，按单位向上取整后相等
+    assert_eq!(parse_size_range("10K").unwrap(), (Some(9 * 1024 + 1), Some(10240)));
+    assert_eq!(parse_size_range("10M").unwrap(), (Some((9 << 20) + 1), Some(10 << 20)));
+    assert_eq!(parse_size_range("512").unwrap(), (Some(512), Some(512)));
+    assert_eq!(parse_size_range("0K").unwrap(), (Some(0), Some(0)));
+    assert!(parse_size_range("1.5M").is_err());
+    assert_eq!(parse_size_range("1K-2K").unwrap(), (Some(1024), Some(2048)));
+    assert_eq!(parse_size_range("10M-").unwrap(), (Some(10 << 20), None));
+    assert_eq!(parse_size_range("+100M").unwrap(), (Some((100 << 20) + 1), None));
+    assert_eq!(parse_size_range(">1G").unwrap(), (Some((1 << 30) + 1), None));
+    assert_eq!(parse_size_range(">=1G").unwrap(), (Some(1 << 30), None));
+    assert_eq!(parse_size_range("-1K").unwrap(), (None, Some(1023)));
+    assert_eq!(parse_size_range("<=1K").unwrap(), (None, Some(1024)));
+    assert!(parse_size_range("2K-1K").is_err());
+    assert!(parse_size_range("-0").is_err());
+    assert!(parse_size_range("big").is_err());
+}
+
+#[test]
+fn test_parse_filter_expressions() {
+    assert!(FilterExpr::parse("(ext rs OR ext toml) AND NOT name test AND size > 10K", false).is_ok());
+    assert!(FilterExpr::parse("ext rs && !name 'my test' || type d", false).is_ok());
+    assert!(FilterExpr::parse("mtime >= 7d empty", false).is_ok());
+    assert!(FilterExpr::parse("ctime < 1d OR btime > 2d OR crtime <= 1h", false).is_ok());
+    // 时间按时间点比较，不支持相等判断
+    assert!(FilterExpr::parse("mtime = 1d", false).is_err());
+    assert!(FilterExpr::parse("atime != 2024-01-31", false).is_err());
+    assert!(FilterExpr::parse("mtime 7d", false).is_err());
+    
+    // 错误信息包含出错的位置
+    let error = FilterExpr::parse("ext rs AND (name a", false).unwrap_err().to_string();
+    assert!(error.contains("位置"), "{}", error);
+    let error = FilterExpr::parse("ext rs AND colour red", false).unwrap_err().to_string();
+    assert!(error.contains("位置 11"), "{}", error);
+    
+    assert!(FilterExpr::parse("", false).is_err());
+    assert!(FilterExpr::parse("size > big", false).is_err());
+    assert!(FilterExpr::parse("name", false).is_err());
+    assert!(FilterExpr::parse("ext rs )", false).is_err());
+    assert!(FilterExpr::parse("name 'unclosed", false).is_err());
+    assert!(FilterExpr::parse("size => 1", false).is_err());
+}
+
+#[test]
+fn test_search_with_expression() {
+    let test_dir = "test_search_expr_dir";
+    fs::create_dir_all(format!("{}/src/empty", test_dir)).unwrap();
+    fs::write(format!("{}/Cargo.toml", test_dir), "x".repeat(20 * 1024)).unwrap();
+    fs::write(format!("{}/small.toml", test_dir), "x").unwrap();
+    fs::write(format!("{}/src/main.rs", test_dir), "x".repeat(11 * 1024)).unwrap();
+    fs::write(format!("{}/src/test_main.rs", test_dir), "x".repeat(11 * 1024)).unwrap();
+    fs::write(format!("{}/src/notes.txt", test_dir), "x".repeat(11 * 1024)).unwrap();
+    
+    let search = |expression: &str| {
+        let expr = FilterExpr::parse(expression, false).unwrap();
+        let result = search_files(test_dir, SearchCriteria::new().with_expression(expr)).unwrap();
+        relative_names(&result, test_dir)
+    };
+    
+    assert_eq!(
+        search("(ext rs OR ext toml) AND NOT name test AND size > 10K"),
+        vec!["Cargo.toml", "src/main.rs"]
+    );
+    assert_eq!(search("size <= 1K || ext txt"), vec!["small.toml", "src/notes.txt"]);
+    assert_eq!(search("type d empty"), vec!["src/empty"]);
+    assert_eq!(search("type d OR glob 'Cargo.*'"), vec!["Cargo.toml", "src", "src/empty"]);
+    // 否定的类型条件不会使其他类型的条目参与匹配
+    assert_eq!(search("NOT type d AND ext toml"), vec!["Cargo.toml", "small.toml"]);
+    #[cfg(unix)]
+    {
+        let dangling = format!("{}/dangling", test_dir);
+        std::os::unix::fs::symlink("missing", &dangling).unwrap();
+        assert!(!search("NOT type d").contains(&"dangling".to_string()));
+        assert_eq!(search("type l"), vec!["dangling"]);
+        fs::remove_file(&dangling).unwrap();
+    }
+    assert_eq!(search("content xxx AND NOT ext rs AND mtime > 1d"), vec!["Cargo.toml", "src/notes.txt"]);
+    
+    // ctime 是 inode 变化时间: 修改时间被设为很久以前的文件在改名后仍然最近变化过
+    #[cfg(unix)]
+    {
+        let old = SystemTime::now() - Duration::from_secs(30 * 24 * 3600);
+        let file = fs::File::options().write(true).open(format!("{}/small.toml", test_dir)).unwrap();
+        file.set_modified(old).unwrap();
+        drop(file);
+        fs::rename(format!("{}/small.toml", test_dir), format!("{}/renamed.toml", test_dir)).unwrap();
+        assert_eq!(search("ext toml AND mtime > 1d"), vec!["Cargo.toml"]);
+        assert_eq!(search("ext toml AND ctime > 1d"), vec!["Cargo.toml", "renamed.toml"]);
+        assert!(search("ctime < 1d").is_empty());
+    }
+    
+    // 表达式与普通条件同时满足
+    let expr = FilterExpr::parse("NOT name test", false).unwrap();
+    let criteria = SearchCriteria::new().with_extension("rs".to_string()).with_expression(expr);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["src/main.rs"]);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_search_sort_and_limit() {
+    let test_dir = "test_search_sort_dir";
+    fs::create_dir_all(format!("{}/a", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/b", test_dir)).unwrap();
+    fs::write(format!("{}/a/same.txt", test_dir), "12345").unwrap();
+    fs::write(format!("{}/b/same.txt", test_dir), "1").unwrap();
+    fs::write(format!("{}/b/big.log", test_dir), "1234567890").unwrap();
+    
+    let paths = |result: &SearchResult| -> Vec<String> {
+        result.files.iter().map(|p| result.display_path(p, PathDisplay::Relative)).collect()
+    };
+    
+    let criteria = SearchCriteria::new().with_sort(SortKey::Size, true);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(paths(&result), vec!["b/big.log", "a/same.txt", "b/same.txt"]);
+    assert_eq!(result.details.iter().map(|d| d.size).collect::<Vec<_>>(), vec![10, 5, 1]);
+    
+    // 名称相同时按路径排序
+    let criteria = SearchCriteria::new().with_sort(SortKey::Name, false);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(paths(&result), vec!["b/big.log", "a/same.txt", "b/same.txt"]);
+    
+    let criteria = SearchCriteria::new().with_sort(SortKey::Extension, true);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(paths(&result), vec!["b/same.txt", "a/same.txt", "b/big.log"]);
+    
+    let criteria = SearchCriteria::new().with_sort(SortKey::Path, false).with_limit(2);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(paths(&result), vec!["a/same.txt", "b/big.log"]);
+    assert_eq!(result.total_count, 2);
+    assert_eq!(result.total_size, 15);
+    
+    // 不排序时达到上限就停止搜索
+    let result = search_files(test_dir, SearchCriteria::new().with_limit(1)).unwrap();
+    assert_eq!(result.files.len(), 1);
+    
+    assert!(!result.display_path(&result.files[0], PathDisplay::Name).contains('/'));
+    let absolute = result.display_path(&result.files[0], PathDisplay::Absolute);
+    assert!(std::path::Path::new(&absolute).is_absolute());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_streaming_search() {
+    let test_dir = "test_search_stream_dir";
+    fs::create_dir_all(format!("{}/a/b", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/c", test_dir)).unwrap();
+    for file in ["1.txt", "a/2.txt", "a/b/3.txt", "c/4.txt"] {
+        fs::write(format!("{}/{}", test_dir, file), "x").unwrap();
+    }
+    
+    // 流式结果与一次性搜索的顺序相同
+    let streamed: Vec<_> = search_iter(test_dir, SearchCriteria::new())
+        .unwrap()
+        .map(|found| found.unwrap().path)
+        .collect();
+    let result = search_files(test_dir, SearchCriteria::new()).unwrap();
+    assert_eq!(streamed, result.files);
+    assert_eq!(streamed.len(), 4);
+    
+    // 提前停止
+    let first: Vec<_> = search_iter(test_dir, SearchCriteria::new()).unwrap().take(2).collect();
+    assert_eq!(first.len(), 2);
+    let limited = search_iter(test_dir, SearchCriteria::new().with_limit(3)).unwrap().count();
+    assert_eq!(limited, 3);
+    
+    let mut seen = 0;
+    let count = search_files_with(test_dir, SearchCriteria::new(), |_| {
+        seen += 1;
+        seen < 2
+    }).unwrap();
+    assert_eq!((count, seen), (2, 2));
+    
+    // 进度回调在每个目录开始读取时调用
+    let mut visited = Vec::new();
+    let iter = search_iter(test_dir, SearchCriteria::new())
+        .unwrap()
+        .with_progress(|progress| visited.push(progress.dirs_visited));
+    assert_eq!(iter.count(), 4);
+    assert_eq!(visited, vec![1, 2, 3, 4]);
+    
+    let mut iter = search_iter(test_dir, SearchCriteria::new().with_max_depth(0)).unwrap();
+    assert!(iter.next().unwrap().is_ok());
+    assert!(iter.next().is_none());
+    assert_eq!(iter.progress().dirs_visited, 1);
+    assert_eq!(iter.progress().matches, 1);
+    
+    assert!(search_iter("test_search_stream_missing", SearchCriteria::new()).is_err());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_fuzzy_scoring() {
+    let query = FuzzyQuery::new("mrs", false);
+    assert!(query.score("main.rs").is_some());
+    assert!(query.score("rsm").is_none());
+    assert_eq!(FuzzyQuery::new("", false).score("anything"), Some(0));
+    
+    // 单词开头和连续字符得分更高
+    let boundary = query.score("my_rust_src").unwrap();
+    let inside = query.score("hammers").unwrap();
+    assert!(boundary > inside);
+    
+    let query = FuzzyQuery::new("fs", false);
+    assert!(query.score("file_search").unwrap() > query.score("offset").unwrap());
+    assert!(query.score("FileSearch").unwrap() > query.score("fires").unwrap());
+    
+    // 区分大小写
+    assert!(FuzzyQuery::new("FS", true).score("file_search").is_none());
+    assert!(FuzzyQuery::new("FS", false).score("file_search").is_some());
+    
+    // 文件名的匹配优先于跨目录的匹配
+    let query = FuzzyQuery::new("main", false);
+    assert!(query.score_path("main.rs", "src/main.rs") > query.score_path("ain.rs", "src/m/ain.rs"));
+}
+
+#[test]
+fn test_fuzzy_search_ranking() {
+    let test_dir = "test_search_fuzzy_dir";
+    fs::create_dir_all(format!("{}/src/common", test_dir)).unwrap();
+    fs::write(format!("{}/src/main.rs", test_dir), "").unwrap();
+    fs::write(format!("{}/src/common/errors.txt", test_dir), "").unwrap();
+    fs::write(format!("{}/src/common/mars.md", test_dir), "").unwrap();
+    fs::write(format!("{}/README.md", test_dir), "").unwrap();
+    
+    let criteria = SearchCriteria::new().with_fuzzy(FuzzyQuery::new("mrs", false));
+    let result = search_files(test_dir, criteria).unwrap();
+    let paths: Vec<String> = result.files.iter().map(|p| result.display_path(p, PathDisplay::Relative)).collect();
+    assert_eq!(paths[0], "src/main.rs");
+    assert!(!paths.contains(&"README.md".to_string()));
+    
+    let scores: Vec<i64> = result.details.iter().map(|d| d.score.unwrap()).collect();
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+    
+    // 模糊条件与其他条件同时满足
+    let criteria = SearchCriteria::new()
+        .with_fuzzy(FuzzyQuery::new("mrs", false))
+        .with_extension("md".to_string())
+        .with_limit(1);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["src/common/mars.md"]);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_search_permission_filters() {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let test_dir = "test_search_perm_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    let script = format!("{}/run.sh", test_dir);
+    let shared = format!("{}/shared.txt", test_dir);
+    fs::write(&script, "#!/bin/sh").unwrap();
+    fs::write(&shared, "shared").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::set_permissions(&shared, fs::Permissions::from_mode(0o666)).unwrap();
+    
+    let criteria = SearchCriteria::new().with_permission(PermissionMatch::EXECUTABLE);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["run.sh"]);
+    
+    let criteria = SearchCriteria::new().with_permission(PermissionMatch::WORLD_WRITABLE);
+    let result = search_files(test_dir, criteria).unwrap();
+    assert_eq!(relative_names(&result, test_dir), vec!["shared.txt"]);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
@@ -0,0 +1,631 @@
+use rtools::{AccessLogParser, AnalyzeOptions, Compression, EntryAssembler, EntryFormat, JsonParser, LatencyStats, LogEntry, LogFilter, LogLevel,
+             LogParser, ParserRegistry, RegexParser, Rfc5424Parser, SyslogParser, analyze_log_file,
+             analyze_log_file_with, analyze_log_files, parse_level, parse_time_bound, query_log_file,
+             resolve_log_paths, write_entries, RtoolsError};
+use rtools::loganalyzer::MAX_CONTINUATION_LINES;
+use regex::Regex;
+use std::fs;
+
+/// 测试用的自定义格式: `LEVEL|message`
+struct PipeParser;
+
+impl LogParser for PipeParser {
+    fn name(&self) -> &str {
+        "pipe"
+    }
+    
+    fn parse(&self, line: &str) -> Option<LogEntry> {
+        let (level, message) = line.split_once('|')?;
+        Some(LogEntry::new(None, LogLevel::parse(level.trim()), message.trim().to_string()))
+    }
+}
+
+#[test]
+fn test_detect_builtin_formats() {
+    let registry = ParserRegistry::default();
+    assert_eq!(registry.names(), vec!["standard", "simple", "json", "syslog", "rfc5424", "combined", "common"]);
+    
+    let standard = ["[2023-01-01 12:00:00] [INFO] started", "[2023-01-01 12:00:01] [ERROR] failed"];
+    assert_eq!(registry.detect(&standard).unwrap().name(), "standard");
+    
+    let simple = ["2023-01-01 12:00:00 INFO started", "garbage", "2023-01-01 12:00:01 WARN slow"];
+    assert_eq!(registry.detect(&simple).unwrap().name(), "simple");
+    
+    assert!(registry.detect(&["no format here", ""]).is_none());
+    assert!(registry.require("nope").is_err());
+}
+
+#[test]
+fn test_custom_parser_registration() {
+    let test_file = "test_loganalyzer_custom.log";
+    fs::write(test_file, "INFO|started\nERROR|Disk Failure\nnot a pipe line\nERROR|Disk Failure\n").unwrap();
+    
+    // 内置解析器无法识别这种格式
+    let analysis = analyze_log_file(test_file).unwrap();
+    assert_eq!(analysis.format, None);
+    assert_eq!(analysis.unparsed_lines, 4);
+    
+    let mut registry = ParserRegistry::default();
+    registry.register(Box::new(PipeParser));
+    assert_eq!(registry.detect(&["INFO|x"]).unwrap().name(), "pipe");
+    
+    // 逐行统计时不能解析的行单独计数
+    let options = AnalyzeOptions::new().with_multiline(false);
+    let analysis = analyze_log_file_with(test_file, &registry, &options).unwrap();
+    assert_eq!(analysis.format.as_deref(), Some("pipe"));
+    assert_eq!(analysis.total_entries, 4);
+    assert_eq!(analysis.unparsed_lines, 1);
+    assert_eq!(analysis.level_distribution.get(&LogLevel::Error), Some(&2));
+    assert_eq!(analysis.error_patterns.get("Failure"), Some(&2));
+    
+    // 默认把不能解析的行合并到上一条目
+    let analysis = analyze_log_file_with(test_file, &registry, &AnalyzeOptions::new()).unwrap();
+    assert_eq!(analysis.total_entries, 3);
+    assert_eq!(analysis.unparsed_lines, 0);
+    assert_eq!(analysis.continuation_lines, 1);
+    
+    // 指定格式时不进行检测
+    let options = AnalyzeOptions::new().with_format("standard");
+    let analysis = analyze_log_file_with(test_file, &registry, &options).unwrap();
+    assert_eq!(analysis.unparsed_lines, 4);
+    
+    let options = AnalyzeOptions::new().with_format("missing");
+    assert!(analyze_log_file_with(test_file, &registry, &options).is_err());
+    
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_json_parser() {
+    let parser = JsonParser::new();
+    let line = r#"{"ts":"2023-01-01T12:00:00+02:00","level":"warn","msg":"slow","fields":{"service":"api","ms":120},"tags":["a"]}"#;
+    let entry = parser.parse(line).unwrap();
+    assert_eq!(entry.timestamp.unwrap().to_rfc3339(), "2023-01-01T10:00:00+00:00");
+    assert_eq!(entry.level, LogLevel::Warning);
+    assert_eq!(entry.message, "slow");
+    assert_eq!(entry.fields.get("fields.service").map(String::as_str), Some("api"));
+    assert_eq!(entry.fields.get("fields.ms").map(String::as_str), Some("120"));
+    assert_eq!(entry.fields.get("tags").map(String::as_str), Some(r#"["a"]"#));
+    assert_eq!(entry.field("service"), Some("api"));
+    assert!(!entry.fields.contains_key("ts"));
+    
+    // 数字级别和毫秒时间戳
+    let entry = parser.parse(r#"{"time":1672574400000,"level":50,"message":"boom"}"#).unwrap();
+    assert_eq!(entry.level, LogLevel::Error);
+    assert_eq!(entry.timestamp.unwrap().to_rfc3339(), "2023-01-01T12:00:00+00:00");
+    
+    assert!(parser.parse("[2023-01-01 12:00:00] [INFO] x").is_none());
+    assert!(parser.parse("{not json").is_none());
+    
+    // 自定义字段名
+    let parser = JsonParser::new().with_message_field("text").with_level_field("sev");
+    let entry = parser.parse(r#"{"sev":"error","text":"custom","msg":"extra"}"#).unwrap();
+    assert_eq!(entry.level, LogLevel::Error);
+    assert_eq!(entry.message, "custom");
+    assert_eq!(entry.field("msg"), Some("extra"));
+}
+
+#[test]
+fn test_json_group_by() {
+    let test_file = "test_loganalyzer_json.log";
+    let lines = [
+        r#"{"ts":"2023-01-01T12:00:00Z","level":"info","msg":"ok","fields":{"service":"api"}}"#,
+        r#"{"ts":"2023-01-01T12:00:01Z","level":"error","msg":"Timeout","fields":{"service":"api"}}"#,
+        r#"{"ts":"2023-01-01T12:00:02Z","level":"info","msg":"ok","fields":{"service":"db"}}"#,
+        r#"{"ts":"2023-01-01T12:00:03Z","level":"info","msg":"no service"}"#,
+    ];
+    fs::write(test_file, lines.join("\n")).unwrap();
+    
+    let options = AnalyzeOptions::new().with_group_by("service");
+    let analysis = analyze_log_file_with(test_file, &ParserRegistry::default(), &options).unwrap();
+    assert_eq!(analysis.format.as_deref(), Some("json"));
+    assert_eq!(analysis.unparsed_lines, 0);
+    assert_eq!(analysis.group_field.as_deref(), Some("service"));
+    
+    let groups: Vec<(Option<&str>, usize, usize)> = analysis
+        .groups
+        .iter()
+        .map(|g| (g.key.as_deref(), g.count, g.errors))
+        .collect();
+    // 条目数相同时按字段值排序，没有该字段的条目排在前面
+    assert_eq!(groups, vec![(Some("api"), 2, 1), (None, 1, 0), (Some("db"), 1, 0)]);
+    
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_syslog_parsers() {
+    let parser = SyslogParser::new().with_year(2023);
+    let entry = parser.parse("<34>Oct 11 22:14:15 mymachine su[123]: 'su root' failed for lonvick").unwrap();
+    assert_eq!(entry.timestamp.unwrap().to_rfc3339(), "2023-10-11T22:14:15+00:00");
+    assert_eq!(entry.level, LogLevel::Critical);
+    assert_eq!(entry.source.as_deref(), Some("mymachine/su"));
+    assert_eq!(entry.field("facility"), Some("auth"));
+    assert_eq!(entry.field("procid"), Some("123"));
+    assert_eq!(entry.message, "'su root' failed for lonvick");
+    
+    // /var/log 中没有优先级，日期用空格补齐
+    let entry = parser.parse("Oct  1 08:00:01 web01 CRON[42]: (root) CMD (run-parts)").unwrap();
+    assert_eq!(entry.timestamp.unwrap().to_rfc3339(), "2023-10-01T08:00:01+00:00");
+    assert_eq!(entry.level, LogLevel::Unknown(String::new()));
+    assert_eq!(entry.field("app"), Some("CRON"));
+    assert_eq!(entry.field("facility"), None);
+    
+    assert!(parser.parse("2023-01-01 12:00:00 INFO started").is_none());
+    
+    let parser = Rfc5424Parser;
+    let line = r#"<165>1 2003-10-11T22:14:15.003Z host.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventID="10\"11"] An application event"#;
+    let entry = parser.parse(line).unwrap();
+    assert_eq!(entry.level, LogLevel::Info);
+    assert_eq!(entry.field("facility"), Some("local4"));
+    assert_eq!(entry.source.as_deref(), Some("host.example.com/evntslog"));
+    assert_eq!(entry.field("msgid"), Some("ID47"));
+    assert_eq!(entry.field("procid"), None);
+    assert_eq!(entry.field("exampleSDID@32473.eventID"), Some("10\"11"));
+    assert_eq!(entry.field("iut"), Some("3"));
+    assert_eq!(entry.message, "An application event");
+    
+    let entry = parser.parse("<11>1 - - - - - -").unwrap();
+    assert_eq!(entry.level, LogLevel::Error);
+    assert!(entry.timestamp.is_none());
+    assert!(entry.source.is_none());
+    assert!(parser.parse("<11>1 - - - - - [broken").is_none());
+}
+
+#[test]
+fn test_syslog_distributions() {
+    let test_file = "test_loganalyzer_syslog.log";
+    let lines = [
+        "<86>1 2023-01-01T12:00:00Z web01 sshd 100 - - Accepted publickey",
+        "<83>1 2023-01-01T12:00:01Z web01 sshd 101 - - Connection Refused",
+        "<30>1 2023-01-01T12:00:02Z db01 systemd 1 - - Started job",
+    ];
+    fs::write(test_file, lines.join("\n")).unwrap();
+    
+    let analysis = analyze_log_file(test_file).unwrap();
+    assert_eq!(analysis.format.as_deref(), Some("rfc5424"));
+    assert_eq!(analysis.facility_distribution.get("authpriv"), Some(&2));
+    assert_eq!(analysis.facility_distribution.get("daemon"), Some(&1));
+    assert_eq!(analysis.host_distribution.get("web01"), Some(&2));
+    
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_access_log_parser() {
+    let parser = AccessLogParser::combined();
+    let line = r#"203.0.113.9 - frank [10/Oct/2023:13:55:36 -0700] "GET /index.html?x=1 HTTP/1.1" 200 2326 "http://example.com/" "Mozilla/5.0""#;
+    let entry = parser.parse(line).unwrap();
+    assert_eq!(entry.timestamp.unwrap().to_rfc3339(), "2023-10-10T20:55:36+00:00");
+    assert_eq!(entry.level, LogLevel::Info);
+    assert_eq!(entry.message, "GET /index.html?x=1 HTTP/1.1");
+    assert_eq!(entry.source.as_deref(), Some("203.0.113.9"));
+    assert_eq!(entry.field("request_uri"), Some("/index.html?x=1"));
+    assert_eq!(entry.field("http_user_agent"), Some("Mozilla/5.0"));
+    
+    // 组合格式的行不是通用格式，反之亦然
+    assert!(AccessLogParser::common().parse(line).is_none());
+    let common = r#"10.0.0.1 - - [10/Oct/2023:13:55:36 +0000] "POST /api HTTP/1.1" 503 -"#;
+    assert!(parser.parse(common).is_none());
+    let entry = AccessLogParser::common().parse(common).unwrap();
+    assert_eq!(entry.level, LogLevel::Error);
+    assert_eq!(entry.field("remote_user"), None);
+    assert_eq!(entry.field("body_bytes_sent"), None);
+    
+    let custom = AccessLogParser::new("nginx", "$remote_addr ${status} $request_time \"$request\"").unwrap();
+    assert_eq!(custom.variables(), ["remote_addr", "status", "request_time", "request"]);
+    let entry = custom.parse(r#"10.0.0.2 404 0.012 "GET /missing HTTP/2.0""#).unwrap();
+    assert_eq!(entry.level, LogLevel::Warning);
+    assert_eq!(entry.field("request_time"), Some("0.012"));
+    
+    assert!(AccessLogParser::new("bad", "no variables").is_err());
+    assert!(AccessLogParser::new("bad", "${status").is_err());
+}
+
+#[test]
+fn test_access_log_analytics() {
+    let test_file = "test_loganalyzer_access.log";
+    let lines = [
+        r#"10.0.0.1 - - [10/Oct/2023:13:55:01 +0000] "GET / HTTP/1.1" 200 100 "-" "curl/8.0""#,
+        r#"10.0.0.1 - - [10/Oct/2023:13:55:20 +0000] "GET /api?id=1 HTTP/1.1" 200 250 "-" "curl/8.0""#,
+        r#"10.0.0.2 - - [10/Oct/2023:13:55:40 +0000] "GET /api?id=2 HTTP/1.1" 404 0 "-" "Mozilla/5.0""#,
+        r#"10.0.0.3 - - [10/Oct/2023:13:56:10 +0000] "POST /api HTTP/1.1" 500 50 "-" "curl/8.0""#,
+    ];
+    fs::write(test_file, lines.join("\n")).unwrap();
+    
+    let analysis = analyze_log_file(test_file).unwrap();
+    assert_eq!(analysis.format.as_deref(), Some("combined"));
+    let access = analysis.access.unwrap();
+    assert_eq!(access.total_requests, 4);
+    assert_eq!(access.status_classes.get("2xx"), Some(&2));
+    assert_eq!(access.status_classes.get("5xx"), Some(&1));
+    assert_eq!(access.top_paths[0], ("/api".to_string(), 3));
+    assert_eq!(access.top_clients[0], ("10.0.0.1".to_string(), 2));
+    assert_eq!(access.top_user_agents[0], ("curl/8.0".to_string(), 3));
+    assert_eq!(access.bytes_served, 400);
+    assert_eq!(access.peak_minute(), Some(("2023-10-10 13:55", 3)));
+    assert!(access.latency.is_none());
+    
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_latency_percentiles() {
+    let samples: Vec<f64> = (1..=100).map(f64::from).collect();
+    let latency = LatencyStats::from_samples(samples).unwrap();
+    assert_eq!(latency.samples, 100);
+    assert_eq!(latency.p50, 50.0);
+    assert_eq!(latency.p90, 90.0);
+    assert_eq!(latency.p99, 99.0);
+    assert_eq!(latency.max, 100.0);
+    assert!(LatencyStats::from_samples(Vec::new()).is_none());
+}
+
+#[test]
+fn test_regex_parser() {
+    let parser = RegexParser::new(
+        "inhouse",
+        r"^(?P<timestamp>\S+ \S+) \| (?P<level>\w+) \| (?P<thread>\S+) \| (?P<message>.*)$",
+    )
+    .unwrap()
+    .with_timestamp_format("%d.%m.%Y %H:%M:%S");
+    
+    let entry = parser.parse("01.02.2023 08:30:00 | WARN | worker-3 | queue is full").unwrap();
+    assert_eq!(entry.timestamp.unwrap().to_rfc3339(), "2023-02-01T08:30:00+00:00");
+    assert_eq!(entry.level, LogLevel::Warning);
+    assert_eq!(entry.message, "queue is full");
+    assert_eq!(entry.field("thread"), Some("worker-3"));
+    assert!(parser.parse("something else").is_none());
+    
+    // 没有 message 分组时整行作为消息
+    let parser = RegexParser::new("bare", r"^(?P<source>\w+):").unwrap();
+    let entry = parser.parse("db: slow query").unwrap();
+    assert_eq!(entry.source.as_deref(), Some("db"));
+    assert_eq!(entry.message, "db: slow query");
+}
+
+#[test]
+fn test_regex_parser_errors() {
+    let error = RegexParser::new("bad", r"^(?P<level>\w+ (abc").unwrap_err().to_string();
+    assert!(error.starts_with("解析错误: 正则表达式位置 "), "{}", error);
+    
+    let error = RegexParser::new("bad", r"^\w+ [a-").unwrap_err().to_string();
+    assert!(error.contains("位置 5"), "{}", error);
+    
+    let error = RegexParser::new("bad", r"^(\w+) (.*)$").unwrap_err().to_string();
+    assert!(error.contains("命名分组"), "{}", error);
+}
+
+#[test]
+fn test_regex_formats_file() {
+    let toml_file = "test_loganalyzer_formats.toml";
+    fs::write(toml_file, concat!(
+        "[pipe]\n",
+        "pattern = '^(?P<level>\\w+)\\|(?P<message>.*)$'\n",
+        "\n",
+        "[dated]\n",
+        "pattern = '^(?P<timestamp>\\S+) (?P<message>.*)$'\n",
+        "timestamp_format = \"%Y/%m/%dT%H:%M\"\n",
+    )).unwrap();
+    
+    let parsers = RegexParser::load_from_file(toml_file).unwrap();
+    let names: Vec<&str> = parsers.iter().map(|p| p.name()).collect();
+    assert_eq!(names, vec!["dated", "pipe"]);
+    let entry = parsers[0].parse("2023/05/06T07:08 hello").unwrap();
+    assert_eq!(entry.timestamp.unwrap().to_rfc3339(), "2023-05-06T07:08:00+00:00");
+    
+    let ini_file = "test_loganalyzer_formats.ini";
+    fs::write(ini_file, "[pipe]\npattern = ^(?P<level>\\w+)\\|(?P<message>.*)$\n").unwrap();
+    let parsers = RegexParser::load_from_file(ini_file).unwrap();
+    assert_eq!(parsers.len(), 1);
+    assert_eq!(parsers[0].parse("ERROR|boom").unwrap().level, LogLevel::Error);
+    
+    fs::write(ini_file, "[broken]\npattern = ^(?P<level>\n").unwrap();
+    match RegexParser::load_from_file(ini_file) {
+        Err(RtoolsError::ParseError(message)) => {
+            assert!(message.contains(ini_file) && message.contains("broken"), "{}", message);
+            assert!(message.contains("正则表达式位置 1: unclosed group"), "{}", message);
+        }
+        other => panic!("应当是解析错误: {:?}", other.map(|parsers| parsers.len())),
+    }
+    
+    fs::remove_file(toml_file).unwrap();
+    fs::remove_file(ini_file).unwrap();
+}
+
+#[test]
+fn test_multiline_stack_traces() {
+    let test_file = "test_loganalyzer_multiline.log";
+    let content = "\
+garbage before the first entry
+  indented garbage
+2023-01-01 12:00:00 INFO starting
+2023-01-01 12:00:01 ERROR Request failed
+java.lang.IllegalStateException: boom
+\tat com.example.Service.handle(Service.java:42)
+\tat com.example.Main.main(Main.java:10)
+Caused by: java.io.IOException: disk full
+\t... 2 more
+
+2023-01-01 12:00:02 ERROR thread 'main' panicked
+stack backtrace:
+   0: rust_begin_unwind
+2023-01-01 12:00:03 INFO done
+";
+    fs::write(test_file, content).unwrap();
+    
+    let analysis = analyze_log_file(test_file).unwrap();
+    assert_eq!(analysis.format.as_deref(), Some("simple"));
+    // 第一个条目之前的行不属于任何条目，仍然单独统计
+    assert_eq!(analysis.total_entries, 6);
+    assert_eq!(analysis.unparsed_lines, 2);
+    assert_eq!(analysis.multiline_entries, 2);
+    assert_eq!(analysis.continuation_lines, 7);
+    assert_eq!(analysis.level_distribution.get(&LogLevel::Error), Some(&2));
+    
+    let lines: Vec<&str> = content.lines().collect();
+    let registry = ParserRegistry::default();
+    let options = AnalyzeOptions::new().with_multiline(false);
+    let analysis = rtools::loganalyzer::analyze_lines(&lines, registry.get("simple"), &options);
+    assert_eq!(analysis.total_entries, 14);
+    assert_eq!(analysis.continuation_lines, 0);
+    
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_multiline_start_pattern() {
+    let lines = [
+        "#1 job started",
+        "step one",
+        "step two",
+        "#2 job failed",
+        "Caused by: timeout",
+    ];
+    let options = AnalyzeOptions::new().with_start_pattern(Regex::new(r"^#\d+ ").unwrap());
+    let analysis = rtools::loganalyzer::analyze_lines(&lines, None, &options);
+    assert_eq!(analysis.total_entries, 2);
+    assert_eq!(analysis.continuation_lines, 3);
+    
+    let entry = LogEntry::new(None, LogLevel::Info, "head".to_string());
+    assert_eq!(entry.full_text(), "head");
+}
+
+#[test]
+fn test_multiline_indented_entry() {
+    // 能被解析的缩进行是独立的条目，不能被解析的缩进行才是后续行
+    let lines = [
+        "[2023-01-01 12:00:00] [INFO] started",
+        "  [2023-01-01 12:00:01] [ERROR] indented failure",
+        "  at main.rs:10",
+    ];
+    let registry = ParserRegistry::default();
+    let analysis = rtools::loganalyzer::analyze_lines(&lines, registry.get("standard"), &AnalyzeOptions::new());
+    assert_eq!(analysis.total_entries, 2);
+    assert_eq!(analysis.continuation_lines, 1);
+    
+    // 匹配起始模式的缩进行也是新条目
+    let lines = ["#1 job started", "  #2 nested job", "  step one"];
+    let options = AnalyzeOptions::new().with_start_pattern(Regex::new(r"^\s*#\d+ ").unwrap());
+    let analysis = rtools::loganalyzer::analyze_lines(&lines, None, &options);
+    assert_eq!(analysis.total_entries, 2);
+    assert_eq!(analysis.continuation_lines, 1);
+}
+
+#[test]
+fn test_multiline_continuation_limit() {
+    // 格式变化后其余的行都是后续行，条目只保存前面的一部分
+    let options = AnalyzeOptions::new();
+    let registry = ParserRegistry::default();
+    let mut assembler = EntryAssembler::new(registry.get("simple"), &options);
+    assert!(assembler.push("2023-01-01 12:00:00 INFO starting").is_none());
+    for i in 0..MAX_CONTINUATION_LINES + 500 {
+        assert!(assembler.push(&format!("garbage {}", i)).is_none());
+    }
+    
+    let entry = assembler.finish().unwrap();
+    assert_eq!(entry.continuation.len(), MAX_CONTINUATION_LINES);
+    assert_eq!(entry.omitted_lines, 500);
+    assert_eq!(assembler.continuation_lines(), MAX_CONTINUATION_LINES + 500);
+    assert!(entry.original_text().ends_with("... (省略 500 行)"));
+}
+
+#[test]
+fn test_log_filter() {
+    let mut entry = LogEntry::new(parse_time_bound("2023-01-01 12:00").ok(), LogLevel::Warning, "disk slow".to_string());
+    entry.source = Some("web01/app".to_string());
+    entry.fields.insert("fields.service".to_string(), "api".to_string());
+    entry.continuation.push("  at io::write".to_string());
+    
+    assert!(LogFilter::new().is_empty());
+    assert!(LogFilter::new().matches(&entry));
+    assert!(LogFilter::new().with_min_level(LogLevel::Info).matches(&entry));
+    assert!(!LogFilter::new().with_min_level(LogLevel::Error).matches(&entry));
+    
+    let since = parse_time_bound("2023-01-01").unwrap();
+    let until = parse_time_bound("2023-01-01T11:59:59Z").unwrap();
+    assert!(LogFilter::new().with_since(since).matches(&entry));
+    assert!(!LogFilter::new().with_until(until).matches(&entry));
+    
+    // 正则表达式也在后续行中搜索
+    assert!(LogFilter::new().with_pattern(Regex::new("io::write").unwrap()).matches(&entry));
+    assert!(!LogFilter::new().with_pattern(Regex::new("^fast").unwrap()).matches(&entry));
+    
+    assert!(LogFilter::new().with_source("web01").matches(&entry));
+    assert!(!LogFilter::new().with_source("db01").matches(&entry));
+    assert!(LogFilter::new().with_field("service", "api").matches(&entry));
+    assert!(!LogFilter::new().with_field("service", "db").with_source("web01").matches(&entry));
+    
+    // 未知级别和没有时间戳的条目
+    let unknown = LogEntry::unparsed("???");
+    assert!(LogFilter::new().with_min_level(LogLevel::Debug).matches(&unknown));
+    assert!(!LogFilter::new().with_min_level(LogLevel::Info).matches(&unknown));
+    assert!(!LogFilter::new().with_since(since).matches(&unknown));
+}
+
+#[test]
+fn test_parse_filter_arguments() {
+    assert_eq!(parse_level("warn").unwrap(), LogLevel::Warning);
+    assert!(parse_level("loud").is_err());
+    
+    let one_hour_ago = parse_time_bound("1h").unwrap();
+    let age = chrono::Utc::now() - one_hour_ago;
+    assert!((3599..=3601).contains(&age.num_seconds()));
+    
+    assert_eq!(parse_time_bound("2023-05-06 07:08").unwrap().to_rfc3339(), "2023-05-06T07:08:00+00:00");
+    assert_eq!(parse_time_bound("2023-05-06T07:08:09+02:00").unwrap().to_rfc3339(), "2023-05-06T05:08:09+00:00");
+    assert!(parse_time_bound("yesterday").is_err());
+    assert!(parse_time_bound("5q").is_err());
+    
+    assert_eq!(EntryFormat::parse("json"), Some(EntryFormat::Json));
+    assert_eq!(EntryFormat::parse("xml"), None);
+}
+
+#[test]
+fn test_query_log_file() {
+    let test_file = "test_loganalyzer_query.log";
+    fs::write(test_file, concat!(
+        "2023-01-01 12:00:00 INFO start\n",
+        "2023-01-01 12:00:01 ERROR Request failed\n",
+        "\tat Foo.bar(Foo.java:1)\n",
+        "2023-01-01 13:00:00 WARN slow\n",
+    )).unwrap();
+    
+    let filter = LogFilter::new().with_min_level(LogLevel::Warning);
+    let options = AnalyzeOptions::new().with_filter(filter);
+    let entries = query_log_file(test_file, &ParserRegistry::default(), &options).unwrap();
+    assert_eq!(entries.len(), 2);
+    
+    let mut output = Vec::new();
+    write_entries(&mut output, &entries, EntryFormat::Text).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), concat!(
+        "2023-01-01 12:00:01 ERROR Request failed\n",
+        "\tat Foo.bar(Foo.java:1)\n",
+        "2023-01-01 13:00:00 WARN slow\n",
+    ));
+    
+    let mut output = Vec::new();
+    write_entries(&mut output, &entries[..1], EntryFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["level"], "ERROR");
+    assert_eq!(json["line"], 2);
+    assert_eq!(json["timestamp"], "2023-01-01T12:00:01+00:00");
+    assert_eq!(json["continuation"][0], "\tat Foo.bar(Foo.java:1)");
+    
+    // 分析时也只统计匹配的条目
+    let analysis = analyze_log_file_with(test_file, &ParserRegistry::default(), &options).unwrap();
+    assert_eq!(analysis.total_entries, 2);
+    assert_eq!(analysis.filtered_entries, 1);
+    
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_parallel_analysis_matches_serial() {
+    let test_file = "test_loganalyzer_parallel.log";
+    let mut content = String::new();
+    for i in 0..2000 {
+        let level = ["INFO", "WARN", "ERROR"][i % 3];
+        content.push_str(&format!("2023-01-01 12:{:02}:{:02} {} request {} Failure\n", i / 60 % 60, i % 60, level, i % 7));
+        // 多行条目可能跨越分块边界
+        if i % 5 == 0 {
+            content.push_str("\tat Foo.bar(Foo.java:1)\n\tat Foo.main(Foo.java:2)\n");
+        }
+    }
+    content.push_str("not a log line\n");
+    fs::write(test_file, &content).unwrap();
+    
+    let registry = ParserRegistry::default();
+    let serial = analyze_log_file_with(test_file, &registry, &AnalyzeOptions::new()).unwrap();
+    let parallel = analyze_log_file_with(test_file, &registry, &AnalyzeOptions::new().with_jobs(4)).unwrap();
+    
+    assert_eq!(serial.total_entries, 2000);
+    assert_eq!(serial.multiline_entries, 401);
+    assert_eq!(serial.continuation_lines, 801);
+    assert_eq!(parallel.format, serial.format);
+    assert_eq!(parallel.total_entries, serial.total_entries);
+    assert_eq!(parallel.multiline_entries, serial.multiline_entries);
+    assert_eq!(parallel.continuation_lines, serial.continuation_lines);
+    assert_eq!(parallel.unparsed_lines, serial.unparsed_lines);
+    assert_eq!(parallel.level_distribution, serial.level_distribution);
+    assert_eq!(parallel.time_distribution, serial.time_distribution);
+    assert_eq!(parallel.error_patterns, serial.error_patterns);
+    assert_eq!(parallel.top_messages, serial.top_messages);
+    assert_eq!(parallel.time_range, serial.time_range);
+    assert_eq!(parallel.distinct_messages, 7);
+    
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_bounded_top_messages() {
+    let test_file = "test_loganalyzer_topk.log";
+    let mut content = String::new();
+    for i in 0..1000 {
+        if i % 4 == 0 {
+            content.push_str("2023-01-01 12:00:00 ERROR Disk full\n");
+        } else {
+            content.push_str(&format!("2023-01-01 12:00:00 INFO request {}\n", i));
+        }
+    }
+    fs::write(test_file, &content).unwrap();
+    
+    let options = AnalyzeOptions::new().with_top_k(10);
+    let analysis = analyze_log_file_with(test_file, &ParserRegistry::default(), &options).unwrap();
+    assert_eq!(analysis.total_entries, 1000);
+    assert_eq!(analysis.top_messages.len(), 10);
+    assert_eq!(analysis.top_messages[0].0, "Disk full");
+    assert!(analysis.top_messages[0].1 >= 250);
+    assert!(analysis.approximate);
+    // 不同消息数的估计误差很小
+    assert!(analysis.distinct_messages.abs_diff(751) < 20, "估计值 {}", analysis.distinct_messages);
+    
+    let analysis = analyze_log_file_with(test_file, &ParserRegistry::default(), &AnalyzeOptions::new()).unwrap();
+    assert!(!analysis.approximate);
+    assert_eq!(analysis.top_messages[0], ("Disk full".to_string(), 250));
+    
+    fs::remove_file(test_file).unwrap();
+}
+
+#[test]
+fn test_analyze_rotated_files() {
+    let test_dir = "test_loganalyzer_rotated";
+    fs::create_dir_all(test_dir).unwrap();
+    fs::write(format!("{}/app.log", test_dir), "2023-01-03 12:00:00 INFO current\n").unwrap();
+    fs::write(format!("{}/app.log.1", test_dir), "2023-01-02 12:00:00 ERROR Disk Failure\n2023-01-02 12:00:01 INFO ok\n").unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(
+        fs::File::create(format!("{}/app.log.2.gz", test_dir)).unwrap(),
+        flate2::Compression::default(),
+    );
+    std::io::Write::write_all(&mut encoder, b"2023-01-01 12:00:00 ERROR Disk Failure\n").unwrap();
+    encoder.finish().unwrap();
+    
+    let paths = resolve_log_paths(&[format!("{}/app.log*", test_dir)]).unwrap();
+    let analysis = analyze_log_files(&paths, &ParserRegistry::default(), &AnalyzeOptions::new()).unwrap();
+    assert_eq!(analysis.format.as_deref(), Some("simple"));
+    assert_eq!(analysis.total_entries, 4);
+    assert_eq!(analysis.error_patterns.get("Failure"), Some(&2));
+    assert_eq!(analysis.top_messages[0], ("Disk Failure".to_string(), 2));
+    
+    // 按从旧到新的顺序列出各文件
+    let files: Vec<(String, usize, usize, Compression)> = analysis
+        .files
+        .iter()
+        .map(|f| (f.path.file_name().unwrap().to_string_lossy().into_owned(), f.total_entries, f.errors, f.compression))
+        .collect();
+    assert_eq!(files, vec![
+        ("app.log.2.gz".to_string(), 1, 1, Compression::Gzip),
+        ("app.log.1".to_string(), 2, 1, Compression::None),
+        ("app.log".to_string(), 1, 0, Compression::None),
+    ]);
+    let (start, end) = analysis.time_range.unwrap();
+    assert_eq!(start, analysis.files[0].time_range.unwrap().0);
+    assert_eq!(end, analysis.files[2].time_range.unwrap().1);
+    
+    // 压缩的文件不分块，也能用 --jobs 分析
+    let options = AnalyzeOptions::new().with_jobs(4);
+    let parallel = analyze_log_files(&paths, &ParserRegistry::default(), &options).unwrap();
+    assert_eq!(parallel.total_entries, 4);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
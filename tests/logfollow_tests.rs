@@ -0,0 +1,148 @@
+use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+use rtools::{AnalyzeOptions, EntryFollower, FileFollower, LogLevel, ParserRegistry, RollingStats};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time::Duration;
+
+fn append(path: &str, text: &str) {
+    let mut file = OpenOptions::new().append(true).create(true).open(path).unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
+
+#[test]
+fn test_follow_appends_and_partial_lines() {
+    let test_dir = "test_logfollow_append_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    let path = format!("{}/app.log", test_dir);
+    fs::write(&path, "existing line\n").unwrap();
+    
+    // 默认从末尾开始，已有的内容不会输出
+    let mut follower = FileFollower::new(&path);
+    assert!(follower.read_lines().unwrap().is_empty());
+    
+    append(&path, "first\nsecond\r\nthi");
+    assert_eq!(follower.read_lines().unwrap(), vec!["first", "second"]);
+    
+    // 不完整的行留到写完换行符之后
+    assert!(follower.read_lines().unwrap().is_empty());
+    append(&path, "rd\n");
+    assert_eq!(follower.read_lines().unwrap(), vec!["third"]);
+    
+    let mut from_start = FileFollower::from_start(&path);
+    assert_eq!(from_start.read_lines().unwrap().len(), 4);
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_follow_truncation_and_rotation() {
+    let test_dir = "test_logfollow_rotate_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    let path = format!("{}/app.log", test_dir);
+    let rotated = format!("{}/app.log.1", test_dir);
+    
+    // 文件还不存在时等待它被创建，之后从头读取
+    let mut follower = FileFollower::new(&path);
+    assert!(follower.read_lines().unwrap().is_empty());
+    fs::write(&path, "created\n").unwrap();
+    assert_eq!(follower.read_lines().unwrap(), vec!["created"]);
+    
+    // 截断后从头读取
+    fs::write(&path, "").unwrap();
+    assert!(follower.read_lines().unwrap().is_empty());
+    append(&path, "after truncate\n");
+    assert_eq!(follower.read_lines().unwrap(), vec!["after truncate"]);
+    
+    // 轮转: 先读完旧文件剩余的内容，再读新文件
+    fs::rename(&path, &rotated).unwrap();
+    append(&rotated, "last old line\n");
+    assert_eq!(follower.read_lines().unwrap(), vec!["last old line"]);
+    fs::write(&path, "new file line\n").unwrap();
+    if cfg!(unix) {
+        assert_eq!(follower.read_lines().unwrap(), vec!["new file line"]);
+    }
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_follow_entries_detects_format_later() {
+    let test_dir = "test_logfollow_entries_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    let path = format!("{}/app.log", test_dir);
+    let registry = ParserRegistry::default();
+    let options = AnalyzeOptions::new();
+    
+    // 文件还不存在时格式未确定，之后用最先读到的行检测
+    let mut follower = EntryFollower::new(&path, &registry, &options).unwrap();
+    assert!(!follower.is_resolved());
+    assert!(follower.poll().unwrap().is_empty());
+    assert!(follower.is_idle());
+    assert!(!follower.is_resolved());
+    
+    append(&path, "[2023-01-01 12:00:00] [INFO] started\n[2023-01-01 12:00:01] [INFO] ready\n");
+    let entries = follower.poll().unwrap();
+    assert!(follower.is_resolved());
+    assert_eq!(follower.parser().map(|p| p.name()), Some("standard"));
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].message, "started");
+    
+    // 没有新的行时输出最后一个条目
+    let entries = follower.poll().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].message, "ready");
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_follow_entries_invalid_utf8_sample() {
+    let test_dir = "test_logfollow_utf8_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    let path = format!("{}/app.log", test_dir);
+    fs::write(&path, b"[2023-01-01 12:00:00] [INFO] caf\xe9\n[2023-01-01 12:00:01] [INFO] ok\n").unwrap();
+    
+    // 开头的无效UTF-8不影响检测格式
+    let registry = ParserRegistry::default();
+    let options = AnalyzeOptions::new();
+    let follower = EntryFollower::new(&path, &registry, &options).unwrap();
+    assert!(follower.is_resolved());
+    assert_eq!(follower.parser().map(|p| p.name()), Some("standard"));
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_rolling_stats() {
+    let mut stats = RollingStats::new(Duration::from_secs(5 * 60));
+    assert_eq!(stats.error_rate(), 0.0);
+    assert_eq!(stats.summary(), "最近 5 分钟: 0 条");
+    
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 12, 0, 0).unwrap();
+    stats.add_at(start, LogLevel::Error);
+    stats.add_at(start + ChronoDuration::minutes(2), LogLevel::Info);
+    stats.add_at(start + ChronoDuration::minutes(4), LogLevel::Info);
+    stats.add_at(start + ChronoDuration::minutes(4), LogLevel::Critical);
+    
+    stats.prune(start + ChronoDuration::minutes(4));
+    assert_eq!(stats.total(), 4);
+    assert_eq!(stats.error_rate(), 0.5);
+    assert_eq!(stats.level_counts().get(&LogLevel::Info), Some(&2));
+    
+    // 5分钟后第一个条目移出窗口
+    stats.prune(start + ChronoDuration::minutes(5));
+    assert_eq!(stats.total(), 3);
+    assert_eq!(stats.level_counts().get(&LogLevel::Error), None);
+    assert_eq!(stats.summary(), "最近 5 分钟: 3 条 (INFO 2, CRITICAL 1), 错误率 33.3%");
+    
+    // 同一秒内的条目合并到一个桶中，整个桶一起移出窗口
+    let later = start + ChronoDuration::minutes(10);
+    for _ in 0..1000 {
+        stats.add_at(later, LogLevel::Warning);
+    }
+    stats.prune(later);
+    assert_eq!(stats.total(), 1000);
+    stats.prune(later + ChronoDuration::minutes(5));
+    assert_eq!(stats.total(), 0);
+    assert!(stats.level_counts().is_empty());
+}
@@ -0,0 +1,108 @@
+use rtools::{Compression, open_log_file, resolve_log_paths, sort_by_rotation};
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+const CONTENT: &str = "2023-01-01 12:00:00 INFO started\n2023-01-01 12:00:01 ERROR failed\n";
+
+fn read_lines(path: &str) -> Vec<String> {
+    open_log_file(path).unwrap().lines().collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn test_open_compressed_logs() {
+    let test_dir = "test_logsource_compressed";
+    fs::create_dir_all(test_dir).unwrap();
+    
+    let plain = format!("{}/app.log", test_dir);
+    fs::write(&plain, CONTENT).unwrap();
+    
+    let gzip = format!("{}/app.log.1.gz", test_dir);
+    let mut encoder = flate2::write::GzEncoder::new(fs::File::create(&gzip).unwrap(), flate2::Compression::default());
+    encoder.write_all(CONTENT.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    
+    let zstd = format!("{}/app.log.2.zst", test_dir);
+    fs::write(&zstd, zstd::encode_all(CONTENT.as_bytes(), 3).unwrap()).unwrap();
+    
+    // 扩展名不影响识别
+    let bzip2 = format!("{}/app.log.3", test_dir);
+    let mut encoder = bzip2::write::BzEncoder::new(fs::File::create(&bzip2).unwrap(), bzip2::Compression::default());
+    encoder.write_all(CONTENT.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    
+    assert_eq!(Compression::of_file(&plain).unwrap(), Compression::None);
+    assert_eq!(Compression::of_file(&gzip).unwrap(), Compression::Gzip);
+    assert_eq!(Compression::of_file(&zstd).unwrap(), Compression::Zstd);
+    assert_eq!(Compression::of_file(&bzip2).unwrap(), Compression::Bzip2);
+    
+    let expected = vec!["2023-01-01 12:00:00 INFO started", "2023-01-01 12:00:01 ERROR failed"];
+    for path in [&plain, &gzip, &zstd, &bzip2] {
+        assert_eq!(read_lines(path), expected, "{}", path);
+    }
+    
+    // 空文件不是压缩文件
+    let empty = format!("{}/empty.log", test_dir);
+    fs::write(&empty, "").unwrap();
+    assert_eq!(Compression::of_file(&empty).unwrap(), Compression::None);
+    assert!(open_log_file(format!("{}/missing.log", test_dir)).is_err());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_sort_by_rotation() {
+    let mut paths: Vec<PathBuf> = [
+        "logs/app.log",
+        "logs/app.log.1",
+        "logs/app.log.10.gz",
+        "logs/app.log.2.gz",
+        "logs/db.log",
+        "logs/db.log-20230102.zst",
+        "logs/db.log-20230101.gz",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    paths.reverse();
+    sort_by_rotation(&mut paths);
+    
+    let names: Vec<&str> = paths.iter().map(|p| p.to_str().unwrap()).collect();
+    assert_eq!(names, vec![
+        "logs/app.log.10.gz",
+        "logs/app.log.2.gz",
+        "logs/app.log.1",
+        "logs/app.log",
+        "logs/db.log-20230101.gz",
+        "logs/db.log-20230102.zst",
+        "logs/db.log",
+    ]);
+}
+
+#[test]
+fn test_resolve_log_paths() {
+    let test_dir = "test_logsource_resolve";
+    fs::create_dir_all(format!("{}/sub", test_dir)).unwrap();
+    for name in ["app.log", "app.log.1", "app.log.2.gz", "other.txt", ".hidden"] {
+        fs::write(format!("{}/{}", test_dir, name), "x\n").unwrap();
+    }
+    fs::write(format!("{}/sub/nested.log", test_dir), "x\n").unwrap();
+    
+    // 目录展开为其中的文件，不递归，跳过隐藏文件
+    let paths = resolve_log_paths(&[test_dir]).unwrap();
+    let names: Vec<String> = paths.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+    assert_eq!(names, vec!["app.log.2.gz", "app.log.1", "app.log", "other.txt"]);
+    
+    // 通配符和重复的文件
+    let glob = format!("{}/app.log*", test_dir);
+    let single = format!("{}/app.log", test_dir);
+    let paths = resolve_log_paths(&[glob.as_str(), single.as_str()]).unwrap();
+    assert_eq!(paths.len(), 3);
+    assert_eq!(paths[2], PathBuf::from(&single));
+    
+    assert!(resolve_log_paths(&[format!("{}/*.json", test_dir)]).is_err());
+    assert!(resolve_log_paths(&[format!("{}/*/nested.log", test_dir)]).is_err());
+    assert!(resolve_log_paths(&[format!("{}/missing.log", test_dir)]).is_err());
+    
+    fs::remove_dir_all(test_dir).unwrap();
+}
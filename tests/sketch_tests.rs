@@ -0,0 +1,101 @@
+use rtools::{DistinctCounter, Reservoir, TopK};
+
+#[test]
+fn test_top_k_exact_within_capacity() {
+    let mut counts = TopK::new(10);
+    for key in ["a", "b", "a", "c", "a", "b"] {
+        counts.add(key);
+    }
+    
+    assert!(counts.is_exact());
+    assert_eq!(counts.top(2), vec![("a".to_string(), 3), ("b".to_string(), 2)]);
+    assert_eq!(counts.estimate("c"), Some(1));
+    assert_eq!(counts.estimate("d"), None);
+}
+
+#[test]
+fn test_top_k_keeps_heavy_hitters() {
+    // 3个高频键混在大量只出现一次的键中
+    let mut counts = TopK::new(20);
+    for i in 0..10_000 {
+        match i % 10 {
+            0 => counts.add("hot"),
+            1 => counts.add("warm"),
+            2 => counts.add("cool"),
+            _ => counts.add(&format!("rare-{}", i)),
+        }
+    }
+    
+    assert!(!counts.is_exact());
+    assert_eq!(counts.len(), 20);
+    let top: Vec<String> = counts.top(3).into_iter().map(|(key, _)| key).collect();
+    assert!(top.contains(&"hot".to_string()) && top.contains(&"warm".to_string()) && top.contains(&"cool".to_string()));
+    
+    // 估计值不小于真实值，偏差不超过记录的误差
+    let estimate = counts.estimate("hot").unwrap();
+    assert!(estimate >= 1000);
+    assert!(estimate - counts.error("hot").unwrap() <= 1000);
+}
+
+#[test]
+fn test_top_k_merge() {
+    let mut left = TopK::new(10);
+    let mut right = TopK::new(10);
+    left.add_count("a", 5);
+    right.add_count("a", 2);
+    right.add_count("b", 4);
+    
+    left.merge(&right);
+    assert_eq!(left.top(10), vec![("a".to_string(), 7), ("b".to_string(), 4)]);
+}
+
+#[test]
+fn test_distinct_counter_accuracy() {
+    let mut counter = DistinctCounter::new();
+    assert_eq!(counter.estimate(), 0);
+    
+    for i in 0..100 {
+        counter.add(&format!("user-{}", i % 10));
+    }
+    assert_eq!(counter.estimate(), 10);
+    
+    let mut large = DistinctCounter::new();
+    let mut other = DistinctCounter::new();
+    for i in 0..100_000u32 {
+        if i % 2 == 0 {
+            large.add(&i);
+        } else {
+            other.add(&i);
+        }
+    }
+    large.merge(&other);
+    let estimate = large.estimate() as f64;
+    assert!((estimate - 100_000.0).abs() / 100_000.0 < 0.03, "估计值 {}", estimate);
+}
+
+#[test]
+fn test_reservoir_sampling() {
+    let mut reservoir = Reservoir::new(100);
+    for i in 0..50 {
+        reservoir.add(i as f64);
+    }
+    assert_eq!(reservoir.samples().len(), 50);
+    
+    for i in 50..10_000 {
+        reservoir.add(i as f64);
+    }
+    assert_eq!(reservoir.seen(), 10_000);
+    assert_eq!(reservoir.samples().len(), 100);
+    
+    // 均匀抽样的平均值应接近总体的平均值
+    let mean = reservoir.samples().iter().sum::<f64>() / 100.0;
+    assert!((mean - 5000.0).abs() < 1000.0, "平均值 {}", mean);
+    
+    let mut other = Reservoir::new(100);
+    for i in 0..10_000 {
+        other.add(i as f64);
+    }
+    reservoir.merge(&other);
+    assert_eq!(reservoir.seen(), 20_000);
+    assert_eq!(reservoir.samples().len(), 100);
+}
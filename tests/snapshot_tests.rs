@@ -0,0 +1,115 @@
+use rtools::dirscan::FileInfo;
+use rtools::{scan_directory_with_options, ScanOptions, Snapshot};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+fn take_snapshot(dir: &str) -> Snapshot {
+    take_snapshot_with(dir, true)
+}
+
+fn take_snapshot_with(dir: &str, hash_files: bool) -> Snapshot {
+    let options = ScanOptions::new().with_snapshot(hash_files);
+    let stats = scan_directory_with_options(dir, &options).unwrap();
+    stats.snapshot.unwrap()
+}
+
+#[test]
+fn test_snapshot_save_and_load() {
+    let test_dir = "test_snapshot_dir";
+    let snapshot_file = "test_snapshot.snap";
+    fs::create_dir_all(format!("{}/sub", test_dir)).unwrap();
+    fs::write(format!("{}/a.txt", test_dir), "hello").unwrap();
+    fs::write(format!("{}/sub/b.txt", test_dir), "world!").unwrap();
+
+    let snapshot = take_snapshot(test_dir);
+    assert_eq!(snapshot.entries.len(), 2);
+    assert_eq!(snapshot.total_size(), 11);
+    assert!(snapshot.has_hashes());
+
+    snapshot.save_to_file(snapshot_file).unwrap();
+    let loaded = Snapshot::load_from_file(snapshot_file).unwrap();
+    assert_eq!(loaded.entries, snapshot.entries);
+
+    // 清理测试文件
+    fs::remove_file(snapshot_file).unwrap();
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_snapshot_diff() {
+    let test_dir = "test_snapshot_diff_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    fs::write(format!("{}/keep.txt", test_dir), "unchanged").unwrap();
+    fs::write(format!("{}/edit.txt", test_dir), "before").unwrap();
+    fs::write(format!("{}/delete.txt", test_dir), "to be deleted").unwrap();
+    fs::write(format!("{}/old_name.txt", test_dir), "moving content").unwrap();
+
+    let old = take_snapshot(test_dir);
+
+    fs::write(format!("{}/edit.txt", test_dir), "after edit").unwrap();
+    fs::remove_file(format!("{}/delete.txt", test_dir)).unwrap();
+    fs::rename(format!("{}/old_name.txt", test_dir), format!("{}/new_name.txt", test_dir)).unwrap();
+    fs::write(format!("{}/added.txt", test_dir), "new").unwrap();
+
+    let new = take_snapshot(test_dir);
+    let diff = old.diff(&new);
+
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].path, "added.txt");
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[0].path, "delete.txt");
+    assert_eq!(diff.modified.len(), 1);
+    assert_eq!(diff.modified[0].1.path, "edit.txt");
+    assert_eq!(diff.moved.len(), 1);
+    assert_eq!(diff.moved[0].0.path, "old_name.txt");
+    assert_eq!(diff.moved[0].1.path, "new_name.txt");
+    // "after edit"(+4) + "new"(+3) - "to be deleted"(-13)
+    assert_eq!(diff.size_change, -6);
+
+    // 没有变化时差异为空
+    assert!(new.diff(&new).is_empty());
+
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_snapshot_diff_mixed_hashes() {
+    let test_dir = "test_snapshot_mixed_dir";
+    fs::create_dir_all(test_dir).unwrap();
+    fs::write(format!("{}/old_name.txt", test_dir), "moving content").unwrap();
+
+    // 只有一边记录了摘要时按大小和修改时间识别移动
+    for (old_hashes, new_hashes) in [(false, true), (true, false)] {
+        let old = take_snapshot_with(test_dir, old_hashes);
+        fs::rename(format!("{}/old_name.txt", test_dir), format!("{}/new_name.txt", test_dir)).unwrap();
+        let new = take_snapshot_with(test_dir, new_hashes);
+        
+        let diff = old.diff(&new);
+        assert_eq!(diff.moved.len(), 1);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        
+        fs::rename(format!("{}/new_name.txt", test_dir), format!("{}/old_name.txt", test_dir)).unwrap();
+    }
+
+    fs::remove_dir_all(test_dir).unwrap();
+}
+
+#[test]
+fn test_snapshot_record_unreadable_file() {
+    // 无法读取内容的文件仍然记录，只是没有摘要
+    let mut snapshot = Snapshot::new(Path::new("test_snapshot_missing_dir"));
+    let file_info = FileInfo {
+        name: "gone.txt".to_string(),
+        path: Path::new("test_snapshot_missing_dir/gone.txt").to_path_buf(),
+        size: 3,
+        is_dir: false,
+        modified: SystemTime::now(),
+        extension: Some("txt".to_string()),
+        depth: 1,
+    };
+    assert!(snapshot.record(&file_info, true).is_err());
+    assert_eq!(snapshot.entries.len(), 1);
+    assert_eq!(snapshot.entries[0].path, "gone.txt");
+    assert!(snapshot.entries[0].hash.is_none());
+}